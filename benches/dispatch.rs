@@ -0,0 +1,156 @@
+//! Startup latency of a shimmed command: how long `ls → eza` takes compared
+//! with running the replacement directly. Run with `cargo bench`.
+//!
+//! The binary can't be linked into a bench, so each case spawns it through a
+//! symlink, the way a shell would. The overhead of a case is its latency
+//! minus the baseline's at the same percentile; the budget is 1ms at p99.
+//!
+//! Comparing two builds takes more samples than one look at the budget:
+//! `SHDW_BENCH_RUNS` sets how many each case gets.
+
+#[cfg(unix)]
+fn main() {
+    unix::main();
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("the dispatch benchmark needs symlinks and only runs on Unix");
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    /// Samples per case when `SHDW_BENCH_RUNS` doesn't say.
+    const RUNS: usize = 500;
+    const WARMUP: usize = 20;
+    const BUDGET: Duration = Duration::from_millis(1);
+
+    /// A config with one alias being benchmarked (`true`, shadowing itself)
+    /// and `padding` others, some using templates and extends, so that
+    /// parsing a large config shows up.
+    fn config(padding: usize) -> String {
+        let mut out = String::from(
+            "version = 1\n\n[templates.t]\ncommand = \"true {flags}\"\n\n[aliases.true]\ncommand = \"true\"\n",
+        );
+        for i in 0..padding {
+            let alias = match i % 3 {
+                0 => format!(
+                    "command = \"true --flag-{}\"\ndescription = \"alias {}\"",
+                    i, i
+                ),
+                1 => format!("template = \"t\"\nflags = \"--{}\"", i),
+                _ => format!("extends = \"pad{}\"\nargs = \"--more\"", i - 2),
+            };
+            out.push_str(&format!("\n[aliases.pad{}]\n{}\n", i, alias));
+        }
+        out
+    }
+
+    struct Setup {
+        home: TempDir,
+    }
+
+    impl Setup {
+        fn new(padding: usize) -> Self {
+            let home = tempfile::tempdir().unwrap();
+            let config_dir = home.path().join("config");
+            fs::create_dir_all(&config_dir).unwrap();
+            fs::write(config_dir.join("config.toml"), config(padding)).unwrap();
+            std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_shdw"), home.path().join("true"))
+                .unwrap();
+            Self { home }
+        }
+
+        fn shim(&self) -> Command {
+            let mut command = Command::new(self.home.path().join("true"));
+            command
+                .env("SHDW_CONFIG_DIR", self.home.path().join("config"))
+                .env("SHDW_STATE_DIR", self.home.path().join("state"));
+            command
+        }
+    }
+
+    fn runs() -> usize {
+        std::env::var("SHDW_BENCH_RUNS")
+            .ok()
+            .and_then(|runs| runs.parse().ok())
+            .filter(|runs| *runs > 0)
+            .unwrap_or(RUNS)
+    }
+
+    fn measure(mut command: Command) -> Vec<Duration> {
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+        let runs = runs();
+        let mut samples = Vec::with_capacity(runs);
+        for run in 0..WARMUP + runs {
+            let start = Instant::now();
+            let status = command.status().expect("failed to spawn");
+            let elapsed = start.elapsed();
+            assert!(status.success(), "{:?} failed: {}", command, status);
+            if run >= WARMUP {
+                samples.push(elapsed);
+            }
+        }
+        samples.sort();
+        samples
+    }
+
+    fn percentile(samples: &[Duration], p: usize) -> Duration {
+        samples[(samples.len() * p / 100).min(samples.len() - 1)]
+    }
+
+    fn micros(duration: Duration) -> String {
+        format!("{:>7.1}µs", duration.as_secs_f64() * 1e6)
+    }
+
+    pub fn main() {
+        let true_path = ["/bin/true", "/usr/bin/true"]
+            .into_iter()
+            .find(|path| Path::new(path).exists())
+            .expect("no `true` binary to benchmark against");
+        let small = Setup::new(0);
+        let large = Setup::new(300);
+        let mut raw = small.shim();
+        raw.arg("--raw");
+
+        let baseline = measure(Command::new(true_path));
+        let cases = [
+            ("dispatch, 1 alias", measure(small.shim())),
+            ("dispatch, 301 aliases", measure(large.shim())),
+            ("dispatch --raw, 1 alias", measure(raw)),
+        ];
+
+        println!(
+            "{:<26} {:>9} {:>9} {:>11}",
+            "case", "p50", "p99", "p99 over"
+        );
+        println!(
+            "{:<26} {} {} {:>11}",
+            "true (baseline)",
+            micros(percentile(&baseline, 50)),
+            micros(percentile(&baseline, 99)),
+            "-"
+        );
+        let mut over_budget = false;
+        for (name, samples) in &cases {
+            let overhead = percentile(samples, 99).saturating_sub(percentile(&baseline, 99));
+            over_budget |= overhead > BUDGET;
+            println!(
+                "{:<26} {} {} {}",
+                name,
+                micros(percentile(samples, 50)),
+                micros(percentile(samples, 99)),
+                micros(overhead)
+            );
+        }
+        if over_budget {
+            println!("\nover the {:?} p99 budget", BUDGET);
+        }
+    }
+}
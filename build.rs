@@ -0,0 +1,77 @@
+//! Records where a binary came from, for `shdw --version`: the git commit,
+//! build date, target triple and enabled cargo features.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // Packagers building from a tarball can pass the commit in.
+    println!("cargo:rerun-if-env-changed=SHDW_GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    // HEAD names the branch, so a commit only shows in refs; the sources
+    // and the index are what can make the tree dirty.
+    let watched = [
+        ".git/HEAD",
+        ".git/index",
+        ".git/refs",
+        ".git/packed-refs",
+        "src",
+        "build.rs",
+        "Cargo.toml",
+        "Cargo.lock",
+    ];
+    for path in watched {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    let commit = env::var("SHDW_GIT_COMMIT").ok().or_else(git_commit);
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=SHDW_GIT_COMMIT={}", commit);
+    }
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible.
+    let built = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=SHDW_BUILD_EPOCH={}", built);
+
+    println!(
+        "cargo:rustc-env=SHDW_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_string))
+        .map(|feature| feature.to_lowercase().replace('_', "-"))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=SHDW_FEATURES={}", features.join(","));
+}
+
+/// The short hash of HEAD, marked `-dirty` when the tree has changes.
+fn git_commit() -> Option<String> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let hash = git(&["rev-parse", "--short=12", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Some(match dirty {
+        true => format!("{}-dirty", hash),
+        false => hash,
+    })
+}
@@ -0,0 +1,136 @@
+use crate::which;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Wrapper scripts larger than this aren't simple wrappers.
+const MAX_WRAPPER_SIZE: u64 = 4096;
+
+/// A hand-made symlink or wrapper script that could become an alias.
+pub struct Candidate {
+    pub name: String,
+    pub path: PathBuf,
+    pub command: String,
+    pub kind: &'static str,
+}
+
+/// Files in `dir` that couldn't be understood, with the reason.
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: &'static str,
+}
+
+/// Look through `dir` for symlinks and wrapper scripts whose command can be
+/// inferred. Shadow's own links are ignored.
+pub fn scan(dir: &Path) -> (Vec<Candidate>, Vec<Skipped>) {
+    let mut candidates = Vec::new();
+    let mut skipped = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (candidates, skipped);
+    };
+    let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        // On Windows the extension (.exe, .cmd) isn't part of the command name.
+        let name = match cfg!(windows) {
+            true => path.file_stem(),
+            false => path.file_name(),
+        };
+        let Some(name) = name.map(|n| n.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        if name.starts_with('.') || which::is_shadow(&path) {
+            continue;
+        }
+        match inspect(dir, &path) {
+            Ok(Some((command, kind))) => candidates.push(Candidate {
+                name,
+                path,
+                command,
+                kind,
+            }),
+            Ok(None) => {}
+            Err(reason) => skipped.push(Skipped { path, reason }),
+        }
+    }
+    (candidates, skipped)
+}
+
+fn inspect(dir: &Path, path: &Path) -> Result<Option<(String, &'static str)>, &'static str> {
+    let metadata = path.symlink_metadata().map_err(|_| "unreadable")?;
+    if metadata.is_symlink() {
+        let target = fs::read_link(path).map_err(|_| "unreadable")?;
+        let target = dir.join(target);
+        if !target.exists() {
+            return Err("dangling symlink");
+        }
+        return Ok(Some((target.display().to_string(), "symlink")));
+    }
+    if !metadata.is_file() || !which::is_executable(path) {
+        return Ok(None);
+    }
+    if metadata.len() > MAX_WRAPPER_SIZE {
+        return Err("not a simple wrapper script");
+    }
+    let contents = fs::read_to_string(path).map_err(|_| "not a simple wrapper script")?;
+    match parse_wrapper(&contents) {
+        Some(command) => Ok(Some((command, "wrapper"))),
+        None => Err("not a simple wrapper script"),
+    }
+}
+
+/// The command a shell wrapper like `exec eza --icons "$@"` runs, if the
+/// script does nothing else. Anything needing a shell to interpret (quotes,
+/// pipes, variables) is rejected, since alias commands are split on
+/// whitespace.
+fn parse_wrapper(contents: &str) -> Option<String> {
+    let mut lines = contents.lines();
+    let shebang = lines.next()?;
+    let interpreter = shebang.strip_prefix("#!")?.split_whitespace().last()?;
+    if !matches!(
+        interpreter.rsplit('/').next()?,
+        "sh" | "bash" | "zsh" | "dash" | "ksh"
+    ) {
+        return None;
+    }
+
+    let mut body = lines
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("set -"));
+    let line = body.next()?;
+    if body.next().is_some() {
+        return None;
+    }
+
+    let line = line.strip_prefix("exec ").unwrap_or(line).trim_start();
+    let command = ["\"$@\"", "$@", "\"$*\"", "$*"]
+        .iter()
+        .find_map(|args| line.strip_suffix(args))?
+        .trim();
+    let special = |c: char| "\"'`$\\|&;<>(){}*?[]~".contains(c);
+    match command.is_empty() || command.contains(special) {
+        true => None,
+        false => Some(command.split_whitespace().collect::<Vec<_>>().join(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wrapper() {
+        assert_eq!(
+            parse_wrapper("#!/bin/sh\n# ls with icons\nexec eza --icons \"$@\"\n"),
+            Some("eza --icons".to_string())
+        );
+        assert_eq!(
+            parse_wrapper("#!/usr/bin/env bash\nset -euo pipefail\nbat --plain $@\n"),
+            Some("bat --plain".to_string())
+        );
+        assert_eq!(parse_wrapper("#!/bin/sh\ncd /tmp\nexec ls \"$@\"\n"), None);
+        assert_eq!(parse_wrapper("#!/bin/sh\nexec ls | less \"$@\"\n"), None);
+        assert_eq!(parse_wrapper("#!/bin/sh\nexec ls\n"), None);
+        assert_eq!(parse_wrapper("#!/usr/bin/python3\nprint(1)\n"), None);
+    }
+}
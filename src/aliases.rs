@@ -1,16 +1,55 @@
-use crate::config::Settings;
+//! The domain model: what an alias is, where its shim lives and what a
+//! command line naming it runs.
+//!
+//! - [`Alias`] is one `[aliases.<name>]` table, fully resolved (templates
+//!   filled in, `extends` applied), and knows how to run itself.
+//! - [`Aliases`] is the config's set of them, looked up by name.
+//! - [`LinkState`] is what is actually at an alias' link path on disk,
+//!   which may not be what the config asks for.
+//! - [`Resolution`] is what a shadowed command line resolves to: the
+//!   replacement, or the original and why.
+
+use crate::app_paths;
+use crate::config::{Backend, Settings};
+use crate::depth;
+use crate::duration::HumanDuration;
 use crate::error::{ExitCode, Result, ShadowError};
+use crate::exit_map::ExitMap;
+use crate::hooks;
+use crate::instance::SingleInstance;
+use crate::log;
+use crate::notify;
+use crate::output;
+use crate::pattern::Pattern;
+use crate::redirect::Output;
+use crate::rules::{self, ArgRule};
+use crate::secrets::EnvValue;
+use crate::signals;
+use crate::size::ByteSize;
+use crate::state;
+use crate::stats;
+use crate::tee::Tee;
+use crate::template::Template;
+use crate::umask::Umask;
+use crate::warning;
+use crate::which;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::ffi::{OsStr, OsString};
 use std::fmt;
 use std::fs;
+use std::io;
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{self, Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(Clone, Debug, Default, Serialize)]
+#[derive(Clone, Debug, Default)]
 pub struct Aliases(HashMap<String, Alias>);
 
 impl Aliases {
@@ -69,6 +108,35 @@ impl<'a> IntoIterator for &'a Aliases {
     }
 }
 
+/// Aliases loaded from the config are written back exactly as they were
+/// written, so values they inherit or get from templates aren't expanded
+/// into them.
+impl Serialize for Aliases {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error, SerializeMap};
+
+        // Subscribed and project aliases live in their own files.
+        let mut aliases: Vec<_> = self.0.iter().filter(|(_, alias)| alias.is_own()).collect();
+        aliases.sort_by_key(|(name, _)| *name);
+        let mut map = serializer.serialize_map(Some(aliases.len()))?;
+        for (name, alias) in aliases {
+            match &alias.written {
+                Some(table) => map.serialize_entry(name, table)?,
+                // Through a table too, so its keys come out in the same
+                // (sorted) order as they will once it's been loaded.
+                None => {
+                    let table = toml::Table::try_from(alias).map_err(S::Error::custom)?;
+                    map.serialize_entry(name, &table)?
+                }
+            }
+        }
+        map.end()
+    }
+}
+
 impl<'de> Deserialize<'de> for Aliases {
     fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
     where
@@ -77,7 +145,7 @@ impl<'de> Deserialize<'de> for Aliases {
         let mut map = HashMap::<String, Alias>::deserialize(deserializer)?;
 
         for (key, alias) in map.iter_mut() {
-            alias.name = key.clone();
+            alias.set_name(key);
         }
 
         Ok(Aliases(map))
@@ -88,23 +156,269 @@ impl<'de> Deserialize<'de> for Aliases {
 pub struct Alias {
     #[serde(skip)]
     name: String,
+    /// The command that runs, after any template is applied.
+    #[serde(skip)]
     command: String,
+    /// The command as written in the config, absent for template aliases.
+    #[serde(rename = "command", skip_serializing_if = "Option::is_none")]
+    written_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template: Option<String>,
+    #[serde(flatten)]
+    params: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
+    /// Arguments appended to the command, e.g. one inherited with `extends`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<String>,
+    /// The alias' table as read from config.toml, if it was.
+    #[serde(skip)]
+    written: Option<toml::Table>,
+    /// The subscription the alias comes from, if it isn't your own.
+    #[serde(skip)]
+    subscription: Option<String>,
+    /// Set when the alias comes from `$SHDW_PROJECT_CONFIG`.
+    #[serde(skip)]
+    project: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    /// Longer documentation for `shdw help <name>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    examples: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     bin_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "is_true")]
+    link: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notify_after: Option<HumanDuration>,
+    #[serde(skip_serializing_if = "is_zero")]
+    retries: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_backoff: Option<HumanDuration>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    retry_on: Vec<i32>,
+    #[serde(skip_serializing_if = "ExitMap::is_empty")]
+    exit_map: ExitMap,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    detach: bool,
+    #[serde(skip_serializing_if = "SingleInstance::is_off")]
+    single_instance: SingleInstance,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nice: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limits: Option<Limits>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    umask: Option<Umask>,
+    #[serde(skip_serializing_if = "is_true")]
+    inherit_stdin: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_banner: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failure_footer: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stdout: Option<Output>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr: Option<Output>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tee: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, EnvValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_args: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_args: Option<usize>,
+    /// Must match the arguments joined with spaces.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args_pattern: Option<Pattern>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    arg_rules: Vec<ArgRule>,
+}
+
+/// What a run under `shdw run --capture` printed and how it ended.
+pub struct Captured {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// After `exit_map`; `None` when a signal ended it.
+    pub exit_code: Option<i32>,
+    pub status: ExitStatus,
+    pub duration: Duration,
+}
+
+/// Resource limits applied to the replacement process (Unix only).
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Limits {
+    /// Maximum address space (`RLIMIT_AS`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_memory: Option<ByteSize>,
+    /// Maximum number of open file descriptors (`RLIMIT_NOFILE`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    open_files: Option<u64>,
+    /// Maximum CPU time (`RLIMIT_CPU`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cpu_time: Option<HumanDuration>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+fn is_true(b: &bool) -> bool {
+    *b
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Deserialize)]
 struct AliasDef {
-    command: String,
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    extends: Option<String>,
+    #[serde(default)]
+    args: Option<String>,
+    /// Template parameters, along with any keys shadow doesn't know.
+    #[serde(flatten)]
+    extra: BTreeMap<String, toml::Value>,
     #[serde(default)]
     description: Option<String>,
     #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    examples: Vec<String>,
+    #[serde(default)]
     bin_path: Option<PathBuf>,
+    #[serde(default = "default_true")]
+    link: bool,
+    #[serde(default)]
+    notify_after: Option<HumanDuration>,
+    #[serde(default)]
+    retries: u32,
+    #[serde(default)]
+    retry_backoff: Option<HumanDuration>,
+    #[serde(default)]
+    retry_on: Vec<i32>,
+    #[serde(default)]
+    exit_map: ExitMap,
+    #[serde(default)]
+    detach: bool,
+    #[serde(default)]
+    single_instance: SingleInstance,
+    #[serde(default)]
+    nice: Option<i32>,
+    #[serde(default)]
+    limits: Option<Limits>,
+    #[serde(default)]
+    umask: Option<Umask>,
+    #[serde(default = "default_true")]
+    inherit_stdin: bool,
+    #[serde(default)]
+    help_banner: Option<bool>,
+    #[serde(default)]
+    failure_footer: Option<bool>,
+    #[serde(default)]
+    stats: Option<bool>,
+    #[serde(default)]
+    stdout: Option<Output>,
+    #[serde(default)]
+    stderr: Option<Output>,
+    #[serde(default)]
+    tee: Option<String>,
+    #[serde(default)]
+    env: BTreeMap<String, EnvValue>,
+    #[serde(default)]
+    min_args: Option<usize>,
+    #[serde(default)]
+    max_args: Option<usize>,
+    #[serde(default)]
+    args_pattern: Option<Pattern>,
+    #[serde(default)]
+    arg_rules: Vec<ArgRule>,
+}
+
+/// Builds an [`Alias`] for the commands that create one (`add`, `adopt`,
+/// `import`, `suggest`), rejecting at [`build`](AliasBuilder::build) what
+/// config validation would reject later anyway.
+#[derive(Debug)]
+pub struct AliasBuilder {
+    name: String,
+    command: String,
+    description: Option<String>,
+    bin_path: Option<PathBuf>,
+    link: bool,
+}
+
+impl AliasBuilder {
+    pub fn description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Link it somewhere other than settings.bin_path.
+    pub fn bin_path(mut self, bin_path: Option<PathBuf>) -> Self {
+        self.bin_path = bin_path;
+        self
+    }
+
+    pub fn link(mut self, link: bool) -> Self {
+        self.link = link;
+        self
+    }
+
+    pub fn build(self) -> Result<Alias> {
+        if let Some(problem) = name_problem(&self.name) {
+            return Err(ShadowError::InvalidAlias(problem));
+        }
+        if self.command.split_whitespace().next().is_none() {
+            return Err(ShadowError::InvalidAlias(format!(
+                "Alias '{}' has an empty command",
+                self.name
+            )));
+        }
+        Ok(
+            Alias::new(self.name, self.command, self.description, self.bin_path)
+                .with_link(self.link),
+        )
+    }
+}
+
+/// Whether `name` can stand as a file name of its own in some directory,
+/// rather than reach into another.
+pub fn is_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\'])
+}
+
+/// Why `name` can't be an alias, if it can't: it has to be usable as a file
+/// name in the bin directory, and can't take over shdw itself.
+pub fn name_problem(name: &str) -> Option<String> {
+    if !is_file_name(name) {
+        return Some(format!("Invalid alias name: '{}'", name));
+    }
+    if name == "shdw" {
+        return Some("An alias cannot be named 'shdw'".to_string());
+    }
+    None
 }
 
 impl Alias {
+    /// Start building an alias running `command` in place of `name`.
+    pub fn builder(name: impl Into<String>, command: impl Into<String>) -> AliasBuilder {
+        AliasBuilder {
+            name: name.into(),
+            command: command.into(),
+            description: None,
+            bin_path: None,
+            link: true,
+        }
+    }
+
     pub fn new(
         name: String,
         command: String,
@@ -113,12 +427,138 @@ impl Alias {
     ) -> Self {
         Self {
             name,
+            written_command: Some(command.clone()),
             command,
+            template: None,
+            params: BTreeMap::new(),
+            extends: None,
+            args: None,
+            written: None,
+            subscription: None,
+            project: false,
             description,
+            notes: None,
+            examples: Vec::new(),
             bin_path,
+            link: true,
+            notify_after: None,
+            retries: 0,
+            retry_backoff: None,
+            retry_on: Vec::new(),
+            exit_map: ExitMap::default(),
+            detach: false,
+            single_instance: SingleInstance::Off,
+            nice: None,
+            limits: None,
+            umask: None,
+            inherit_stdin: true,
+            help_banner: None,
+            failure_footer: None,
+            stats: None,
+            stdout: None,
+            stderr: None,
+            tee: None,
+            env: BTreeMap::new(),
+            min_args: None,
+            max_args: None,
+            args_pattern: None,
+            arg_rules: Vec::new(),
         }
     }
 
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn subscription(&self) -> Option<&str> {
+        self.subscription.as_deref()
+    }
+
+    /// This alias, as one from subscription `name`.
+    pub fn subscribed(mut self, name: &str) -> Self {
+        self.subscription = Some(name.to_string());
+        self
+    }
+
+    pub fn is_project(&self) -> bool {
+        self.project
+    }
+
+    /// This alias, as one from the project's config.
+    pub fn projected(mut self) -> Self {
+        self.project = true;
+        self
+    }
+
+    /// Whether the alias is saved in config.toml, rather than coming from a
+    /// subscription or the project's config.
+    pub fn is_own(&self) -> bool {
+        self.subscription.is_none() && !self.project
+    }
+
+    /// This alias with templates, `extends` and `args` resolved into a plain
+    /// command, as older versions of shdw understand it.
+    pub fn frozen(&self) -> Alias {
+        Alias {
+            written_command: Some(self.command.clone()),
+            template: None,
+            params: BTreeMap::new(),
+            extends: None,
+            args: None,
+            written: None,
+            ..self.clone()
+        }
+    }
+
+    /// The template the alias' command comes from, if any.
+    pub fn template(&self) -> Option<&str> {
+        self.template.as_deref()
+    }
+
+    /// The alias this one inherits from.
+    pub fn extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+
+    /// The alias this one extends in the config, which is still known once
+    /// what it inherits has been merged in.
+    pub fn parent(&self) -> Option<&str> {
+        self.extends
+            .as_deref()
+            .or_else(|| self.written.as_ref()?.get("extends")?.as_str())
+    }
+
+    pub fn written(&self) -> Option<&toml::Table> {
+        self.written.as_ref()
+    }
+
+    /// Fill in the command from the alias' template, if it uses one, and
+    /// append `args`.
+    pub fn apply_template(&mut self, templates: &BTreeMap<String, Template>) -> Result<()> {
+        let error = |message: String| {
+            ShadowError::ConfigError(format!("Alias '{}': {}", self.name, message))
+        };
+        match (&self.template, &self.written_command) {
+            (Some(_), Some(_)) => {
+                return Err(error("sets both a command and a template".to_string()))
+            }
+            (Some(name), None) => {
+                let template = templates
+                    .get(name)
+                    .ok_or_else(|| error(format!("unknown template '{}'", name)))?;
+                self.command = template
+                    .expand(&self.params)
+                    .map_err(|e| error(format!("template '{}': {}", name, e)))?;
+            }
+            (None, Some(_)) => {}
+            (None, None) => return Err(error("needs a command or a template".to_string())),
+        }
+        if let Some(args) = &self.args {
+            self.command = format!("{} {}", self.command, args.trim());
+        }
+        Ok(())
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -127,14 +567,103 @@ impl Alias {
         &self.command
     }
 
+    /// The command before `args` are added; absent for aliases using a
+    /// template.
+    pub fn written_command(&self) -> Option<&str> {
+        self.written_command.as_deref()
+    }
+
+    /// Replace the command the alias writes, keeping its `args`.
+    pub fn set_command(&mut self, command: &str) {
+        self.command = match &self.args {
+            Some(args) => format!("{} {}", command, args.trim()),
+            None => command.to_string(),
+        };
+        self.written_command = Some(command.to_string());
+        if let Some(table) = &mut self.written {
+            table.insert("command".to_string(), toml::Value::from(command));
+        }
+    }
+
+    /// The executable the command runs, i.e. its first word.
+    pub fn program(&self) -> Option<&str> {
+        self.command.split_whitespace().next()
+    }
+
+    /// Environment variables set for the replacement (not with `--raw`).
+    pub fn env(&self) -> &BTreeMap<String, EnvValue> {
+        &self.env
+    }
+
     pub fn description(&self) -> Option<&str> {
         self.description.as_deref()
     }
 
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    pub fn examples(&self) -> &[String] {
+        &self.examples
+    }
+
     pub fn bin_path(&self) -> &Option<PathBuf> {
         &self.bin_path
     }
 
+    /// The alias' own `help_banner`, over `[settings]`' (see
+    /// [`crate::effective`]), and likewise `failure_footer` and `stats`.
+    pub fn help_banner(&self) -> Option<bool> {
+        self.help_banner
+    }
+
+    pub fn failure_footer(&self) -> Option<bool> {
+        self.failure_footer
+    }
+
+    pub fn stats(&self) -> Option<bool> {
+        self.stats
+    }
+
+    /// Whether the alias gets a symlink. Unlinked aliases shadow nothing and
+    /// only run through `shdw run`.
+    pub fn links(&self) -> bool {
+        self.link
+    }
+
+    /// Whether the alias gets a symlink under the configured backend; with
+    /// `backend = "shell"` it's a shell function instead.
+    pub fn wants_symlink(&self, settings: &Settings) -> bool {
+        self.link && settings.backend() == Backend::Symlink
+    }
+
+    /// `min_args` and `max_args`.
+    pub fn arg_bounds(&self) -> (Option<usize>, Option<usize>) {
+        (self.min_args, self.max_args)
+    }
+
+    pub fn with_bin_path(mut self, bin_path: Option<PathBuf>) -> Self {
+        self.bin_path = bin_path;
+        self
+    }
+
+    pub fn with_link(mut self, link: bool) -> Self {
+        self.link = link;
+        self
+    }
+
+    /// The directory this alias' symlink lives in.
+    pub fn link_dir<'a>(&'a self, settings: &'a Settings) -> &'a Path {
+        self.bin_path
+            .as_deref()
+            .unwrap_or_else(|| settings.bin_path())
+    }
+
+    /// Where this alias' symlink goes.
+    pub fn link_file(&self, settings: &Settings) -> PathBuf {
+        self.link_path(self.link_dir(settings))
+    }
+
     fn link_path(&self, bin_path: &Path) -> PathBuf {
         let link_name = if cfg!(windows) {
             format!("{}.exe", self.name)
@@ -144,65 +673,573 @@ impl Alias {
         bin_path.join(link_name)
     }
 
-    pub fn execute(&self, args: &[String], raw: bool) -> ExitCode {
+    pub fn execute<S: AsRef<OsStr>>(&self, args: &[S], raw: bool) -> ExitCode {
+        state::record_use(&self.name);
+        match self.process(args, raw) {
+            Ok((command, program)) => self.run(command, &program),
+            Err(code) => code,
+        }
+    }
+
+    /// Run like `execute`, but with output collected rather than shown, for
+    /// `shdw run --capture`. Redirects, `tee`, `detach` and retries don't
+    /// apply; stdin is still the terminal's, unless `inherit_stdin = false`.
+    pub fn capture<S: AsRef<OsStr>>(
+        &self,
+        args: &[S],
+        raw: bool,
+    ) -> std::result::Result<Captured, ExitCode> {
+        state::record_use(&self.name);
+        let (mut command, program) = self.process(args, raw)?;
+        self.apply_priority(&mut command);
+        let _guard = self.single_instance.acquire(&self.name)?;
+
+        let started = Instant::now();
+        let stdin = match self.inherit_stdin {
+            true => Stdio::inherit(),
+            false => Stdio::null(),
+        };
+        let output = command.stdin(stdin).output().map_err(|e| {
+            log::error(format!("Failed to execute {}: {}", program, e));
+            ExitCode::CommandFailed
+        })?;
+        Ok(Captured {
+            stdout: output.stdout,
+            stderr: output.stderr,
+            exit_code: output
+                .status
+                .code()
+                .map(|code| self.exit_map.get(code).unwrap_or(code)),
+            status: output.status,
+            duration: started.elapsed(),
+        })
+    }
+
+    /// The command running this alias with `args` starts, and the name to
+    /// report it by.
+    fn process<S: AsRef<OsStr>>(
+        &self,
+        args: &[S],
+        raw: bool,
+    ) -> std::result::Result<(Command, String), ExitCode> {
         if raw {
-            self.execute_original(args)
+            self.original_command(args)
         } else {
-            self.execute_command(args)
+            self.replacement_command(args)
         }
     }
 
-    fn execute_original(&self, args: &[String]) -> ExitCode {
-        match Command::new(&self.name).args(args).status() {
-            Ok(status) => match status.code() {
-                Some(0) => ExitCode::Success,
-                Some(_) => ExitCode::CommandFailed,
-                None => ExitCode::CommandFailed,
-            },
-            Err(e) => {
-                eprintln!("Failed to execute {}: {}", self.name, e);
-                ExitCode::CommandFailed
+    /// What running this alias' shim with `args` runs; `raw` as for
+    /// `--raw`. Aliases with `link = false` have no shim, so their name
+    /// resolves to the original.
+    pub fn resolve<S: AsRef<OsStr>>(&self, args: &[S], raw: bool) -> Resolution {
+        let reason = match (self.links(), raw) {
+            (false, _) => "has link = false",
+            (true, true) => "runs the original with --raw",
+            (true, false) => return Resolution::Replacement(self.argv(args)),
+        };
+        let program = self
+            .original()
+            .map(PathBuf::into_os_string)
+            .unwrap_or_else(|| OsString::from(&self.name));
+        Resolution::original(program, args, reason)
+    }
+
+    /// The command this alias hides, which `--raw` runs. The shim itself
+    /// usually comes first on PATH, so it's skipped.
+    pub fn original(&self) -> Option<PathBuf> {
+        which::find_all(&self.name)
+            .into_iter()
+            .find(|path| !which::is_shadow(path))
+    }
+
+    fn original_command<S: AsRef<OsStr>>(
+        &self,
+        args: &[S],
+    ) -> std::result::Result<(Command, String), ExitCode> {
+        let Some(original) = self.original() else {
+            log::error(format!("No original {} found on PATH", self.name));
+            return Err(ExitCode::CommandNotFound);
+        };
+        let mut command = Command::new(original);
+        command.args(args);
+        Ok((command, self.name.clone()))
+    }
+
+    fn replacement_command<S: AsRef<OsStr>>(
+        &self,
+        args: &[S],
+    ) -> std::result::Result<(Command, String), ExitCode> {
+        if let Err(problem) = self.check_args(args) {
+            log::error(format!("{}: {}", self.name, problem));
+            if let Some(description) = &self.description {
+                log::info(format!("{}: {}", self.name, description));
+            }
+            return Err(ExitCode::InvalidArguments);
+        }
+        if self.program().is_none() {
+            log::error(format!("Invalid command: {}", self.command));
+            return Err(ExitCode::InvalidArguments);
+        }
+        let argv = self.argv(args);
+        let cmd = argv[0].to_string_lossy().into_owned();
+
+        let mut command = Command::new(&argv[0]);
+        command.args(&argv[1..]);
+        if let Err(problem) = depth::enter(&mut command, &self.name) {
+            log::error(format!("{}: {}", self.name, problem));
+            return Err(ExitCode::InvalidArguments);
+        }
+        for (name, value) in &self.env {
+            match value.resolve() {
+                Ok(value) => {
+                    command.env(name, value);
+                }
+                Err(e) => {
+                    log::error(format!(
+                        "Failed to resolve {} for {}: {}",
+                        name, self.name, e
+                    ));
+                    return Err(ExitCode::GeneralError);
+                }
             }
         }
+        Ok((command, cmd))
+    }
+
+    /// The first option set on this alias that only a shim can apply, if
+    /// any: a shell alias or abbreviation just types out the command.
+    pub fn shim_only(&self) -> Option<&'static str> {
+        [
+            ("env", !self.env.is_empty()),
+            ("arg_rules", !self.arg_rules.is_empty()),
+            ("min_args", self.min_args.is_some()),
+            ("max_args", self.max_args.is_some()),
+            ("args_pattern", self.args_pattern.is_some()),
+            ("notify_after", self.notify_after.is_some()),
+            ("retries", self.retries > 0),
+            ("exit_map", !self.exit_map.is_empty()),
+            ("detach", self.detach),
+            ("single_instance", !self.single_instance.is_off()),
+            ("nice", self.nice.is_some()),
+            ("limits", self.limits.is_some()),
+            ("umask", self.umask.is_some()),
+            ("inherit_stdin", !self.inherit_stdin),
+            ("stdout", self.stdout.is_some()),
+            ("stderr", self.stderr.is_some()),
+            ("tee", self.tee.is_some()),
+        ]
+        .into_iter()
+        .find_map(|(option, set)| set.then_some(option))
+    }
+
+    /// What the replacement runs as for `args`: the command split on
+    /// whitespace, whatever `arg_rules` add, then `args`.
+    pub fn argv<S: AsRef<OsStr>>(&self, args: &[S]) -> Vec<OsString> {
+        self.command
+            .split_whitespace()
+            .map(OsString::from)
+            .chain(
+                rules::extra_args(&self.arg_rules, args)
+                    .into_iter()
+                    .map(OsString::from),
+            )
+            .chain(args.iter().map(|arg| arg.as_ref().to_os_string()))
+            .collect()
     }
 
-    fn execute_command(&self, args: &[String]) -> ExitCode {
-        let parts: Vec<&str> = self.command.split_whitespace().collect();
-        let (cmd, base_args) = match parts.split_first() {
-            Some(parts) => parts,
-            None => {
-                eprintln!("Invalid command: {}", self.command);
-                return ExitCode::InvalidArguments;
+    /// Whether `args` satisfy `min_args`, `max_args` and `args_pattern`, so
+    /// a shim around a destructive command never passes it garbage.
+    fn check_args<S: AsRef<OsStr>>(&self, args: &[S]) -> std::result::Result<(), String> {
+        let count = args.len();
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        match (self.min_args, self.max_args) {
+            (Some(min), Some(max)) if count < min || count > max => {
+                return Err(match min == max {
+                    true => format!("takes {} argument{}, got {}", min, plural(min), count),
+                    false => format!("takes {} to {} arguments, got {}", min, max, count),
+                });
             }
+            (Some(min), None) if count < min => {
+                return Err(format!(
+                    "takes at least {} argument{}, got {}",
+                    min,
+                    plural(min),
+                    count
+                ));
+            }
+            (None, Some(max)) if count > max => {
+                return Err(format!(
+                    "takes at most {} argument{}, got {}",
+                    max,
+                    plural(max),
+                    count
+                ));
+            }
+            _ => {}
+        }
+        if let Some(pattern) = &self.args_pattern {
+            let joined: Vec<_> = args
+                .iter()
+                .map(|arg| arg.as_ref().to_string_lossy())
+                .collect();
+            if !pattern.is_match(&joined.join(" ")) {
+                return Err(format!("arguments must match {}", pattern));
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&self, mut command: Command, program: &str) -> ExitCode {
+        self.apply_priority(&mut command);
+
+        if self.detach {
+            return self.run_detached(command, program);
+        }
+
+        if let Err(e) = self.apply_redirects(&mut command) {
+            log::error(format!("Failed to redirect output of {}: {}", self.name, e));
+            return ExitCode::GeneralError;
+        }
+
+        // Held until the last attempt finishes.
+        let _guard = match self.single_instance.acquire(&self.name) {
+            Ok(guard) => guard,
+            Err(code) => return code,
         };
 
-        let all_args: Vec<String> = base_args
-            .iter()
-            .map(|&s| s.to_string())
-            .chain(args.iter().cloned())
-            .collect();
+        let tee = match self.tee.as_deref().map(|path| Tee::open(path, &self.name)) {
+            Some(Ok(tee)) => {
+                // Streams redirected elsewhere stay that way.
+                if self.stdout.is_none() {
+                    command.stdout(Stdio::piped());
+                }
+                if self.stderr.is_none() {
+                    command.stderr(Stdio::piped());
+                }
+                Some(tee)
+            }
+            Some(Err(e)) => {
+                log::error(format!("Failed to open tee log for {}: {}", self.name, e));
+                return ExitCode::GeneralError;
+            }
+            None => None,
+        };
+
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let status = command.spawn().and_then(|mut child| {
+                let copiers = tee.as_ref().map(|tee| tee.attach(&mut child));
+                let status = match self.notify_after {
+                    Some(threshold) => self.wait_with_notify(&mut child, threshold.as_duration()),
+                    None => signals::wait(&mut child),
+                };
+                for copier in copiers.into_iter().flatten() {
+                    let _ = copier.join();
+                }
+                status
+            });
+
+            return match status {
+                Ok(status) if self.should_retry(attempt, &status) => {
+                    let delay = self.retry_delay(attempt);
+                    attempt += 1;
+                    log::info(format!(
+                        "{} exited with {}, retrying in {} ({}/{})",
+                        program,
+                        status,
+                        HumanDuration::from(delay),
+                        attempt,
+                        self.retries
+                    ));
+                    thread::sleep(delay);
+                    continue;
+                }
+                Ok(status) => {
+                    let mapped = status.code().and_then(|code| self.exit_map.get(code));
+                    let failed = !status.success() && mapped != Some(0);
+                    stats::record(&self.name, failed, started.elapsed());
+                    if failed && FAILURE_FOOTER.load(Ordering::Relaxed) {
+                        self.print_failure_footer(program, &status);
+                    }
+                    self.exit_code(&status)
+                }
+                Err(e) => {
+                    log::error(format!("Failed to execute {}: {}", program, e));
+                    stats::record(&self.name, true, started.elapsed());
+                    if e.kind() == io::ErrorKind::NotFound {
+                        hooks::missing(&self.name);
+                    }
+                    ExitCode::CommandFailed
+                }
+            };
+        }
+    }
+
+    /// Lower (or raise) the child's scheduling priority, cap its resources
+    /// and set its umask right before exec, so none of it affects shadow
+    /// itself.
+    #[cfg(unix)]
+    fn apply_priority(&self, command: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        let nice = self.nice;
+        let limits = self.limits.unwrap_or_default();
+        let umask = self.umask;
+        if nice.is_none()
+            && umask.is_none()
+            && limits.max_memory.is_none()
+            && limits.open_files.is_none()
+            && limits.cpu_time.is_none()
+        {
+            return;
+        }
+
+        // SAFETY: the hook only calls async-signal-safe libc functions.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(umask) = umask {
+                    libc::umask(umask.bits() as libc::mode_t);
+                }
+                if let Some(nice) = nice {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                if let Some(size) = limits.max_memory {
+                    set_rlimit(libc::RLIMIT_AS, size.bytes())?;
+                }
+                if let Some(files) = limits.open_files {
+                    set_rlimit(libc::RLIMIT_NOFILE, files)?;
+                }
+                if let Some(cpu) = limits.cpu_time {
+                    set_rlimit(libc::RLIMIT_CPU, cpu.as_duration().as_secs().max(1))?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn apply_priority(&self, _command: &mut Command) {
+        if self.nice.is_some() || self.limits.is_some() || self.umask.is_some() {
+            warning::warn(format!(
+                "nice, limits and umask are not supported on this platform, ignoring for {}",
+                self.name
+            ));
+        }
+    }
 
-        match Command::new(cmd).args(all_args).status() {
-            Ok(status) => match status.code() {
-                Some(0) => ExitCode::Success,
-                Some(_) => ExitCode::CommandFailed,
-                None => ExitCode::CommandFailed,
-            },
+    /// Point stdout/stderr wherever the alias configures them.
+    fn apply_redirects(&self, command: &mut Command) -> std::io::Result<()> {
+        if !self.inherit_stdin {
+            command.stdin(Stdio::null());
+        }
+        if let Some(stdout) = &self.stdout {
+            command.stdout(stdout.stdio()?);
+        }
+        if let Some(stderr) = &self.stderr {
+            command.stderr(stderr.stdio()?);
+        }
+        Ok(())
+    }
+
+    /// Spawn `command` in its own session with stdio going to a log file and
+    /// return without waiting for it.
+    fn run_detached(&self, mut command: Command, program: &str) -> ExitCode {
+        let log_path = state::log_dir().join(format!("{}.log", self.name));
+        let log = fs::create_dir_all(state::log_dir()).and_then(|_| {
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log_path)
+        });
+        let log = match log {
+            Ok(log) => log,
+            Err(e) => {
+                log::error(format!(
+                    "Failed to open log file {}: {}",
+                    log_path.display(),
+                    e
+                ));
+                return ExitCode::GeneralError;
+            }
+        };
+        let stderr = match log.try_clone() {
+            Ok(stderr) => stderr,
             Err(e) => {
-                eprintln!("Failed to execute {}: {}", cmd, e);
+                log::error(format!(
+                    "Failed to open log file {}: {}",
+                    log_path.display(),
+                    e
+                ));
+                return ExitCode::GeneralError;
+            }
+        };
+
+        command.stdin(Stdio::null()).stdout(log).stderr(stderr);
+        if let Err(e) = self.apply_redirects(&mut command) {
+            log::error(format!("Failed to redirect output of {}: {}", self.name, e));
+            return ExitCode::GeneralError;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // SAFETY: setsid is async-signal-safe and touches no parent state.
+            unsafe {
+                command.pre_exec(|| {
+                    if libc::setsid() == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const DETACHED_PROCESS: u32 = 0x0000_0008;
+            const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+            command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+        }
+
+        match command.spawn() {
+            Ok(_) => ExitCode::Success,
+            Err(e) => {
+                log::error(format!("Failed to execute {}: {}", program, e));
+                ExitCode::CommandFailed
+            }
+        }
+    }
+
+    /// `shadowed ls → eza exited 2 (...)`, so whoever sees the failure knows
+    /// it wasn't the `ls` they expected.
+    fn print_failure_footer(&self, program: &str, status: &ExitStatus) {
+        let outcome = match status.code() {
+            Some(code) => format!("exited {}", code),
+            None => "was killed by a signal".to_string(),
+        };
+        eprintln!(
+            "shadowed {} {} {} {} (use --shdw-raw for the original)",
+            self.name,
+            output::terminal().arrow(),
+            program,
+            outcome
+        );
+    }
+
+    /// What shadow exits with after the command ended with `status`. A
+    /// status translated by `exit_map` is exited with straight away: like a
+    /// signal, it's passed on as it is, past any [settings.exit_codes]
+    /// remapping.
+    pub fn exit_code(&self, status: &ExitStatus) -> ExitCode {
+        let translated = status.code().and_then(|code| self.exit_map.get(code));
+        match (status.code(), translated) {
+            (_, Some(0)) | (Some(0), None) => ExitCode::Success,
+            (_, Some(code)) => process::exit(code),
+            (Some(_), None) => ExitCode::CommandFailed,
+            (None, None) => {
+                signals::propagate(status);
                 ExitCode::CommandFailed
             }
         }
     }
 
+    /// Whether run number `attempt` (zero-based), which ended with `status`,
+    /// deserves another attempt. Runs killed by a signal (e.g. Ctrl-C) are
+    /// never retried.
+    fn should_retry(&self, attempt: u32, status: &ExitStatus) -> bool {
+        if attempt >= self.retries {
+            return false;
+        }
+        match status.code() {
+            Some(0) | None => false,
+            Some(code) => self.retry_on.is_empty() || self.retry_on.contains(&code),
+        }
+    }
+
+    /// Delay before retry number `attempt` (zero-based): `retry_backoff`
+    /// doubled after every attempt, or no delay when unset.
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        self.retry_backoff
+            .map(|backoff| backoff.as_duration().saturating_mul(1 << attempt.min(16)))
+            .unwrap_or_default()
+    }
+
+    /// Wait for `child`, notifying once it has been running for `threshold`
+    /// and again when it finishes if it ran at least that long.
+    fn wait_with_notify(
+        &self,
+        child: &mut Child,
+        threshold: Duration,
+    ) -> std::io::Result<ExitStatus> {
+        let started = Instant::now();
+        let (done, finished) = mpsc::channel::<()>();
+        let name = self.name.clone();
+        let watcher = thread::spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = finished.recv_timeout(threshold) {
+                notify::send(
+                    &format!("{} is still running", name),
+                    &format!("Running for over {}", HumanDuration::from(threshold)),
+                );
+            }
+        });
+
+        let status = signals::wait(child);
+        let _ = done.send(());
+        let _ = watcher.join();
+
+        if let Ok(status) = &status {
+            let elapsed = started.elapsed();
+            if elapsed >= threshold {
+                let outcome = match status.code() {
+                    Some(0) => "succeeded".to_string(),
+                    Some(code) => format!("failed with exit status {}", code),
+                    None => "was terminated by a signal".to_string(),
+                };
+                notify::send(
+                    &format!("{} {}", self.name, outcome),
+                    &format!("Finished after {}s", elapsed.as_secs()),
+                );
+            }
+        }
+
+        status
+    }
+
     pub fn create_symlink(&self, settings: &Settings) -> Result<()> {
-        let bin_path = self
-            .bin_path
-            .as_deref()
-            .unwrap_or_else(|| settings.bin_path());
+        self.create_link(settings, false)
+    }
+
+    /// Link this alias to shdw, by a symlink or, with `hard`, a hardlink:
+    /// a second name for the binary itself, which dispatches the same but
+    /// keeps pointing at the old binary when shdw is upgraded in place.
+    /// With `settings.app_paths` on Windows the link is registered too.
+    pub fn create_link(&self, settings: &Settings, hard: bool) -> Result<()> {
+        self.place_link(settings, hard)?;
+        if cfg!(windows) && settings.app_paths() {
+            let link_path = self.link_path(self.link_dir(settings));
+            if let Err(e) = app_paths::register(&link_path) {
+                warning::warn(format!(
+                    "could not register {} under App Paths: {}",
+                    link_path.display(),
+                    e
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn place_link(&self, settings: &Settings, hard: bool) -> Result<()> {
+        let bin_path = self.link_dir(settings);
 
         fs::create_dir_all(bin_path).map_err(|e| {
-            ShadowError::ConfigError(format!("Failed to create bin directory: {}", e))
+            ShadowError::unwritable(bin_path, &e).unwrap_or_else(|| {
+                ShadowError::ConfigError(format!("Failed to create bin directory: {}", e))
+            })
         })?;
 
         let target = env::current_exe().map_err(|e| {
@@ -212,58 +1249,305 @@ impl Alias {
         let link_path = self.link_path(bin_path);
 
         if link_path.exists() {
-            if let Ok(existing_target) = fs::read_link(&link_path) {
-                if existing_target == target {
-                    return Ok(());
+            let is_symlink = link_path
+                .symlink_metadata()
+                .is_ok_and(|metadata| metadata.file_type().is_symlink());
+            // Either kind of link will do unless a hardlink is asked for.
+            let hardlinked = !is_symlink && which::is_shadow(&link_path);
+            let done = match hard {
+                true => hardlinked,
+                false => {
+                    hardlinked || fs::read_link(&link_path).is_ok_and(|existing| existing == target)
+                }
+            };
+            if done {
+                return Ok(());
+            }
+            match is_symlink || which::is_shadow(&link_path) {
+                // A symlink pointing somewhere else only holds a path, and a
+                // hardlink to shdw is only another name for it.
+                true => fs::remove_file(&link_path).map_err(|e| {
+                    ShadowError::unwritable(bin_path, &e).unwrap_or_else(|| {
+                        ShadowError::ConfigError(format!(
+                            "Failed to remove existing symlink: {}",
+                            e
+                        ))
+                    })
+                })?,
+                false => {
+                    let backup = state::divert(&link_path).map_err(|e| {
+                        ShadowError::ConfigError(format!(
+                            "Failed to move {} aside: {}",
+                            link_path.display(),
+                            e
+                        ))
+                    })?;
+                    log::info(format!(
+                        "Moved {} to {}; removing the alias puts it back",
+                        link_path.display(),
+                        backup.display()
+                    ));
                 }
             }
-            // Either not a symlink or points somewhere else, remove it
-            fs::remove_file(&link_path).map_err(|e| {
-                ShadowError::ConfigError(format!("Failed to remove existing symlink: {}", e))
-            })?;
         }
 
-        #[cfg(unix)]
-        std::os::unix::fs::symlink(&target, &link_path)?;
+        let linked = match hard {
+            true => fs::hard_link(&target, &link_path),
+            #[cfg(unix)]
+            false => std::os::unix::fs::symlink(&target, &link_path),
+            #[cfg(windows)]
+            false => std::os::windows::fs::symlink_file(&target, &link_path),
+        };
+        linked.map_err(|e| ShadowError::unwritable(bin_path, &e).unwrap_or(e.into()))
+    }
 
-        #[cfg(windows)]
-        std::os::windows::fs::symlink_file(&target, &link_path)?;
+    /// What is currently at this alias' link path.
+    pub fn link_state(&self, settings: &Settings) -> LinkState {
+        let link_path = self.link_path(self.link_dir(settings));
+        let Ok(target) = fs::read_link(&link_path) else {
+            return match link_path.symlink_metadata() {
+                Ok(_) if which::is_shadow(&link_path) => LinkState::Linked(link_path),
+                Ok(_) => LinkState::NotASymlink(link_path),
+                Err(_) => LinkState::Missing(link_path),
+            };
+        };
 
-        Ok(())
+        let ours = env::current_exe()
+            .and_then(fs::canonicalize)
+            .ok()
+            .zip(fs::canonicalize(&link_path).ok())
+            .is_some_and(|(exe, resolved)| exe == resolved);
+        match ours {
+            true => LinkState::Linked(link_path),
+            false => LinkState::Foreign(link_path, target),
+        }
     }
 
     pub fn remove_symlink(&self, settings: &Settings) -> Result<()> {
-        let bin_path = self
-            .bin_path
-            .as_deref()
-            .unwrap_or_else(|| settings.bin_path());
+        let bin_path = self.link_dir(settings);
         let link_path = self.link_path(bin_path);
 
-        if link_path.exists() {
+        // `exists` follows the link, so it misses dangling ones.
+        if link_path.symlink_metadata().is_ok() {
             fs::remove_file(&link_path)?;
         }
+        restore_diverted(&link_path);
+        unregister_app_path(&link_path);
         Ok(())
     }
 }
 
+static FAILURE_FOOTER: AtomicBool = AtomicBool::new(false);
+
+/// Have a failing replacement followed by a line saying what ran instead of
+/// the command, as `settings.failure_footer` asks.
+pub fn show_failure_footer() {
+    FAILURE_FOOTER.store(true, Ordering::Relaxed);
+}
+
+/// On Windows, drop the App Paths entry made for the link at `path`, whatever
+/// `settings.app_paths` says now.
+pub fn unregister_app_path(path: &Path) {
+    if cfg!(windows) {
+        if let Err(e) = app_paths::unregister(path) {
+            warning::warn(format!(
+                "could not remove {} from App Paths: {}",
+                path.display(),
+                e
+            ));
+        }
+    }
+}
+
+/// Put back the file the link at `path` displaced, if any, saying so.
+pub fn restore_diverted(path: &Path) {
+    match state::restore(path) {
+        Ok(Some(backup)) => log::info(format!(
+            "Restored {} from {}",
+            path.display(),
+            backup.display()
+        )),
+        Ok(None) => {}
+        Err(e) => warning::warn(format!(
+            "could not restore {}, which is still in {}: {}",
+            path.display(),
+            state::file("diverted").display(),
+            e
+        )),
+    }
+}
+
+/// What a command line resolves to, for `shdw resolve`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    /// The replacement's full argv.
+    Replacement(Vec<OsString>),
+    /// The command itself, by path when it's on PATH, and why no shadow
+    /// answers it.
+    Original {
+        argv: Vec<OsString>,
+        reason: &'static str,
+    },
+}
+
+impl Resolution {
+    pub fn original<S: AsRef<OsStr>>(program: OsString, args: &[S], reason: &'static str) -> Self {
+        let argv = std::iter::once(program)
+            .chain(args.iter().map(|arg| arg.as_ref().to_os_string()))
+            .collect();
+        Resolution::Original { argv, reason }
+    }
+
+    pub fn argv(&self) -> &[OsString] {
+        match self {
+            Resolution::Replacement(argv) | Resolution::Original { argv, .. } => argv,
+        }
+    }
+
+    /// Why the original runs; `None` when a shadow answers.
+    pub fn reason(&self) -> Option<&'static str> {
+        match self {
+            Resolution::Replacement(_) => None,
+            Resolution::Original { reason, .. } => Some(reason),
+        }
+    }
+}
+
+/// The state of an alias' symlink on disk.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkState {
+    /// The link exists and points at this shadow binary, or is a hardlink
+    /// to it.
+    Linked(PathBuf),
+    /// Nothing exists at the link path.
+    Missing(PathBuf),
+    /// A symlink to something else (or a dangling one) is in the way.
+    Foreign(PathBuf, PathBuf),
+    /// A regular file or directory occupies the link path.
+    NotASymlink(PathBuf),
+}
+
+impl fmt::Display for LinkState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkState::Linked(path) => write!(f, "linked at {}", path.display()),
+            LinkState::Missing(path) => write!(f, "symlink missing at {}", path.display()),
+            LinkState::Foreign(path, target) => write!(
+                f,
+                "{} points to {} instead of shadow",
+                path.display(),
+                target.display()
+            ),
+            LinkState::NotASymlink(path) => {
+                write!(f, "{} exists but is not a symlink", path.display())
+            }
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+#[cfg(unix)]
+fn set_rlimit(resource: RlimitResource, value: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call.
+    match unsafe { libc::setrlimit(resource, &limit) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
 impl<'de> Deserialize<'de> for Alias {
     fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let def = AliasDef::deserialize(deserializer)?;
+        let table = toml::Table::deserialize(deserializer)?;
+        Alias::from_table(table.clone(), table).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Alias {
+    /// Build an alias from its resolved table (with anything it `extends`
+    /// merged in), remembering the table it was `written` as.
+    pub fn from_table(
+        table: toml::Table,
+        written: toml::Table,
+    ) -> std::result::Result<Self, toml::de::Error> {
+        use serde::de::Error;
+
+        let def: AliasDef = table.try_into()?;
+        let params = match def.template {
+            Some(_) => def
+                .extra
+                .into_iter()
+                .map(|(key, value)| match value {
+                    toml::Value::String(value) => Ok((key, value)),
+                    _ => Err(toml::de::Error::custom(format!(
+                        "template parameter '{}' must be a string",
+                        key
+                    ))),
+                })
+                .collect::<std::result::Result<_, _>>()?,
+            None => BTreeMap::new(),
+        };
         Ok(Alias {
             name: String::new(),
-            command: def.command,
+            command: def.command.clone().unwrap_or_default(),
+            written_command: def.command,
+            template: def.template,
+            params,
+            extends: def.extends,
+            args: def.args,
+            written: Some(written),
+            subscription: None,
+            project: false,
             description: def.description,
+            notes: def.notes,
+            examples: def.examples,
             bin_path: def.bin_path,
+            link: def.link,
+            notify_after: def.notify_after,
+            retries: def.retries,
+            retry_backoff: def.retry_backoff,
+            retry_on: def.retry_on,
+            exit_map: def.exit_map,
+            detach: def.detach,
+            single_instance: def.single_instance,
+            nice: def.nice,
+            limits: def.limits,
+            umask: def.umask,
+            inherit_stdin: def.inherit_stdin,
+            help_banner: def.help_banner,
+            failure_footer: def.failure_footer,
+            stats: def.stats,
+            stdout: def.stdout,
+            stderr: def.stderr,
+            tee: def.tee,
+            env: def.env,
+            min_args: def.min_args,
+            max_args: def.max_args,
+            args_pattern: def.args_pattern,
+            arg_rules: def.arg_rules,
         })
     }
 }
 
 impl fmt::Display for Alias {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} → {}", self.name, self.command)?;
+        write!(
+            f,
+            "{} {} {}",
+            self.name,
+            output::terminal().arrow(),
+            self.command
+        )?;
 
         if let Some(desc) = &self.description {
             write!(f, " ({})", desc)?;
@@ -273,6 +1557,101 @@ impl fmt::Display for Alias {
             write!(f, " [in {}]", path.display())?;
         }
 
+        if !self.link {
+            write!(f, " [not linked]")?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let alias = Alias::new(
+            "shdw-test-ls".to_string(),
+            "eza --icons".to_string(),
+            None,
+            None,
+        );
+        let argv = |resolution: Resolution| -> Vec<String> {
+            resolution
+                .argv()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        let replacement = alias.resolve(&["-l"], false);
+        assert_eq!(replacement.reason(), None);
+        assert_eq!(argv(replacement), ["eza", "--icons", "-l"]);
+
+        let raw = alias.resolve(&["-l"], true);
+        assert_eq!(raw.reason(), Some("runs the original with --raw"));
+        assert_eq!(argv(raw), ["shdw-test-ls", "-l"]);
+
+        let unlinked = alias.with_link(false).resolve::<&str>(&[], false);
+        assert_eq!(unlinked.reason(), Some("has link = false"));
+    }
+
+    #[test]
+    fn test_builder_validates() {
+        let alias = Alias::builder("ls", "eza --icons")
+            .description(Some("ls with icons".to_string()))
+            .link(false)
+            .build()
+            .unwrap();
+        assert_eq!(alias.command(), "eza --icons");
+        assert!(!alias.links());
+
+        assert!(Alias::builder("ls", "  ").build().is_err());
+        assert!(Alias::builder("bin/ls", "eza").build().is_err());
+        assert!(Alias::builder("shdw", "eza").build().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_should_retry() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let exited = |code: i32| ExitStatus::from_raw(code << 8);
+        let alias = Alias::new("tool".to_string(), "tool".to_string(), None, None);
+        assert!(!alias.should_retry(0, &exited(1)));
+
+        let any = Alias {
+            retries: 2,
+            ..alias.clone()
+        };
+        assert!(any.should_retry(0, &exited(1)));
+        assert!(any.should_retry(1, &exited(75)));
+        assert!(!any.should_retry(2, &exited(1)));
+        assert!(!any.should_retry(0, &exited(0)));
+        // SIGINT
+        assert!(!any.should_retry(0, &ExitStatus::from_raw(2)));
+
+        let some = Alias {
+            retry_on: vec![75],
+            ..any
+        };
+        assert!(some.should_retry(0, &exited(75)));
+        assert!(!some.should_retry(0, &exited(1)));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let alias = Alias::new("tool".to_string(), "tool".to_string(), None, None);
+        assert_eq!(alias.retry_delay(3), Duration::ZERO);
+
+        let backoff = Alias {
+            retry_backoff: Some(Duration::from_millis(100).into()),
+            ..alias
+        };
+        let delays: Vec<_> = (0..4).map(|attempt| backoff.retry_delay(attempt)).collect();
+        assert_eq!(delays, [100, 200, 400, 800].map(Duration::from_millis));
+        // The doubling stops somewhere rather than overflowing.
+        assert_eq!(backoff.retry_delay(40), backoff.retry_delay(16));
+    }
+}
@@ -0,0 +1,85 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Where Windows looks up bare names typed into Win+R or `start`, and some
+/// launchers with it, before (or instead of) PATH. Entries are named after
+/// the executable, `ls.exe`, with the full path as their default value.
+const KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\App Paths";
+
+fn key(file_name: &str) -> String {
+    format!(r"{}\{}", KEY, file_name)
+}
+
+/// Make `link`, a shim, what its file name resolves to for the current user,
+/// with `settings.app_paths`.
+pub fn register(link: &Path) -> io::Result<()> {
+    let Some(file_name) = link.file_name().and_then(|name| name.to_str()) else {
+        return Ok(());
+    };
+    let path = link.to_string_lossy();
+    reg(&["add", &key(file_name), "/ve", "/d", &path, "/f"])
+}
+
+/// Remove the entry for `link`'s file name if it still names `link`, leaving
+/// one that some other program has registered since.
+pub fn unregister(link: &Path) -> io::Result<()> {
+    let Some(file_name) = link.file_name().and_then(|name| name.to_str()) else {
+        return Ok(());
+    };
+    let ours = registered(file_name)
+        .is_some_and(|path| Path::new(&path).as_os_str().eq_ignore_ascii_case(link));
+    match ours {
+        true => reg(&["delete", &key(file_name), "/f"]),
+        false => Ok(()),
+    }
+}
+
+/// The path registered for `file_name`, from `reg query`'s
+/// `    (Default)    REG_SZ    C:\...` line, whose first column is
+/// translated but whose type isn't.
+fn registered(file_name: &str) -> Option<String> {
+    let output = Command::new("reg")
+        .args(["query", &key(file_name), "/ve"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_default(&stdout)
+}
+
+fn parse_default(query: &str) -> Option<String> {
+    query.lines().find_map(|line| {
+        let (_, value) = line.split_once("REG_SZ")?;
+        Some(value.trim().to_string())
+    })
+}
+
+fn reg(args: &[&str]) -> io::Result<()> {
+    let output = Command::new("reg")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default() {
+        let query = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\App Paths\\ls.exe\r\n    (Standard)    REG_SZ    C:\\Users\\me\\.local\\bin\\ls.exe\r\n\r\n";
+        assert_eq!(
+            parse_default(query).as_deref(),
+            Some("C:\\Users\\me\\.local\\bin\\ls.exe")
+        );
+        assert_eq!(parse_default("ERROR: not found"), None);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::config::Config;
+use crate::tee;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where an archive came from, saved next to its config as `metadata.toml`.
+#[derive(Serialize, Deserialize)]
+pub struct Metadata {
+    /// The shdw version that wrote the archive.
+    pub shdw: String,
+    /// UTC date of the export, `YYYY-MM-DD`.
+    pub exported: String,
+    /// Home directory on the exporting machine, for remapping paths in it.
+    pub home: Option<PathBuf>,
+    pub bin_path: PathBuf,
+}
+
+/// A `.tar.gz` holding the resolved config and its metadata. Archives are
+/// made and read with the system's `tar`, which macOS, Linux and Windows 10
+/// and later all have.
+pub fn export(config: &Config, path: &Path) -> Result<(), String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let metadata = Metadata {
+        shdw: env!("CARGO_PKG_VERSION").to_string(),
+        exported: tee::utc_date(now),
+        home: dirs::home_dir(),
+        bin_path: config.settings().bin_path().clone(),
+    };
+    // Resolved, so the archive doesn't depend on templates and loads in
+    // older versions too.
+    let contents = config.frozen().to_toml().map_err(|e| e.to_string())?;
+    let metadata = toml::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+
+    let staging = Staging::new()?;
+    fs::write(staging.0.join("config.toml"), contents).map_err(|e| e.to_string())?;
+    fs::write(staging.0.join("metadata.toml"), metadata).map_err(|e| e.to_string())?;
+    tar(Command::new("tar")
+        .arg("-czf")
+        .arg(path)
+        .arg("-C")
+        .arg(&staging.0)
+        .args(["config.toml", "metadata.toml"]))
+}
+
+/// The config and metadata in an archive written by `export`.
+pub fn unpack(path: &Path) -> Result<(Config, Metadata), String> {
+    let staging = Staging::new()?;
+    tar(Command::new("tar")
+        .arg("-xzf")
+        .arg(path)
+        .arg("-C")
+        .arg(&staging.0)
+        .args(["config.toml", "metadata.toml"]))?;
+    let config = Config::from_file(&staging.0.join("config.toml")).map_err(|e| e.to_string())?;
+    let metadata = fs::read_to_string(staging.0.join("metadata.toml"))
+        .map_err(|e| e.to_string())
+        .and_then(|contents| toml::from_str(&contents).map_err(|e| e.to_string()))?;
+    Ok((config, metadata))
+}
+
+/// `path` with the exporting machine's home directory replaced by this
+/// one's, so `~/bin` stays `~/bin` when the user name differs.
+pub fn remap(path: &Path, metadata: &Metadata) -> PathBuf {
+    let here = dirs::home_dir();
+    match (&metadata.home, &here) {
+        (Some(there), Some(here)) => match path.strip_prefix(there) {
+            Ok(rest) => here.join(rest),
+            Err(_) => path.to_path_buf(),
+        },
+        _ => path.to_path_buf(),
+    }
+}
+
+fn tar(command: &mut Command) -> Result<(), String> {
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("tar failed ({})", status)),
+        Err(e) => Err(format!("Failed to run tar: {}", e)),
+    }
+}
+
+/// A scratch directory, removed again when dropped.
+struct Staging(PathBuf);
+
+impl Staging {
+    /// A new directory in the shared temporary directory, which only this
+    /// user can enter. It's created under a name nobody can guess and must
+    /// not exist yet: one that someone else made, or a symlink planted in
+    /// its place, would otherwise be written into and then removed.
+    fn new() -> Result<Self, String> {
+        use std::collections::hash_map::RandomState;
+        use std::hash::{BuildHasher, Hasher};
+
+        #[cfg(unix)]
+        let builder = {
+            use std::os::unix::fs::DirBuilderExt;
+            let mut builder = fs::DirBuilder::new();
+            builder.mode(0o700);
+            builder
+        };
+        #[cfg(not(unix))]
+        let builder = fs::DirBuilder::new();
+        let mut attempts = 0;
+        loop {
+            // Hashers are seeded randomly for each process.
+            let suffix = RandomState::new().build_hasher().finish();
+            let dir = env::temp_dir().join(format!("shdw-archive-{:016x}", suffix));
+            match builder.create(&dir) {
+                Ok(()) => return Ok(Staging(dir)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempts < 8 => {
+                    attempts += 1;
+                }
+                Err(e) => {
+                    return Err(format!("Failed to create {}: {}", dir.display(), e));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staging_is_private_and_new() {
+        let first = Staging::new().unwrap();
+        let second = Staging::new().unwrap();
+        assert_ne!(first.0, second.0);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&first.0).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        let dir = first.0.clone();
+        drop(first);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_remap_moves_paths_with_home() {
+        let there = PathBuf::from("/home/alice-on-another-machine");
+        let metadata = Metadata {
+            shdw: "0.1.0".into(),
+            exported: "2026-01-01".into(),
+            home: Some(there.clone()),
+            bin_path: there.join(".local/bin"),
+        };
+        let here = dirs::home_dir().unwrap();
+        assert_eq!(remap(&there.join("bin"), &metadata), here.join("bin"));
+        assert_eq!(
+            remap(Path::new("/opt/bin"), &metadata),
+            Path::new("/opt/bin")
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// A baked binary is shdw with a config appended, followed by the config's
+/// length (8 bytes, little-endian) and these 8 bytes. Executables don't mind
+/// trailing data, and shdw finds the config without a config directory.
+const MAGIC: &[u8; 8] = b"shdwbake";
+const TRAILER: u64 = 16;
+
+/// Part of every shdw binary, and rewritten to `BAKED` in the copies `write`
+/// makes. A shim can then tell that it isn't baked without opening its own
+/// file, which would cost every run before the daemon or cache is reached.
+static MARKER: [u8; 16] = *b"shdw:plain:build";
+const BAKED: &[u8; 16] = b"shdw:baked:build";
+
+fn is_baked() -> bool {
+    // SAFETY: MARKER is a static, so the pointer is valid and aligned.
+    // Reading it volatile keeps the compiler from assuming it still holds
+    // what was compiled in.
+    let marker = unsafe { std::ptr::read_volatile(&MARKER) };
+    &marker == BAKED
+}
+
+/// The config baked into the running binary, if it is a baked one.
+pub fn embedded() -> Option<String> {
+    if !is_baked() {
+        return None;
+    }
+    let mut file = File::open(env::current_exe().ok()?).ok()?;
+    let (contents, _) = read(&mut file).ok()??;
+    Some(contents)
+}
+
+/// Mark `binary` as baked, or fail if it isn't shdw.
+fn mark(binary: &mut [u8]) -> io::Result<()> {
+    let mut marked = false;
+    let mut at = 0;
+    while let Some(offset) = binary[at..]
+        .windows(MARKER.len())
+        .position(|window| window == MARKER || window == BAKED)
+    {
+        at += offset;
+        binary[at..at + BAKED.len()].copy_from_slice(BAKED);
+        at += BAKED.len();
+        marked = true;
+    }
+    match marked {
+        true => Ok(()),
+        false => Err(io::Error::other("not a shdw binary")),
+    }
+}
+
+/// The baked config in `file` and where it starts, which is where the
+/// plain binary ends.
+fn read(file: &mut File) -> io::Result<Option<(String, u64)>> {
+    let size = file.metadata()?.len();
+    if size < TRAILER {
+        return Ok(None);
+    }
+    let mut trailer = [0u8; TRAILER as usize];
+    file.seek(SeekFrom::End(-(TRAILER as i64)))?;
+    file.read_exact(&mut trailer)?;
+    if &trailer[8..] != MAGIC {
+        return Ok(None);
+    }
+    let len = u64::from_le_bytes(trailer[..8].try_into().unwrap_or_default());
+    let Some(start) = size.checked_sub(TRAILER + len) else {
+        return Ok(None);
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let mut contents = String::new();
+    file.take(len).read_to_string(&mut contents)?;
+    Ok(Some((contents, start)))
+}
+
+/// Write to `out` a copy of the binary at `exe` with `config` baked in,
+/// replacing any config baked into it already.
+pub fn write(exe: &Path, config: &str, out: &Path) -> io::Result<()> {
+    let mut file = File::open(exe)?;
+    let end = match read(&mut file)? {
+        Some((_, start)) => start,
+        None => file.metadata()?.len(),
+    };
+    file.rewind()?;
+    let mut binary = Vec::new();
+    file.take(end).read_to_end(&mut binary)?;
+    mark(&mut binary)?;
+    binary.extend_from_slice(config.as_bytes());
+    binary.extend_from_slice(&(config.len() as u64).to_le_bytes());
+    binary.extend_from_slice(MAGIC);
+    fs::write(out, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(out, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bake_twice_keeps_one_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("shdw");
+        fs::write(&exe, [b"\x7fELF ".as_slice(), &MARKER].concat()).unwrap();
+        let once = dir.path().join("once");
+        let twice = dir.path().join("twice");
+        write(&exe, "[aliases.a]\ncommand = \"b\"\n", &once).unwrap();
+        write(&once, "[aliases.c]\ncommand = \"d\"\n", &twice).unwrap();
+
+        let mut file = File::open(&twice).unwrap();
+        let (contents, start) = read(&mut file).unwrap().unwrap();
+        assert_eq!(contents, "[aliases.c]\ncommand = \"d\"\n");
+        assert_eq!(start, 21);
+        assert!(read(&mut File::open(&exe).unwrap()).unwrap().is_none());
+
+        let baked = fs::read(&twice).unwrap();
+        assert_eq!(&baked[5..21], BAKED);
+        assert!(!is_baked());
+        let not_shdw = dir.path().join("other");
+        fs::write(&not_shdw, b"\x7fELF").unwrap();
+        assert!(write(&not_shdw, "", &dir.path().join("out")).is_err());
+    }
+}
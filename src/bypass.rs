@@ -0,0 +1,129 @@
+use crate::aliases::Alias;
+use crate::which;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Scripts larger than this are assumed not to be scripts.
+const MAX_SCRIPT_SIZE: u64 = 1024 * 1024;
+
+/// A line in a script that runs a shadowed command by absolute path, which
+/// skips PATH lookup and therefore the shadow.
+pub struct Bypass {
+    pub file: PathBuf,
+    pub line: usize,
+    pub alias: String,
+    pub path: String,
+}
+
+/// Absolute paths through which the original of `alias` can be reached.
+fn original_paths(alias: &Alias) -> Vec<String> {
+    let mut paths: Vec<String> = which::find_all(alias.name())
+        .into_iter()
+        .filter(|path| !which::is_shadow(path))
+        .map(|path| path.display().to_string())
+        .collect();
+
+    // With a merged /usr, /bin/ls and /usr/bin/ls are the same file but
+    // scripts may spell either one.
+    for dir in ["/bin/", "/usr/bin/", "/sbin/", "/usr/sbin/"] {
+        let path = format!("{}{}", dir, alias.name());
+        if Path::new(&path).exists() && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Search the files under `roots` for absolute invocations of shadowed
+/// commands.
+pub fn scan(roots: &[PathBuf], aliases: &[&Alias]) -> Vec<Bypass> {
+    let targets: Vec<(&str, String)> = aliases
+        .iter()
+        .flat_map(|alias| {
+            original_paths(alias)
+                .into_iter()
+                .map(move |path| (alias.name(), path))
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    for root in roots {
+        collect_files(root, &mut files);
+    }
+
+    let mut bypasses = Vec::new();
+    for file in files {
+        let Ok(contents) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for (i, line) in contents.lines().enumerate() {
+            for (alias, path) in &targets {
+                if invokes(line, path) {
+                    bypasses.push(Bypass {
+                        file: file.clone(),
+                        line: i + 1,
+                        alias: alias.to_string(),
+                        path: path.clone(),
+                    });
+                }
+            }
+        }
+    }
+    bypasses
+}
+
+/// Whether `line` mentions `path` as a whole word (so `/usr/bin/ls` doesn't
+/// match `/usr/bin/lsblk`).
+fn invokes(line: &str, path: &str) -> bool {
+    line.match_indices(path).any(|(start, _)| {
+        let before = line[..start].chars().next_back();
+        let after = line[start + path.len()..].chars().next();
+        let boundary = |c: Option<char>| {
+            c.is_none_or(|c| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/')))
+        };
+        boundary(before) && boundary(after)
+    })
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return;
+    };
+    if metadata.is_file() {
+        if metadata.len() <= MAX_SCRIPT_SIZE {
+            files.push(path.to_path_buf());
+        }
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    for entry in entries {
+        // Don't wander into VCS metadata or through symlinked directories.
+        let is_hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        let is_link = entry.symlink_metadata().is_ok_and(|m| m.is_symlink());
+        if is_hidden || (is_link && entry.is_dir()) {
+            continue;
+        }
+        collect_files(&entry, files);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invokes_whole_word() {
+        assert!(invokes("/usr/bin/ls -la", "/usr/bin/ls"));
+        assert!(invokes("x=$(/usr/bin/ls)", "/usr/bin/ls"));
+        assert!(invokes("exec \"/usr/bin/ls\" \"$@\"", "/usr/bin/ls"));
+        assert!(!invokes("/usr/bin/lsblk", "/usr/bin/ls"));
+        assert!(!invokes("/usr/bin/ls.real", "/usr/bin/ls"));
+        assert!(!invokes("/opt/usr/bin/ls", "/usr/bin/ls"));
+    }
+}
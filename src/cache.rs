@@ -0,0 +1,191 @@
+use crate::aliases::Alias;
+use crate::config::{Config, Settings};
+use crate::state;
+use crate::subscription;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// What a shim needs to run one alias, saved in the state directory so the
+/// next invocation can skip parsing the whole config. Parsing a config with
+/// a few hundred aliases costs about a millisecond, far more than reading
+/// this back.
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    sources: Sources,
+    pub settings: Settings,
+    pub alias: Alias,
+}
+
+/// The files a config was loaded from, as they were then: config.toml, the
+/// project's config layered over it and the subscriptions' files beneath.
+/// If any of them changed, or the environment now names different ones,
+/// whatever was made from the config is stale.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sources {
+    config: Source,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project: Option<Source>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    subscriptions: Vec<Source>,
+}
+
+/// A file and its size and modification time, or `None` if it didn't exist.
+#[derive(Clone, Serialize, Deserialize)]
+struct Source {
+    path: PathBuf,
+    stamp: Option<Stamp>,
+}
+
+impl Source {
+    fn of(path: PathBuf) -> Self {
+        let stamp = Stamp::of(&path);
+        Source { path, stamp }
+    }
+
+    fn is_stale(&self) -> bool {
+        Stamp::of(&self.path) != self.stamp
+    }
+}
+
+impl Sources {
+    /// The files `config`, freshly loaded, came from.
+    pub fn of(config: &Config) -> Self {
+        Sources {
+            config: Source::of(Config::config_path()),
+            project: Config::project_path().map(Source::of),
+            subscriptions: config
+                .subscriptions()
+                .keys()
+                .map(|name| Source::of(subscription::cached(name)))
+                .collect(),
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        let project = self.project.as_ref().map(|project| &project.path);
+        self.config.path != Config::config_path()
+            || project != Config::project_path().as_ref()
+            || self.config.is_stale()
+            || self.project.as_ref().is_some_and(Source::is_stale)
+            || self.subscriptions.iter().any(Source::is_stale)
+    }
+}
+
+/// The size and modification time of a file, to notice it changing.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stamp {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl Stamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Stamp {
+            len: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+fn path(name: &str) -> PathBuf {
+    state::file("dispatch").join(format!("{}.toml", name))
+}
+
+impl Entry {
+    /// An entry for alias `name` from `config`, loaded from `sources`.
+    pub fn new(name: &str, config: &Config, sources: &Sources) -> Option<Self> {
+        // A baked config isn't the one in config_path, which the entry
+        // would be checked against.
+        if config.is_baked() {
+            return None;
+        }
+        let alias = config.aliases().get(name)?;
+        Some(Entry {
+            sources: sources.clone(),
+            settings: config.settings().clone(),
+            alias: alias.frozen(),
+        })
+    }
+
+    pub fn to_toml(&self) -> Option<String> {
+        toml::to_string(self).ok()
+    }
+
+    /// Read back an entry for alias `name` written by `to_toml`.
+    pub fn from_toml(name: &str, contents: &str) -> Option<Self> {
+        let mut entry: Entry = toml::from_str(contents).ok()?;
+        entry.alias.set_name(name);
+        Some(entry)
+    }
+
+    /// Whether any file the entry was made from changed since.
+    pub fn is_stale(&self) -> bool {
+        self.sources.is_stale()
+    }
+}
+
+/// The cached alias `name`, if there is an entry and the config hasn't
+/// changed since it was written.
+pub fn get(name: &str) -> Option<Entry> {
+    let contents = fs::read_to_string(path(name)).ok()?;
+    let entry = Entry::from_toml(name, &contents)?;
+    (!entry.is_stale()).then_some(entry)
+}
+
+/// Save alias `name` from a freshly loaded `config` for the next run. Best
+/// effort: a shim works the same without its cache entry.
+pub fn put(name: &str, config: &Config) {
+    let Some(contents) =
+        Entry::new(name, config, &Sources::of(config)).and_then(|entry| entry.to_toml())
+    else {
+        return;
+    };
+    let path = path(name);
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    // Write and rename, so a shim running concurrently never reads half an
+    // entry.
+    let temporary = dir.join(format!(".{}.{}", name, std::process::id()));
+    if fs::create_dir_all(dir).is_ok() && fs::write(&temporary, contents).is_ok() {
+        let _ = fs::rename(&temporary, &path);
+    }
+    let _ = fs::remove_file(&temporary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_goes_stale_with_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_dir = dir.path().join("config");
+        fs::create_dir_all(&config_dir).unwrap();
+        let config_file = config_dir.join("config.toml");
+        let state_dir = dir.path().join("state");
+        let vars = [
+            ("SHDW_CONFIG_DIR", Some(config_dir.as_os_str())),
+            ("SHDW_STATE_DIR", Some(state_dir.as_os_str())),
+        ];
+        temp_env::with_vars(vars, || {
+            fs::write(&config_file, "[aliases.ls]\ncommand = \"eza --icons\"\n").unwrap();
+            let config = Config::load().unwrap();
+            assert!(get("ls").is_none());
+
+            put("ls", &config);
+            let entry = get("ls").unwrap();
+            assert_eq!(entry.alias.name(), "ls");
+            assert_eq!(entry.alias.command(), "eza --icons");
+
+            fs::write(&config_file, "[aliases.ls]\ncommand = \"lsd\"\n").unwrap();
+            assert!(get("ls").is_none());
+        });
+    }
+}
@@ -1,20 +1,79 @@
-use crate::commands::{Add, List, Remove};
-use crate::config::Config;
+use crate::aliases::{self, Alias};
+use crate::cache;
+use crate::commands::{
+    Add, Adopt, Bake, Bootstrap, CheckConfig, CommandNotFound, Completions, ConfigCommand, Daemon,
+    DiffConfig, Doctor, ExitCodes, Expand, Export, Format, Gc, Grep, GroupCommand, Help, Import,
+    Info, Init, InstallMissing, InstallMulticall, Lint, List, Merge, Open, PresetCommand,
+    PromptState, Remove, ResolveCommand, Run, Show, Stats, SubscriptionCommand, Suggest,
+    SyncCommand, Uninstall, WhichWins,
+};
+use crate::config::{Config, OnMissingConfig, Settings};
+use crate::effective::Effective;
 use crate::error::ExitCode;
+use crate::hooks;
+use crate::log::{self, LogFormat};
+use crate::signals;
+use crate::state::State;
+use crate::stats;
+use crate::version::Provenance;
+use crate::warning;
+use crate::which;
 use clap::{Parser, Subcommand};
 use std::env;
+use std::ffi::OsString;
+use std::process;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    arg_required_else_help = true,
+    disable_help_subcommand = true,
+    disable_version_flag = true
+)]
 pub struct Cli {
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+    /// Print the exit codes shdw uses and what they mean
+    #[arg(long)]
+    explain_exit_codes: bool,
+    /// Print the version, commit, build date, target and features
+    #[arg(long, short = 'V')]
+    version: bool,
+    /// Output format for --version
+    #[arg(long, value_enum, default_value_t, requires = "version")]
+    format: Format,
+    /// How to write warnings and errors to stderr
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t,
+        env = "SHDW_LOG_FORMAT"
+    )]
+    log_format: LogFormat,
+    /// Fail when there were warnings, e.g. a bin directory missing from PATH
+    #[arg(long, global = true)]
+    deny_warnings: bool,
 }
 
+/// A shim's arguments, with its own flags taken out. Those are spelled
+/// `--shdw-*` so they can't collide with the replacement's; `--raw` and
+/// `-R` still mean `--shdw-raw`.
 #[derive(Debug)]
 pub struct ShadowedArgs {
-    args: Vec<String>,
+    args: Vec<OsString>,
     is_raw: bool,
+    /// `--shdw-trace`: print the command line before running it.
+    trace: bool,
+    /// `--shdw-why`: print what would run, and why, instead of running it.
+    why: bool,
+    /// `--shdw-help`: print the alias' help page.
+    help: bool,
+    /// The first `--shdw-*` flag that isn't one of the above.
+    unknown: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -28,45 +87,318 @@ pub enum Commands {
     /// List all aliases
     #[command(visible_alias = "ls")]
     List(List),
+    /// Show an alias and what else intercepts its name
+    Show(Show),
+    /// Edit the config at an alias' definition, in $VISUAL or $EDITOR
+    Open(Open),
+    /// Run an alias, linked or not
+    Run(Run),
+    /// Print what a command line would actually run, e.g. for a prompt
+    Resolve(ResolveCommand),
+    /// Expand an alias at the start of a command line, for shell keybindings
+    Expand(Expand),
+    /// Turn existing symlinks and wrapper scripts into aliases
+    Adopt(Adopt),
+    /// Import aliases from shell alias definitions or wrapper scripts
+    #[command(visible_alias = "migrate-from")]
+    Import(Import),
+    /// Set up every alias on a new machine
+    Bootstrap(Bootstrap),
+    /// Link every alias to shdw, optionally as hardlinks
+    InstallMulticall(InstallMulticall),
+    /// Check the health of every alias
+    Doctor(Doctor),
+    /// Install missing replacement binaries
+    InstallMissing(InstallMissing),
+    /// Show which executable the shell picks for a name
+    WhichWins(WhichWins),
+    /// Make shadowed commands complete like their replacements
+    Completions(Completions),
+    /// Print the shell startup snippet (PATH, completions)
+    Init(Init),
+    /// Print help for a shdw command, or an alias' notes and examples
+    Help(Help),
+    /// Fold someone else's aliases into your config
+    Merge(Merge),
+    /// Share aliases as preset files, and apply other people's
+    Preset(PresetCommand),
+    /// Keep alias files your team maintains in sync
+    Subscription(SubscriptionCommand),
+    /// Show how two config files differ, alias by alias
+    DiffConfig(DiffConfig),
+    /// Bundle the aliases into an archive for another machine
+    Export(Export),
+    /// Write a copy of shdw with a config built in, e.g. for containers
+    Bake(Bake),
+    /// Suggest aliases for long commands you type often
+    Suggest(Suggest),
+    /// Search alias names, commands, descriptions and env values
+    Grep(Grep),
+    /// Rewrite the alias functions open shells source (backend = "shell"),
+    /// or link a project's aliases (--project)
+    Sync(SyncCommand),
+    /// Describe this installation, for bug reports
+    Info(Info),
+    /// Summarize whether shadows are active, for shell prompts
+    PromptState(PromptState),
+    /// Show how often each alias ran, failed and how long it took
+    Stats(Stats),
+    /// Clean up old logs and stale state
+    Gc(Gc),
+    /// Remove every symlink shdw made, and optionally its config and state
+    Uninstall(Uninstall),
+    /// Inspect the configuration format
+    Config(ConfigCommand),
+    /// Run a group of aliases, like a small task runner
+    Group(GroupCommand),
+    /// Strictly check a config file, e.g. in a dotfiles repository's CI
+    CheckConfig(CheckConfig),
+    /// Point out aliases that work but probably aren't what was meant
+    Lint(Lint),
+    /// Hold the config in memory and answer shims over a Unix socket
+    Daemon(Daemon),
+    /// Print the exit codes shdw uses and what they mean
+    #[command(hide = true)]
+    ExitCodes(ExitCodes),
+    /// Explain a missing alias; called by the shell hook from `shdw init`
+    #[command(hide = true)]
+    CommandNotFound(CommandNotFound),
 }
 
 impl ShadowedArgs {
     pub fn from_env() -> Self {
-        let args: Vec<String> = env::args().skip(1).collect();
-        let is_raw = args.contains(&"--raw".to_string()) || args.contains(&"-R".to_string());
-        let args = args
-            .into_iter()
-            .filter(|arg| arg != "--raw" && arg != "-R")
-            .collect();
-
-        Self { args, is_raw }
+        Self::parse(env::args_os().skip(1))
+    }
+
+    /// Runs on every shimmed invocation, so the flags are taken out in one
+    /// pass and arguments are passed on as they came, without requiring (or
+    /// converting them to) UTF-8. Everything from `--` on is an operand of
+    /// the command, passed through with the `--` itself.
+    fn parse(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut parsed = Self {
+            args: Vec::new(),
+            is_raw: false,
+            trace: false,
+            why: false,
+            help: false,
+            unknown: None,
+        };
+        let mut operands = false;
+        for arg in args {
+            operands |= arg == "--";
+            if operands {
+                parsed.args.push(arg);
+                continue;
+            }
+            match arg.to_str() {
+                Some("--raw" | "-R" | "--shdw-raw") => parsed.is_raw = true,
+                Some("--shdw-trace") => parsed.trace = true,
+                Some("--shdw-why") => parsed.why = true,
+                Some("--shdw-help") => parsed.help = true,
+                Some(flag) if flag.starts_with("--shdw-") => {
+                    parsed.unknown.get_or_insert_with(|| flag.to_string());
+                }
+                _ => parsed.args.push(arg),
+            }
+        }
+        parsed
+    }
+
+    /// Run `alias` with these arguments, or answer the shim's own flags.
+    fn dispatch(&self, alias: &Alias, settings: &Settings) -> ExitCode {
+        if let Some(flag) = &self.unknown {
+            log::error(format!(
+                "Unknown shdw flag {}; shims take --shdw-raw, --shdw-trace, --shdw-why and --shdw-help",
+                flag
+            ));
+            return ExitCode::InvalidArguments;
+        }
+        if self.help {
+            return Help::print_alias(alias);
+        }
+        let effective = Effective::resolve(alias, settings, self.is_raw);
+        let raw = effective.raw.value;
+        if self.why {
+            let resolution = alias.resolve(&self.args, raw);
+            return ResolveCommand::report(alias.name(), &resolution, false);
+        }
+        if self.trace {
+            let resolution = alias.resolve(&self.args, raw);
+            log::info(format!("+ {}", ResolveCommand::command_line(&resolution)));
+        }
+        if effective.help_banner.value && !raw {
+            self.print_help_banner(alias);
+        }
+        stats::set_enabled(effective.stats.value);
+        if effective.failure_footer.value && !raw {
+            aliases::show_failure_footer();
+        }
+        hooks::watch(settings.hooks());
+        alias.execute(&self.args, raw)
+    }
+
+    /// With `help_banner`, say who answers `--help` before the replacement
+    /// prints its own, unfamiliar help.
+    fn print_help_banner(&self, alias: &Alias) {
+        let asks_for_help = self
+            .args
+            .iter()
+            .take_while(|arg| *arg != "--")
+            .any(|arg| arg == "--help" || arg == "-h");
+        let program = alias.program().unwrap_or_default();
+        if asks_for_help && program != alias.name() {
+            log::info(format!(
+                "{} is shadowed by {} via shdw; use `{} --shdw-raw --help` for the original",
+                alias.name(),
+                program,
+                alias.name()
+            ));
+        }
     }
 }
 
 impl Cli {
     pub fn execute(config: Config) -> ExitCode {
-        let cli = Self::parse();
-        match cli.command {
+        let cli = Self::try_parse().unwrap_or_else(|e| {
+            warning::finish();
+            e.exit()
+        });
+        log::set_format(cli.log_format);
+        let deny_warnings = cli.deny_warnings;
+        let code = cli.run(config);
+        let warnings = warning::finish();
+        if deny_warnings && warnings > 0 && code == ExitCode::Success {
+            log::error(format!(
+                "{} warning{}, and --deny-warnings turns them into a failure",
+                warnings,
+                if warnings == 1 { "" } else { "s" }
+            ));
+            return ExitCode::GeneralError;
+        }
+        code
+    }
+
+    fn run(self, config: Config) -> ExitCode {
+        if self.version {
+            let provenance = Provenance::current();
+            match self.format {
+                Format::Human => provenance.print(),
+                Format::Json => println!("{}", provenance.to_json()),
+            }
+            return ExitCode::Success;
+        }
+        hooks::watch(config.settings().hooks());
+        // Date hand edits to config.toml roughly when shdw first sees them.
+        let mut state = State::load();
+        if state.observe(&config) {
+            let _ = state.save();
+        }
+        let command = match self.command {
+            Some(command) => command,
+            None if self.explain_exit_codes => Commands::ExitCodes(ExitCodes),
+            None => return ExitCode::InvalidArguments,
+        };
+        // These keep going after the command proper (the alias, the
+        // server), so there's no end to hold warnings until.
+        if matches!(command, Commands::Run(_) | Commands::Daemon(_)) {
+            warning::finish();
+        }
+        match command {
             Commands::Add(cmd) => cmd.execute(config),
             Commands::Remove(cmd) => cmd.execute(config),
             Commands::List(cmd) => cmd.execute(config),
+            Commands::Show(cmd) => cmd.execute(config),
+            Commands::Open(cmd) => cmd.execute(config),
+            Commands::Run(cmd) => cmd.execute(config),
+            Commands::Resolve(cmd) => cmd.execute(config),
+            Commands::Expand(cmd) => cmd.execute(config),
+            Commands::Adopt(cmd) => cmd.execute(config),
+            Commands::Import(cmd) => cmd.execute(config),
+            Commands::Bootstrap(cmd) => cmd.execute(config),
+            Commands::InstallMulticall(cmd) => cmd.execute(config),
+            Commands::Doctor(cmd) => cmd.execute(config),
+            Commands::InstallMissing(cmd) => cmd.execute(config),
+            Commands::WhichWins(cmd) => cmd.execute(config),
+            Commands::Completions(cmd) => cmd.execute(config),
+            Commands::Init(cmd) => cmd.execute(config),
+            Commands::Help(cmd) => cmd.execute(config),
+            Commands::Merge(cmd) => cmd.execute(config),
+            Commands::Preset(cmd) => cmd.execute(config),
+            Commands::Subscription(cmd) => cmd.execute(config),
+            Commands::DiffConfig(cmd) => cmd.execute(config),
+            Commands::Export(cmd) => cmd.execute(config),
+            Commands::Bake(cmd) => cmd.execute(config),
+            Commands::Suggest(cmd) => cmd.execute(config),
+            Commands::Grep(cmd) => cmd.execute(config),
+            Commands::Sync(cmd) => cmd.execute(config),
+            Commands::Info(cmd) => cmd.execute(config),
+            Commands::PromptState(cmd) => cmd.execute(config),
+            Commands::Stats(cmd) => cmd.execute(config),
+            Commands::Gc(cmd) => cmd.execute(config),
+            Commands::Uninstall(cmd) => cmd.execute(config),
+            Commands::Config(cmd) => cmd.execute(config),
+            Commands::Group(cmd) => cmd.execute(config),
+            Commands::CheckConfig(cmd) => cmd.execute(config),
+            Commands::Lint(cmd) => cmd.execute(config),
+            Commands::Daemon(cmd) => cmd.execute(config),
+            Commands::ExitCodes(cmd) => cmd.execute(config),
+            Commands::CommandNotFound(cmd) => cmd.execute(config),
         }
     }
 
     pub fn execute_shadowed(config: Config, command: &str) -> ExitCode {
+        cache::put(command, &config);
         let args = ShadowedArgs::from_env();
         match config
             .aliases()
             .find(command)
-            .map(|shadow| shadow.execute(&args.args, args.is_raw))
+            .map(|shadow| args.dispatch(shadow, config.settings()))
         {
             Ok(code) => code,
+            Err(_) if config.settings().on_missing_config() == OnMissingConfig::Passthrough => {
+                passthrough(command)
+            }
             Err(e) => {
-                eprintln!("{}", e);
+                log::error(format!(
+                    "{} is linked to shdw, but {} has no alias for it; run `shdw bootstrap` to remove stale links, or set on_missing_config = \"passthrough\" to run the original",
+                    command,
+                    Config::config_path().display()
+                ));
                 e.into()
             }
         }
     }
+
+    /// Run an alias from the dispatch cache, without loading the config.
+    pub fn execute_cached(alias: &Alias, settings: &Settings) -> ExitCode {
+        ShadowedArgs::from_env().dispatch(alias, settings)
+    }
+}
+
+/// Run the command `name` hides with the shim's arguments as they came,
+/// exiting as it does, for `on_missing_config = "passthrough"`.
+fn passthrough(name: &str) -> ExitCode {
+    let Some(original) = which::find_all(name)
+        .into_iter()
+        .find(|path| !which::is_shadow(path))
+    else {
+        log::error(format!("No original {} found on PATH", name));
+        return ExitCode::CommandNotFound;
+    };
+    let status = process::Command::new(&original)
+        .args(env::args_os().skip(1))
+        .spawn()
+        .and_then(|mut child| signals::wait(&mut child));
+    match status {
+        Ok(status) => {
+            signals::propagate(&status);
+            process::exit(status.code().unwrap_or(ExitCode::CommandFailed as i32))
+        }
+        Err(e) => {
+            log::error(format!("Failed to execute {}: {}", original.display(), e));
+            ExitCode::CommandFailed
+        }
+    }
 }
 
 #[cfg(test)]
@@ -75,13 +407,7 @@ mod tests {
 
     impl ShadowedArgs {
         pub fn new(args: Vec<String>) -> Self {
-            let is_raw = args.contains(&"--raw".to_string()) || args.contains(&"-R".to_string());
-            let args = args
-                .into_iter()
-                .filter(|arg| arg != "--raw" && arg != "-R")
-                .collect();
-
-            Self { args, is_raw }
+            Self::parse(args.into_iter().map(OsString::from))
         }
     }
 
@@ -120,6 +446,37 @@ mod tests {
             assert!(args.is_raw);
         }
 
+        #[test]
+        fn test_raw_after_separator_is_an_operand() {
+            let args = ShadowedArgs::new(vec![
+                "--".to_string(),
+                "--raw".to_string(),
+                "file".to_string(),
+            ]);
+            assert_eq!(args.args, vec!["--", "--raw", "file"]);
+            assert!(!args.is_raw);
+
+            let args =
+                ShadowedArgs::new(vec!["-R".to_string(), "--".to_string(), "-R".to_string()]);
+            assert_eq!(args.args, vec!["--", "-R"]);
+            assert!(args.is_raw);
+        }
+
+        #[test]
+        fn test_shdw_flags() {
+            let args = ShadowedArgs::new(vec![
+                "--shdw-raw".to_string(),
+                "--shdw-trace".to_string(),
+                "--shdw-bogus".to_string(),
+                "arg1".to_string(),
+                "--".to_string(),
+                "--shdw-why".to_string(),
+            ]);
+            assert_eq!(args.args, vec!["arg1", "--", "--shdw-why"]);
+            assert!(args.is_raw && args.trace && !args.why && !args.help);
+            assert_eq!(args.unknown.as_deref(), Some("--shdw-bogus"));
+        }
+
         #[test]
         fn test_multiple_raw_flags() {
             let args = ShadowedArgs::new(vec![
@@ -0,0 +1,39 @@
+use std::process::{Command, Stdio};
+
+/// Programs that print the clipboard, in the order they're tried: a Linux
+/// desktop may have any of the last three, for Wayland or X11.
+const READERS: &[&[&str]] = if cfg!(target_os = "macos") {
+    &[&["pbpaste"]]
+} else if cfg!(windows) {
+    &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]]
+} else {
+    &[
+        &["wl-paste", "--no-newline"],
+        &["xclip", "-out", "-selection", "clipboard"],
+        &["xsel", "--output", "--clipboard"],
+    ]
+};
+
+/// What's on the clipboard, from the first of `READERS` that runs.
+pub fn read() -> Result<String, String> {
+    for reader in READERS {
+        let output = Command::new(reader[0])
+            .args(&reader[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        let Ok(output) = output else {
+            continue;
+        };
+        if !output.status.success() {
+            return Err(format!("{} exited with {}", reader[0], output.status));
+        }
+        return String::from_utf8(output.stdout)
+            .map_err(|_| "the clipboard doesn't hold UTF-8 text".to_string());
+    }
+    let names: Vec<_> = READERS.iter().map(|reader| reader[0]).collect();
+    Err(format!(
+        "no clipboard program found (tried {})",
+        names.join(", ")
+    ))
+}
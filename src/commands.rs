@@ -1,24 +1,131 @@
-use crate::aliases::Alias;
-use crate::config::Config;
-use crate::error::ExitCode;
-use clap::Parser;
-use std::path::PathBuf;
+use crate::adopt;
+use crate::aliases::{
+    name_problem, restore_diverted, unregister_app_path, Alias, LinkState, Resolution,
+};
+use crate::archive;
+use crate::bake;
+use crate::bypass;
+use crate::cli::Cli;
+use crate::clipboard;
+use crate::competitors::{self, Competitor};
+use crate::completions;
+use crate::config::{Backend, BuiltinPolicy, Config, Settings};
+#[cfg(unix)]
+use crate::daemon;
+use crate::diff;
+use crate::duration::HumanDuration;
+use crate::editor;
+use crate::effective::Effective;
+use crate::error::{ExitCode, ShadowError};
+use crate::gatekeeper;
+use crate::gc;
+use crate::history;
+use crate::hooks::Event;
+use crate::import;
+use crate::init;
+use crate::json::Value;
+use crate::lint;
+use crate::log;
+use crate::output::{self, Style};
+use crate::packages::{Manager, Package};
+use crate::paths;
+use crate::pattern::Pattern;
+use crate::preset;
+use crate::quote;
+use crate::schema;
+use crate::secrets::EnvValue;
+use crate::shell::Shell;
+use crate::size::ByteSize;
+use crate::state::{self, Source, State};
+use crate::stats;
+use crate::subscription;
+use crate::tee;
+use crate::version::Provenance;
+use crate::warning::{self, Warning};
+use crate::which;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How a report is printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    #[default]
+    Human,
+    Json,
+}
 
 #[derive(Clone, Debug, Parser)]
+#[command(group(
+    ArgGroup::new("replacement")
+        .required(true)
+        .args(["command", "from_clipboard", "from_stdin"])
+))]
 pub struct Add {
     /// Name of the alias
     name: String,
     /// Command to execute
-    command: String,
+    command: Option<String>,
+    /// Take the command from the clipboard, quotes and all
+    #[arg(long)]
+    from_clipboard: bool,
+    /// Take the command from standard input, e.g. a here-document
+    #[arg(long)]
+    from_stdin: bool,
     /// Description of the alias
     #[arg(long)]
     description: Option<String>,
     /// Directory to create symlink in
     #[arg(long)]
     bin_path: Option<PathBuf>,
+    /// Don't create a symlink; the alias only runs through `shdw run`
+    #[arg(long)]
+    no_link: bool,
+    /// Shadow a command in settings.protected, such as sudo or ssh
+    #[arg(long)]
+    i_know_what_im_doing: bool,
 }
 
 impl Add {
+    /// The replacement as given, or as read from the clipboard or stdin,
+    /// without the newline that usually comes with it.
+    fn command(&self) -> Result<String, ExitCode> {
+        let (text, source) = if self.from_clipboard {
+            (clipboard::read(), "the clipboard")
+        } else if self.from_stdin {
+            let text = io::read_to_string(io::stdin()).map_err(|e| e.to_string());
+            (text, "standard input")
+        } else {
+            return Ok(self.command.clone().unwrap_or_default());
+        };
+        let text = match text {
+            Ok(text) => text,
+            Err(e) => {
+                log::error(format!("Failed to read {}: {}", source, e));
+                return Err(ExitCode::GeneralError);
+            }
+        };
+        let command = text.trim();
+        if command.is_empty() {
+            log::error(format!("No command in {}", source));
+            return Err(ExitCode::InvalidArguments);
+        }
+        if command.contains('\n') {
+            log::error(format!(
+                "Expected the command on one line in {}, found {} lines",
+                source,
+                command.lines().count()
+            ));
+            return Err(ExitCode::InvalidArguments);
+        }
+        Ok(command.to_string())
+    }
+
     pub fn execute(&self, mut config: Config) -> ExitCode {
         let bin_path = match &self.bin_path {
             Some(p) if p == config.settings().bin_path() => None,
@@ -26,25 +133,95 @@ impl Add {
             None => None,
         };
 
-        let alias = Alias::new(
-            self.name.clone(),
-            self.command.clone(),
-            self.description.clone(),
-            bin_path,
-        );
+        let command = match self.command() {
+            Ok(command) => command,
+            Err(code) => return code,
+        };
+        let alias = Alias::builder(&self.name, &command)
+            .description(self.description.clone())
+            .bin_path(bin_path)
+            .link(!self.no_link)
+            .build();
+        let checked = alias.and_then(|alias| {
+            match self.i_know_what_im_doing {
+                true => config.check_new(&alias),
+                false => config.check_add(&alias),
+            }
+            .map(|_| alias)
+        });
+        let alias = match checked {
+            Ok(alias) => alias,
+            Err(e) => {
+                log::error(&e);
+                return e.into();
+            }
+        };
 
-        if let Err(e) = alias.create_symlink(config.settings()) {
-            eprintln!("{}", e);
-            return e.into();
+        let mut linked = false;
+        if alias.wants_symlink(config.settings()) {
+            if let Err(code) = check_builtin(&alias, config.settings().builtins()) {
+                return code;
+            }
+            let conflicts = config.link_conflicts(&alias);
+            if !conflicts.is_empty() {
+                log::error(format!(
+                    "{} would link to {}, which is alias {}'s link",
+                    self.name,
+                    alias.link_file(config.settings()).display(),
+                    conflicts.join(", ")
+                ));
+                return ExitCode::DuplicateCommand;
+            }
+            match link_or_defer(&alias, config.settings()) {
+                Ok(linked_now) => linked = linked_now,
+                Err(e) => {
+                    log::error(&e);
+                    return e.into();
+                }
+            }
+            let dir = alias.link_dir(config.settings());
+            for problem in paths::permission_problems(dir) {
+                warning::warn(format!(
+                    "{} {}, so others can shadow any command behind it on PATH (see `shdw doctor`)",
+                    dir.display(),
+                    problem
+                ));
+            }
+            if which::path_position(dir).is_none() {
+                warning::warn(Warning::NotOnPath(dir.to_path_buf()));
+            }
+        }
+        for (program, aliases) in missing_replacements(&[&alias]) {
+            warning::warn(Warning::missing_replacement(program, &aliases));
         }
 
-        match config.add(alias) {
+        let record = alias.clone();
+        let link = alias.link_file(config.settings());
+        let added = match self.i_know_what_im_doing {
+            true => config.add_protected(alias),
+            false => config.add(alias),
+        };
+        match added {
             Ok(()) => {
+                update_state(|state| {
+                    if linked {
+                        state.record_link(&self.name, link);
+                    }
+                    state.record_alias(&record, Source::Cli);
+                });
                 println!("Added alias: {}", self.name);
+                sync_functions(&config);
+                config.settings().hooks().run(Event::Add, &[&self.name]);
                 ExitCode::Success
             }
             Err(e) => {
-                eprintln!("{}", e);
+                log::error(&e);
+                // Without the alias in the config, the link is a stray.
+                if linked {
+                    if let Err(e) = record.remove_symlink(config.settings()) {
+                        log::error(format!("Failed to remove {}: {}", link.display(), e));
+                    }
+                }
                 e.into()
             }
         }
@@ -61,27 +238,73 @@ pub struct Remove {
 }
 
 impl Remove {
-    pub fn execute(&self, mut config: Config) -> ExitCode {
+    pub fn execute(&self, config: Config) -> ExitCode {
         let alias = match config.aliases().get(&self.name) {
             Some(alias) => alias,
             None => {
-                eprintln!("Alias not found: {}", self.name);
+                log::error(format!("Alias not found: {}", self.name));
                 return ExitCode::CommandNotFound;
             }
         };
+        // Refused before any link or record goes, so the alias keeps working.
+        if let Err(e) = config.check_removable(&self.name) {
+            log::error(&e);
+            return e.into();
+        }
+        // Only your own alias underneath goes; the project's stays in
+        // effect, through the same link.
+        if alias.is_project() {
+            return self.forget(config);
+        }
 
+        // Only delete what shdw created: a link to this binary, or a symlink
+        // recorded in the state file (e.g. dangling after shdw moved).
+        // Anything else at the link path was put there by someone else.
+        let record = State::load().links().get(&self.name).cloned();
+        let recorded = record.as_ref().map(|record| record.path.clone());
+        // A link made before bin_path was edited by hand is somewhere else.
+        if let Some(record) = &record {
+            if record.path != alias.link_file(config.settings()) && record.is_ours() {
+                match fs::remove_file(&record.path) {
+                    Ok(()) => restore_diverted(&record.path),
+                    Err(e) => {
+                        log::error(format!("Failed to remove {}: {}", record.path.display(), e));
+                        return ExitCode::GeneralError;
+                    }
+                }
+            }
+        }
+        match alias.link_state(config.settings()) {
+            LinkState::Linked(_) => {}
+            LinkState::Foreign(path, _) if recorded.as_ref() == Some(&path) => {}
+            LinkState::Missing(_) => {}
+            LinkState::Foreign(path, _) | LinkState::NotASymlink(path) => {
+                warning::warn(format!(
+                    "leaving {}, which shdw didn't create",
+                    path.display()
+                ));
+                update_state(|state| state.forget_link(&self.name));
+                return self.forget(config);
+            }
+        }
         if let Err(e) = alias.remove_symlink(config.settings()) {
-            eprintln!("{}", e);
+            log::error(&e);
             return e.into();
         }
+        update_state(|state| state.forget_link(&self.name));
+        self.forget(config)
+    }
 
+    fn forget(&self, mut config: Config) -> ExitCode {
         match config.remove(&self.name) {
             Ok(()) => {
                 println!("Removed alias: {}", self.name);
+                sync_functions(&config);
+                config.settings().hooks().run(Event::Remove, &[&self.name]);
                 ExitCode::Success
             }
             Err(e) => {
-                eprintln!("{}", e);
+                log::error(&e);
                 e.into()
             }
         }
@@ -89,20 +312,3516 @@ impl Remove {
 }
 
 #[derive(Clone, Debug, Parser)]
-pub struct List;
+pub struct List {
+    /// Order aliases by name, or most recently added or changed first
+    #[arg(long, value_enum, default_value_t = Sort::Name)]
+    sort: Sort,
+    /// Only aliases added within this long [default: 30d]
+    #[arg(long, value_name = "AGE", num_args = 0..=1, default_missing_value = "30d")]
+    recent: Option<HumanDuration>,
+    /// Only aliases that haven't run for this long, e.g. 90d
+    #[arg(long, value_name = "AGE")]
+    unused: Option<HumanDuration>,
+    /// Show aliases under the ones they extend, grouped by template
+    #[arg(long, conflicts_with_all = ["sort", "recent", "unused"])]
+    tree: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Sort {
+    Name,
+    Recent,
+}
 
 impl List {
     pub fn execute(&self, config: Config) -> ExitCode {
-        match config.aliases().is_empty() {
-            true => println!("No aliases configured"),
-            false => {
-                let mut aliases: Vec<_> = config.aliases().values().collect();
-                aliases.sort_by(|a, b| a.name().cmp(b.name()));
-                for alias in aliases {
-                    println!("{}", alias);
-                }
+        if config.aliases().is_empty() {
+            println!("No aliases configured");
+            return ExitCode::Success;
+        }
+        let mut aliases: Vec<_> = config.aliases().values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+        let terminal = output::terminal();
+        if self.tree {
+            print_tree(&aliases);
+            return ExitCode::Success;
+        }
+        if self.sort == Sort::Name && self.recent.is_none() && self.unused.is_none() {
+            for alias in aliases {
+                println!("{}", terminal.fit(&alias.to_string()));
             }
+            return ExitCode::Success;
+        }
+
+        let state = State::load();
+        let record = |alias: &Alias| state.alias(alias.name());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let within = |time: u64, age: &HumanDuration| {
+            now.saturating_sub(time) <= age.as_duration().as_secs()
+        };
+
+        if let Some(age) = &self.recent {
+            aliases.retain(|alias| record(alias).is_some_and(|record| within(record.created, age)));
+        }
+        if let Some(age) = &self.unused {
+            // Aliases that never ran count from when they were added.
+            aliases.retain(|alias| {
+                let last = state::last_used(alias.name()).or(record(alias).map(|r| r.created));
+                !last.is_some_and(|last| within(last, age))
+            });
+        }
+        if self.sort == Sort::Recent {
+            aliases.sort_by_key(|alias| std::cmp::Reverse(record(alias).map(|r| r.updated)));
+        }
+        if aliases.is_empty() {
+            println!("No matching aliases");
+        }
+        for alias in aliases {
+            let date = match self.unused {
+                Some(_) => match state::last_used(alias.name()) {
+                    Some(last) => format!("used {}", tee::utc_date(last)),
+                    None => "never used".to_string(),
+                },
+                None => record(alias)
+                    .map(|r| tee::utc_date(r.updated))
+                    .unwrap_or_default(),
+            };
+            println!("{}", terminal.fit(&format!("{:<15}  {}", date, alias)));
         }
         ExitCode::Success
     }
 }
+
+/// For `shdw list --tree`: each alias under the one it `extends`, and
+/// aliases that extend nothing under their template, if they have one.
+/// `aliases` are sorted by name.
+fn print_tree(aliases: &[&Alias]) {
+    let terminal = output::terminal();
+    let names: BTreeSet<_> = aliases.iter().map(|alias| alias.name()).collect();
+    let has_parent = |alias: &Alias| alias.parent().is_some_and(|parent| names.contains(parent));
+    let children = |name: &str| -> Vec<&Alias> {
+        aliases
+            .iter()
+            .copied()
+            .filter(|alias| alias.parent() == Some(name))
+            .collect()
+    };
+
+    fn print<'a>(
+        alias: &'a Alias,
+        prefix: &str,
+        last: Option<bool>,
+        children: &dyn Fn(&str) -> Vec<&'a Alias>,
+    ) {
+        let terminal = output::terminal();
+        let (branch, indent) = match last {
+            Some(last) => terminal.branch(last),
+            None => ("", ""),
+        };
+        println!(
+            "{}",
+            terminal.fit(&format!("{}{}{}", prefix, branch, alias))
+        );
+        let under = children(alias.name());
+        let prefix = format!("{}{}", prefix, indent);
+        for (i, child) in under.iter().enumerate() {
+            print(child, &prefix, Some(i + 1 == under.len()), children);
+        }
+    }
+
+    let roots: Vec<_> = aliases.iter().filter(|alias| !has_parent(alias)).collect();
+    let mut templates: BTreeMap<&str, Vec<&Alias>> = BTreeMap::new();
+    for alias in &roots {
+        match alias.template() {
+            Some(template) => templates.entry(template).or_default().push(alias),
+            None => print(alias, "", None, &children),
+        }
+    }
+    for (template, aliases) in templates {
+        println!(
+            "{}",
+            terminal.paint(Style::Bold, &format!("template {}", template))
+        );
+        for (i, alias) in aliases.iter().enumerate() {
+            print(alias, "", Some(i + 1 == aliases.len()), &children);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct InstallMulticall {
+    /// Hardlink every alias to shdw instead of symlinking it, for systems
+    /// where argv[0] comes out as the symlink's target
+    #[arg(long)]
+    hardlink: bool,
+}
+
+impl InstallMulticall {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        if self.hardlink && !cfg!(unix) {
+            log::error("--hardlink is only supported on Unix");
+            return ExitCode::InvalidArguments;
+        }
+        let settings = config.settings();
+        let mut aliases: Vec<_> = config.aliases().values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let mut code = ExitCode::Success;
+        let mut state = State::load();
+        for alias in aliases {
+            if !alias.wants_symlink(settings) {
+                continue;
+            }
+            if config
+                .link_conflicts(alias)
+                .iter()
+                .any(|other| *other < alias.name())
+            {
+                log::error(format!(
+                    "Not linking {}: {} is already another alias' link",
+                    alias.name(),
+                    alias.link_file(settings).display()
+                ));
+                code = ExitCode::GeneralError;
+                continue;
+            }
+            clear_outdated_link(&state, alias, settings);
+            match alias.create_link(settings, self.hardlink) {
+                Ok(()) => {
+                    println!(
+                        "Linked {} in {}",
+                        alias.name(),
+                        alias.link_dir(settings).display()
+                    );
+                    state.record_link(alias.name(), alias.link_file(settings));
+                }
+                Err(e) => {
+                    log::error(format!("Failed to link {}: {}", alias.name(), e));
+                    if let ShadowError::Unwritable(..) = e {
+                        state.record_pending(alias.name());
+                    }
+                    code = ExitCode::GeneralError;
+                }
+            }
+        }
+        if let Err(e) = state.save() {
+            warning::warn(format!("could not save state: {}", e));
+        }
+        code
+    }
+}
+
+/// Remove the link shdw made for `alias` if it no longer names this binary,
+/// such as a hardlink made before shdw was upgraded, so relinking replaces
+/// it rather than moving it aside as someone else's file.
+fn clear_outdated_link(state: &State, alias: &Alias, settings: &Settings) {
+    let path = alias.link_file(settings);
+    if state.owns(alias.name(), &path) && !which::is_shadow(&path) {
+        let _ = fs::remove_file(&path);
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Bootstrap {
+    /// Print install commands for missing replacement binaries
+    #[arg(long)]
+    install_commands: bool,
+}
+
+impl Bootstrap {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let problems = config.validate();
+        if !problems.is_empty() {
+            log::error(format!("Config is invalid:\n  {}", problems.join("\n  ")));
+            return ExitCode::ConfigError;
+        }
+
+        let settings = config.settings();
+        let mut aliases: Vec<_> = config.aliases().values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let mut failed = false;
+        let mut state = State::load();
+        for (name, path) in stale_links(&state, &config) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {
+                    println!("Removed {}, left over from alias {}", path.display(), name);
+                    restore_diverted(&path);
+                    unregister_app_path(&path);
+                    state.forget_link(&name);
+                }
+                Err(e) => {
+                    log::error(format!("Failed to remove {}: {}", path.display(), e));
+                    failed = true;
+                }
+            }
+        }
+
+        let mut dirs: BTreeMap<PathBuf, Vec<&Alias>> = BTreeMap::new();
+        let mut pending = Vec::new();
+        for alias in &aliases {
+            if !alias.wants_symlink(settings) {
+                if let LinkState::Linked(path) = alias.link_state(settings) {
+                    match alias.remove_symlink(settings) {
+                        Ok(()) => {
+                            println!("Unlinked {} ({})", path.display(), no_link_reason(alias))
+                        }
+                        Err(e) => {
+                            log::error(format!("Failed to unlink {}: {}", alias.name(), e));
+                            failed = true;
+                        }
+                    }
+                }
+                state.forget_link(alias.name());
+                continue;
+            }
+            // Aliases go in name order, so the first of a clash keeps it.
+            let taken: Vec<&str> = config
+                .link_conflicts(alias)
+                .into_iter()
+                .filter(|other| *other < alias.name())
+                .collect();
+            if !taken.is_empty() {
+                log::error(format!(
+                    "Not linking {}: {} is already alias {}'s link",
+                    alias.name(),
+                    alias.link_file(settings).display(),
+                    taken.join(", ")
+                ));
+                failed = true;
+                continue;
+            }
+            let dir = alias.link_dir(settings).to_path_buf();
+            if !dir.exists() {
+                println!("Creating {}", dir.display());
+            }
+            clear_outdated_link(&state, alias, settings);
+            match alias.create_symlink(settings) {
+                Ok(()) => {
+                    println!("Linked {} in {}", alias.name(), dir.display());
+                    state.record_link(alias.name(), alias.link_file(settings));
+                }
+                Err(e) => {
+                    log::error(format!("Failed to link {}: {}", alias.name(), e));
+                    if let ShadowError::Unwritable(..) = e {
+                        state.record_pending(alias.name());
+                        pending.push(alias.name());
+                    }
+                    failed = true;
+                }
+            }
+            dirs.entry(dir).or_default().push(alias);
+        }
+        if !pending.is_empty() {
+            log::error(format!(
+                "Not linked, for want of a writable bin directory: {}. They stay in the config; \
+                 run `shdw bootstrap` again once it can write to them.",
+                pending.join(", ")
+            ));
+        }
+
+        if let Err(e) = state.save() {
+            warning::warn(format!("could not save state: {}", e));
+        }
+        sync_functions(&config);
+
+        let shell = Shell::detect();
+        for (dir, aliases) in &dirs {
+            let Some(position) = which::path_position(dir) else {
+                warning::warn(Warning::NotOnPath(dir.to_path_buf()));
+                println!();
+                println!(
+                    "To put {} on your PATH, add this to {}:",
+                    dir.display(),
+                    shell.rc_file()
+                );
+                println!("  {}", shell.path_line(dir));
+                continue;
+            };
+
+            let dir_list = which::path_dirs();
+            for alias in aliases {
+                let Some(original) = which::find_excluding(alias.name(), dir) else {
+                    continue;
+                };
+                let earlier = original
+                    .parent()
+                    .and_then(which::path_position)
+                    .is_some_and(|original_position| original_position < position);
+                if earlier {
+                    println!();
+                    println!(
+                        "{} comes before {} on your PATH, so {} is not shadowed. Add this to {}:",
+                        original.parent().unwrap_or(&original).display(),
+                        dir_list[position].display(),
+                        alias.name(),
+                        shell.rc_file()
+                    );
+                    println!("  {}", shell.path_line(dir));
+                }
+            }
+        }
+
+        let missing = missing_replacements(&aliases);
+        for (program, users) in &missing {
+            warning::warn(Warning::missing_replacement(program, users));
+        }
+        if self.install_commands && !missing.is_empty() {
+            let managers = Manager::detect();
+            println!();
+            println!("Replacements still to install:");
+            for program in missing.keys() {
+                println!("  {}", program);
+                print_install_hints(program, &managers);
+            }
+        }
+
+        config.settings().hooks().run(Event::Sync, &[]);
+        match failed {
+            true => ExitCode::GeneralError,
+            false => ExitCode::Success,
+        }
+    }
+}
+
+/// Links shdw created that no alias accounts for anymore (e.g. the alias
+/// was deleted from config.toml by hand) and that still point at shdw.
+fn stale_links(state: &State, config: &Config) -> Vec<(String, PathBuf)> {
+    state
+        .links()
+        .iter()
+        .filter(|(name, record)| {
+            let current = config
+                .aliases()
+                .get(name.as_str())
+                .filter(|alias| alias.links())
+                .map(|alias| alias.link_file(config.settings()));
+            current.as_ref() != Some(&record.path) && record.is_ours()
+        })
+        .map(|(name, record)| (name.clone(), record.path.clone()))
+        .collect()
+}
+
+/// Why an alias has no symlink, for messages about one it shouldn't have.
+fn no_link_reason(alias: &Alias) -> &'static str {
+    match alias.links() {
+        true => "backend = \"shell\"",
+        false => "link = false",
+    }
+}
+
+/// With `backend = "shell"`, rewrite the files open shells can source to
+/// pick up changed alias functions.
+fn sync_functions(config: &Config) {
+    if config.settings().backend() != Backend::Shell {
+        return;
+    }
+    match init::sync(config) {
+        Ok(_) => {
+            if let Some(path) = init::functions_file(Shell::detect()) {
+                println!(
+                    "Run `source {}` to update the functions in open shells",
+                    path.display()
+                );
+            }
+        }
+        Err(e) => warning::warn(format!("could not write alias functions: {}", e)),
+    }
+}
+
+/// The shell whose builtin `name` is, which it runs instead of a link.
+fn builtin_of(name: &str) -> Option<Shell> {
+    let shell = Shell::detect();
+    // Scripts run by sh hit the POSIX builtins whatever the login shell is.
+    if shell.has_builtin(name) {
+        Some(shell)
+    } else if cfg!(unix) && Shell::Posix.has_builtin(name) {
+        Some(Shell::Posix)
+    } else {
+        None
+    }
+}
+
+/// Warn about, or refuse, linking an alias that the user's shell would never
+/// run because it has a builtin of that name.
+fn check_builtin(alias: &Alias, policy: BuiltinPolicy) -> Result<(), ExitCode> {
+    let shell = Shell::detect();
+    let Some(shadowed_by) = builtin_of(alias.name()) else {
+        return Ok(());
+    };
+    if policy == BuiltinPolicy::Allow {
+        return Ok(());
+    }
+
+    let refused = policy == BuiltinPolicy::Refuse;
+    let message = format!(
+        "{} is a {} builtin, so the shell runs its own {} and never this alias; only scripts \
+         calling the executable by path would get it.",
+        alias.name(),
+        shadowed_by.name(),
+        alias.name()
+    );
+    match refused {
+        true => log::error(&message),
+        false => warning::warn(message),
+    }
+    log::info(format!(
+        "Define it as a function in {} instead:\n  {}",
+        shell.rc_file(),
+        shell.function(alias.name(), alias.command())
+    ));
+    match refused {
+        true => {
+            log::info("(settings.builtins = \"refuse\"; use --no-link to keep it for `shdw run`)");
+            Err(ExitCode::InvalidArguments)
+        }
+        false => Ok(()),
+    }
+}
+
+/// Link `alias`, or, if its bin directory is read-only or full, warn and
+/// note the link as pending: the alias goes in the config all the same, and
+/// `shdw bootstrap` links it once the directory can be written. Returns
+/// whether it was linked.
+fn link_or_defer(alias: &Alias, settings: &Settings) -> Result<bool, ShadowError> {
+    match alias.create_symlink(settings) {
+        Ok(()) => Ok(true),
+        Err(e @ ShadowError::Unwritable(..)) => {
+            warning::warn(format!(
+                "{} is not linked, as {}; run `shdw bootstrap` once it can be written",
+                alias.name(),
+                e
+            ));
+            update_state(|state| state.record_pending(alias.name()));
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Apply `update` to the saved state. State only records what shdw did, so
+/// failing to save it is a warning rather than an error.
+fn update_state(update: impl FnOnce(&mut State)) {
+    let mut state = State::load();
+    update(&mut state);
+    if let Err(e) = state.save() {
+        warning::warn(format!("could not save state: {}", e));
+    }
+}
+
+/// Replacement programs that can't be found on PATH, with the aliases that
+/// need them.
+fn missing_replacements<'a>(aliases: &[&'a Alias]) -> BTreeMap<&'a str, Vec<&'a str>> {
+    let mut missing: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for alias in aliases {
+        if let Some(program) = alias.program() {
+            if which::find(program).is_none() {
+                missing.entry(program).or_default().push(alias.name());
+            }
+        }
+    }
+    missing
+}
+
+fn print_install_hints(program: &str, managers: &[Manager]) {
+    let commands = Package::find(program)
+        .map(|package| package.install_commands(managers))
+        .unwrap_or_default();
+    if commands.is_empty() {
+        println!("    no known package for the available package managers");
+    }
+    for command in commands {
+        println!("    install with: {}", command.join(" "));
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Doctor {
+    /// Also scan scripts in these files or directories for commands called
+    /// by absolute path (e.g. /bin/ls), which bypass shadows
+    #[arg(long, value_name = "PATH")]
+    scan: Vec<PathBuf>,
+    /// Check these machines instead, running their shdw over SSH
+    #[arg(long, value_name = "HOST")]
+    host: Vec<String>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+/// What `shdw doctor` found on one machine, which `--format json` prints
+/// and `--host` reads back from others.
+#[derive(Debug, Default, PartialEq)]
+struct Checkup {
+    /// Problems with the config, bin directories and links.
+    problems: Vec<String>,
+    aliases: Vec<AliasCheckup>,
+    /// Scripts calling shadowed commands by absolute path, with `--scan`.
+    bypasses: Option<Vec<String>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct AliasCheckup {
+    name: String,
+    command: String,
+    /// The alias as `shdw list` shows it, for people; the arrow depends on
+    /// the terminal, so it's left out of the JSON.
+    shown: String,
+    issues: Vec<String>,
+    /// The replacement, if it isn't installed.
+    missing: Option<String>,
+}
+
+impl Checkup {
+    fn count(&self) -> usize {
+        let issues: usize = self.aliases.iter().map(|alias| alias.issues.len()).sum();
+        self.problems.len() + issues + self.bypasses.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Print the checkup, with how to install missing replacements when
+    /// `managers` are the ones on this machine.
+    fn print(&self, managers: Option<&[Manager]>) {
+        let terminal = output::terminal();
+        let (ok, failed) = (terminal.mark(true), terminal.mark(false));
+        for problem in &self.problems {
+            println!("{} {}", failed, problem);
+        }
+        for alias in &self.aliases {
+            if alias.issues.is_empty() {
+                println!("{} {}", ok, alias.shown);
+                continue;
+            }
+            println!("{} {}", failed, alias.shown);
+            for issue in &alias.issues {
+                println!("    {}", issue);
+            }
+            if let (Some(program), Some(managers)) = (&alias.missing, managers) {
+                print_install_hints(program, managers);
+            }
+        }
+        let Some(bypasses) = &self.bypasses else {
+            return;
+        };
+        match bypasses.is_empty() {
+            true => println!("{} No scripts call shadowed commands by absolute path", ok),
+            false => println!("{} Scripts bypassing shadows by absolute path:", failed),
+        }
+        for bypass in bypasses {
+            println!("    {}", bypass);
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let aliases = self.aliases.iter().map(|alias| {
+            Value::object([
+                ("name", Value::from(alias.name.as_str())),
+                ("command", Value::from(alias.command.as_str())),
+                ("issues", Value::from(alias.issues.clone())),
+                ("missing", Value::from(alias.missing.clone())),
+            ])
+        });
+        Value::object([
+            ("problems", Value::from(self.problems.clone())),
+            ("aliases", Value::Array(aliases.collect())),
+            ("bypasses", Value::from(self.bypasses.clone())),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let strings = |value: &Value| -> Option<Vec<String>> {
+            value
+                .as_array()?
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect()
+        };
+        let arrow = output::terminal().arrow();
+        let aliases = value.get("aliases")?.as_array()?.iter().map(|alias| {
+            let name = alias.get("name")?.as_str()?.to_string();
+            let command = alias.get("command")?.as_str()?.to_string();
+            Some(AliasCheckup {
+                shown: format!("{} {} {}", name, arrow, command),
+                name,
+                command,
+                issues: strings(alias.get("issues")?)?,
+                missing: alias.get("missing")?.as_str().map(str::to_string),
+            })
+        });
+        Some(Checkup {
+            problems: strings(value.get("problems")?)?,
+            aliases: aliases.collect::<Option<_>>()?,
+            bypasses: value.get("bypasses").and_then(strings),
+        })
+    }
+}
+
+impl Doctor {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        if !self.host.is_empty() {
+            return self.check_hosts();
+        }
+        let checkup = self.checkup(&config);
+        match self.format {
+            Format::Json => println!("{}", checkup.to_json()),
+            Format::Human => {
+                checkup.print(Some(&Manager::detect()));
+                self.offer_chmod(&config);
+            }
+        }
+        match checkup.count() {
+            0 => ExitCode::Success,
+            _ => ExitCode::GeneralError,
+        }
+    }
+
+    fn checkup(&self, config: &Config) -> Checkup {
+        let settings = config.settings();
+        let mut aliases: Vec<_> = config.aliases().values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let mut checkup = Checkup {
+            problems: config.validate(),
+            ..Checkup::default()
+        };
+        for dir in bin_dirs(config) {
+            for problem in paths::permission_problems(dir) {
+                checkup.problems.push(format!(
+                    "{} {}, so others can shadow any command behind it on PATH",
+                    dir.display(),
+                    problem
+                ));
+            }
+        }
+        let records = State::load();
+        for (name, path) in stale_links(&records, config) {
+            checkup.problems.push(format!(
+                "{} is left over from alias {} (run `shdw bootstrap`)",
+                path.display(),
+                name
+            ));
+        }
+
+        for alias in &aliases {
+            let mut issues = Vec::new();
+            match alias.link_state(settings) {
+                LinkState::Linked(_) if !alias.wants_symlink(settings) => issues.push(format!(
+                    "linked despite {} (run `shdw bootstrap`)",
+                    no_link_reason(alias)
+                )),
+                LinkState::Linked(_) => {}
+                _ if !alias.wants_symlink(settings) => {}
+                LinkState::Missing(_) if records.is_pending(alias.name()) => issues.push(format!(
+                    "not linked yet: {} was read-only or full (run `shdw bootstrap`)",
+                    alias.link_dir(settings).display()
+                )),
+                state => issues.push(format!("{} (run `shdw bootstrap`)", state)),
+            }
+            if alias.wants_symlink(settings)
+                && which::path_position(alias.link_dir(settings)).is_none()
+            {
+                issues.push(format!(
+                    "{} is not on PATH",
+                    alias.link_dir(settings).display()
+                ));
+            }
+            if alias.links() {
+                let found = which::find_all(alias.name());
+                let position = |path: &PathBuf| found.iter().position(|p| p == path);
+                let shadow = found.iter().position(|path| which::is_shadow(path));
+                let competitors = competitors::find(alias.name());
+                // Shims behind the shadow on PATH never run; anything else
+                // makes the outcome depend on the shell or the PATH order.
+                let relevant: Vec<_> = competitors
+                    .iter()
+                    .filter(|competitor| match competitor {
+                        Competitor::Shim { path, .. } => {
+                            shadow.is_none_or(|shadow| position(path) < Some(shadow))
+                        }
+                        Competitor::OtherShadow(_) | Competitor::ShellAlias { .. } => true,
+                    })
+                    .collect();
+                for competitor in &relevant {
+                    issues.push(format!("{} also intercepts {}", competitor, alias.name()));
+                }
+                if !relevant.is_empty() {
+                    issues.push(format!(
+                        "effective: {}",
+                        competitors::winner(alias.name(), &competitors)
+                    ));
+                }
+            }
+            let conflicts = config.link_conflicts(alias);
+            if alias.wants_symlink(settings) && !conflicts.is_empty() {
+                issues.push(format!(
+                    "links to the same file as {} (rename one)",
+                    conflicts.join(", ")
+                ));
+            }
+            if alias.links() && settings.is_protected(alias.name()) {
+                issues.push(format!(
+                    "{} is protected; a broken shadow can lock you out of fixing it",
+                    alias.name()
+                ));
+            }
+            let missing = alias
+                .program()
+                .filter(|program| which::find(program).is_none());
+            if let Some(program) = missing {
+                issues.push(format!("replacement '{}' is not installed", program));
+            }
+            let installed = alias.program().and_then(which::find);
+            if let Some(problem) = installed.as_deref().and_then(gatekeeper::problem) {
+                issues.push(problem);
+            }
+
+            checkup.aliases.push(AliasCheckup {
+                name: alias.name().to_string(),
+                command: alias.command().to_string(),
+                shown: alias.to_string(),
+                issues,
+                missing: missing.map(str::to_string),
+            });
+        }
+
+        if !self.scan.is_empty() {
+            let linked: Vec<_> = aliases.iter().copied().filter(|a| a.links()).collect();
+            let bypasses = bypass::scan(&self.scan, &linked).into_iter().map(|bypass| {
+                format!(
+                    "{}:{}: {} runs the original {}",
+                    bypass.file.display(),
+                    bypass.line,
+                    bypass.path,
+                    bypass.alias
+                )
+            });
+            checkup.bypasses = Some(bypasses.collect());
+        }
+        checkup
+    }
+
+    /// Run interactively, offer to fix bin directories others can write to.
+    fn offer_chmod(&self, config: &Config) {
+        if !io::stdin().is_terminal() {
+            return;
+        }
+        for dir in bin_dirs(config) {
+            if paths::world_writable(dir)
+                && confirm(&format!(
+                    "Remove write permission for others on {}?",
+                    dir.display()
+                ))
+            {
+                match paths::remove_world_write(dir) {
+                    Ok(()) => println!("Ran chmod o-w {}", dir.display()),
+                    Err(e) => log::error(format!("Failed to change {}: {}", dir.display(), e)),
+                }
+            }
+        }
+    }
+
+    /// `shdw doctor --host`: every host's checkup, gathered at once over
+    /// SSH from the shdw on its PATH.
+    fn check_hosts(&self) -> ExitCode {
+        let checkups: Vec<_> = thread::scope(|scope| {
+            let running: Vec<_> = self
+                .host
+                .iter()
+                .map(|host| scope.spawn(move || remote_checkup(host, &self.scan)))
+                .collect();
+            running
+                .into_iter()
+                .map(|checkup| {
+                    checkup
+                        .join()
+                        .unwrap_or_else(|_| Err("doctor failed".to_string()))
+                })
+                .collect()
+        });
+        let hosts = self.host.iter().map(String::as_str).zip(&checkups);
+
+        match self.format {
+            Format::Json => {
+                let checkups = hosts.clone().map(|(host, checkup)| match checkup {
+                    Ok(checkup) => (host, checkup.to_json()),
+                    Err(e) => (host, Value::object([("error", Value::from(e.as_str()))])),
+                });
+                println!("{}", Value::object(checkups));
+            }
+            Format::Human => {
+                let terminal = output::terminal();
+                for (host, checkup) in hosts.clone() {
+                    println!("{}", terminal.paint(Style::Bold, host));
+                    match checkup {
+                        Ok(checkup) => checkup.print(None),
+                        Err(e) => println!("{} {}", terminal.mark(false), e),
+                    }
+                }
+                if self.host.len() > 1 {
+                    println!();
+                    for (host, checkup) in hosts.clone() {
+                        match checkup {
+                            Ok(checkup) if checkup.count() == 0 => println!("{}: ok", host),
+                            Ok(checkup) => println!(
+                                "{}: {} problem{}",
+                                host,
+                                checkup.count(),
+                                if checkup.count() == 1 { "" } else { "s" }
+                            ),
+                            Err(_) => println!("{}: not checked", host),
+                        }
+                    }
+                }
+            }
+        }
+        let healthy =
+            |checkup: &Result<Checkup, String>| checkup.as_ref().is_ok_and(|c| c.count() == 0);
+        match checkups.iter().all(healthy) {
+            true => ExitCode::Success,
+            false => ExitCode::GeneralError,
+        }
+    }
+}
+
+/// The bin directories aliases link into.
+fn bin_dirs(config: &Config) -> BTreeSet<&Path> {
+    let settings = config.settings();
+    std::iter::once(settings.bin_path().as_path())
+        .chain(
+            config
+                .aliases()
+                .values()
+                .filter(|alias| alias.wants_symlink(settings))
+                .map(|alias| alias.link_dir(settings)),
+        )
+        .collect()
+}
+
+/// `host`'s checkup, from `shdw doctor --format json` run there. It exits
+/// non-zero on any problem, so only output that doesn't parse is a failure.
+fn remote_checkup(host: &str, scan: &[PathBuf]) -> Result<Checkup, String> {
+    // ssh hands the remote shell one command line.
+    let mut remote = "shdw doctor --format json".to_string();
+    for path in scan {
+        remote.push_str(&format!(
+            " --scan {}",
+            quote::posix(&path.to_string_lossy())
+        ));
+    }
+    let output = std::process::Command::new("ssh")
+        .args(["-o", "BatchMode=yes", "--", host, &remote])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("could not run ssh: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Value::parse(&stdout)
+        .ok()
+        .and_then(|value| Checkup::from_json(&value))
+        .ok_or_else(|| {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            format!(
+                "ssh {} exited with {}: {}",
+                host,
+                output.status,
+                stderr.trim()
+            )
+        })
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct InstallMissing {
+    /// Print an install script instead of running it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl InstallMissing {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let aliases: Vec<_> = config.aliases().values().collect();
+        let managers = Manager::detect();
+
+        let mut commands = Vec::new();
+        let mut unknown = Vec::new();
+        for program in missing_replacements(&aliases).into_keys() {
+            match Package::find(program)
+                .and_then(|package| package.install_commands(&managers).into_iter().next())
+            {
+                Some(command) => commands.push(command),
+                None => unknown.push(program),
+            }
+        }
+
+        if self.dry_run {
+            println!("#!/bin/sh");
+            println!("set -e");
+            for command in &commands {
+                println!("{}", command.join(" "));
+            }
+            for program in &unknown {
+                println!("# no known package for {}", program);
+            }
+            return ExitCode::Success;
+        }
+
+        if commands.is_empty() && unknown.is_empty() {
+            println!("All replacements are installed");
+            return ExitCode::Success;
+        }
+
+        let mut code = ExitCode::Success;
+        for command in &commands {
+            println!("Running: {}", command.join(" "));
+            let status = std::process::Command::new(&command[0])
+                .args(&command[1..])
+                .status();
+            if !status.is_ok_and(|status| status.success()) {
+                log::error(format!("Failed: {}", command.join(" ")));
+                code = ExitCode::CommandFailed;
+            }
+        }
+        for program in &unknown {
+            log::error(format!(
+                "No known package for {}, install it manually",
+                program
+            ));
+            code = ExitCode::CommandNotFound;
+        }
+        code
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct DiffConfig {
+    /// Config before, e.g. the current one
+    old: PathBuf,
+    /// Config after, e.g. one pulled into a dotfiles repository
+    new: PathBuf,
+}
+
+impl DiffConfig {
+    pub fn execute(&self, _config: Config) -> ExitCode {
+        let load = |path: &PathBuf| {
+            Config::from_file(path).map_err(|e| {
+                log::error(format!("{}: {}", path.display(), e));
+                ExitCode::from(e)
+            })
+        };
+        let (old, new) = match (load(&self.old), load(&self.new)) {
+            (Ok(old), Ok(new)) => (old, new),
+            (Err(code), _) | (_, Err(code)) => return code,
+        };
+
+        let terminal = output::terminal();
+        let arrow = terminal.arrow();
+        let (added, removed, changed) = (
+            terminal.paint(Style::Green, "+"),
+            terminal.paint(Style::Red, "-"),
+            terminal.paint(Style::Yellow, "~"),
+        );
+        let print_keys = |keys: &[diff::KeyChange]| {
+            for key in keys {
+                println!(
+                    "    {}: {} {} {}",
+                    key.key,
+                    key.old.as_deref().unwrap_or("(unset)"),
+                    arrow,
+                    key.new.as_deref().unwrap_or("(unset)")
+                );
+            }
+        };
+        let changes = diff::aliases(&old, &new);
+        for change in &changes {
+            match change {
+                diff::Change::Added { name, command } => {
+                    println!("{} {} {} {}", added, name, arrow, command)
+                }
+                diff::Change::Removed { name, command } => {
+                    println!("{} {} {} {}", removed, name, arrow, command)
+                }
+                diff::Change::Changed { name, keys } => {
+                    println!("{} {}", changed, name);
+                    print_keys(keys);
+                }
+            }
+        }
+        let settings = diff::settings(&old, &new);
+        if !settings.is_empty() {
+            println!("{} [settings]", changed);
+            print_keys(&settings);
+        }
+        if changes.is_empty() && settings.is_empty() {
+            println!("No differences");
+        }
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct PresetCommand {
+    #[command(subcommand)]
+    command: PresetSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum PresetSubcommand {
+    /// Write some of your aliases to a preset file for others to apply
+    Create(PresetCreate),
+    /// Merge the aliases of a preset file into your config
+    Apply(PresetApply),
+}
+
+impl PresetCommand {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        match &self.command {
+            PresetSubcommand::Create(cmd) => cmd.execute(config),
+            PresetSubcommand::Apply(cmd) => cmd.execute(config),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct PresetCreate {
+    /// File to write the preset to
+    file: PathBuf,
+    /// Aliases to include (default: all of them)
+    aliases: Vec<String>,
+    /// Name of the preset (default: the file name)
+    #[arg(long)]
+    name: Option<String>,
+    /// What the preset is for
+    #[arg(long)]
+    description: Option<String>,
+    /// Who made it
+    #[arg(long)]
+    author: Option<String>,
+    /// Replace the file if it exists
+    #[arg(long)]
+    force: bool,
+}
+
+impl PresetCreate {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let aliases: Vec<&Alias> = match self.aliases.is_empty() {
+            true => {
+                let mut aliases: Vec<_> = config.aliases().values().collect();
+                aliases.sort_by(|a, b| a.name().cmp(b.name()));
+                aliases
+            }
+            false => {
+                let found: crate::error::Result<Vec<_>> = self
+                    .aliases
+                    .iter()
+                    .map(|name| config.aliases().find(name))
+                    .collect();
+                match found {
+                    Ok(aliases) => aliases,
+                    Err(e) => {
+                        log::error(&e);
+                        return e.into();
+                    }
+                }
+            }
+        };
+        if aliases.is_empty() {
+            log::error("No aliases to put in the preset");
+            return ExitCode::CommandNotFound;
+        }
+        if self.file.exists() && !self.force {
+            log::error(format!(
+                "{} exists; pass --force to replace it",
+                self.file.display()
+            ));
+            return ExitCode::GeneralError;
+        }
+
+        let name = self.name.clone().unwrap_or_else(|| {
+            self.file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "preset".to_string())
+        });
+        let metadata = preset::Metadata {
+            name,
+            description: self.description.clone(),
+            author: self.author.clone(),
+        };
+        let contents = match preset::to_toml(&metadata, &aliases) {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::error(&e);
+                return e.into();
+            }
+        };
+        if let Err(e) = fs::write(&self.file, contents) {
+            log::error(format!("Failed to write {}: {}", self.file.display(), e));
+            return ExitCode::GeneralError;
+        }
+        let noun = if aliases.len() == 1 {
+            "alias"
+        } else {
+            "aliases"
+        };
+        println!(
+            "Wrote preset {} with {} {} to {}",
+            metadata.name,
+            aliases.len(),
+            noun,
+            self.file.display()
+        );
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct PresetApply {
+    #[command(flatten)]
+    merge: Merge,
+}
+
+impl PresetApply {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let file = &self.merge.file;
+        let metadata = fs::read_to_string(file)
+            .map_err(ShadowError::from)
+            .and_then(|contents| preset::metadata(&contents));
+        match metadata {
+            Ok(Some(metadata)) => {
+                let by = match &metadata.author {
+                    Some(author) => format!(" by {}", author),
+                    None => String::new(),
+                };
+                println!("Preset {}{}", metadata.name, by);
+                if let Some(description) = &metadata.description {
+                    println!("  {}", description);
+                }
+            }
+            Ok(None) => {
+                log::error(format!(
+                    "{} is not a preset (it has no [preset] table); use `shdw merge` for config files",
+                    file.display()
+                ));
+                return ExitCode::ConfigError;
+            }
+            Err(e) => {
+                log::error(format!("{}: {}", file.display(), e));
+                return e.into();
+            }
+        }
+        self.merge.execute(config)
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct SubscriptionCommand {
+    #[command(subcommand)]
+    command: SubscriptionSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum SubscriptionSubcommand {
+    /// Fetch the latest aliases of your subscriptions
+    Update(SubscriptionUpdate),
+}
+
+impl SubscriptionCommand {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        match &self.command {
+            SubscriptionSubcommand::Update(cmd) => cmd.execute(config),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct SubscriptionUpdate {
+    /// Subscriptions to update (default: all of them)
+    names: Vec<String>,
+}
+
+impl SubscriptionUpdate {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let subscriptions = config.subscriptions();
+        if subscriptions.is_empty() {
+            println!("No subscriptions; add them under [subscriptions] in config.toml");
+            return ExitCode::Success;
+        }
+        if let Some(unknown) = self
+            .names
+            .iter()
+            .find(|name| !subscriptions.contains_key(*name))
+        {
+            log::error(format!("No subscription named {}", unknown));
+            return ExitCode::CommandNotFound;
+        }
+
+        let mut code = ExitCode::Success;
+        for (name, url) in subscriptions {
+            if !self.names.is_empty() && !self.names.contains(name) {
+                continue;
+            }
+            match subscription::update(name, url) {
+                Ok(count) => {
+                    let noun = if count == 1 { "alias" } else { "aliases" };
+                    println!("Updated {}: {} {}", name, count, noun);
+                }
+                Err(e) => {
+                    log::error(format!("Failed to update {}: {}", name, e));
+                    code = ExitCode::GeneralError;
+                }
+            }
+        }
+        // Shims would otherwise keep running what they last cached.
+        let _ = fs::remove_dir_all(state::file("dispatch"));
+        println!("Run `shdw bootstrap` to link new aliases");
+        code
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Merge {
+    /// Config file (or fragment with just `[aliases]`) to fold into yours
+    file: PathBuf,
+    /// What to do with aliases both files define differently
+    #[arg(long, value_enum, default_value_t = OnConflict::Prompt)]
+    on_conflict: OnConflict,
+    /// Also create the symlinks for merged aliases
+    #[arg(long)]
+    link: bool,
+    /// Only report what would be merged
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum OnConflict {
+    /// Keep your alias
+    Skip,
+    /// Replace your alias with theirs
+    Theirs,
+    /// Ask about each one (skips them without a terminal)
+    Prompt,
+}
+
+impl Merge {
+    pub fn execute(&self, mut config: Config) -> ExitCode {
+        let theirs = match Config::from_file(&self.file) {
+            Ok(theirs) => theirs,
+            Err(e) => {
+                log::error(format!("{}: {}", self.file.display(), e));
+                return e.into();
+            }
+        };
+
+        let interactive = !self.dry_run && io::stdin().is_terminal();
+        let mut take = Vec::new();
+        let mut conflicts = 0;
+        for change in diff::aliases(&config, &theirs) {
+            match change {
+                diff::Change::Added { name, command } => {
+                    println!("+ {} → {}", name, command);
+                    take.push(name);
+                }
+                // Aliases only you have stay as they are.
+                diff::Change::Removed { .. } => {}
+                diff::Change::Changed { name, keys } => {
+                    conflicts += 1;
+                    println!("! {} differs:", name);
+                    for key in &keys {
+                        println!(
+                            "    {}: {} (yours) vs {} (theirs)",
+                            key.key,
+                            key.old.as_deref().unwrap_or("(unset)"),
+                            key.new.as_deref().unwrap_or("(unset)")
+                        );
+                    }
+                    let theirs_wins = match self.on_conflict {
+                        OnConflict::Skip => false,
+                        OnConflict::Theirs => true,
+                        OnConflict::Prompt if interactive => {
+                            confirm(&format!("  Replace your {} with theirs?", name))
+                        }
+                        OnConflict::Prompt => false,
+                    };
+                    match theirs_wins {
+                        true => take.push(name),
+                        false => println!("  keeping yours"),
+                    }
+                }
+            }
+        }
+        if self.on_conflict == OnConflict::Prompt && !interactive && conflicts > 0 {
+            let noun = if conflicts == 1 { "alias" } else { "aliases" };
+            println!(
+                "Kept {} conflicting {}; pass --on-conflict theirs to take theirs",
+                conflicts, noun
+            );
+        }
+        if take.is_empty() {
+            println!("Nothing to merge");
+            return ExitCode::Success;
+        }
+        if self.dry_run {
+            return ExitCode::Success;
+        }
+
+        let mut code = ExitCode::Success;
+        let mut added = Vec::new();
+        for name in &take {
+            // Resolved, as their templates don't come along.
+            let Some(alias) = theirs.aliases().get(name).map(Alias::frozen) else {
+                continue;
+            };
+            // A subscribed alias is taken over rather than removed. Yours is
+            // only removed once theirs is known to be addable.
+            let replaced = config.owns(name);
+            let result = config
+                .check_protected(&alias)
+                .and_then(|_| match replaced {
+                    true => config.remove(name),
+                    false => Ok(()),
+                })
+                .and_then(|_| config.add(alias.clone()));
+            if let Err(e) = result {
+                log::error(format!("Failed to merge {}: {}", name, e));
+                code = e.into();
+                continue;
+            }
+            println!(
+                "{} alias: {}",
+                if replaced { "Replaced" } else { "Added" },
+                name
+            );
+            added.push(name.as_str());
+            let mut wants_symlink = self.link && alias.wants_symlink(config.settings());
+            if wants_symlink {
+                match link_or_defer(&alias, config.settings()) {
+                    Ok(linked) => wants_symlink = linked,
+                    Err(e) => {
+                        log::error(format!("Failed to link {}: {}", name, e));
+                        code = e.into();
+                    }
+                }
+            }
+            update_state(|state| {
+                if wants_symlink {
+                    state.record_link(name, alias.link_file(config.settings()));
+                }
+                state.record_alias(&alias, Source::Import);
+            });
+        }
+        sync_functions(&config);
+        if !added.is_empty() {
+            config.settings().hooks().run(Event::Add, &added);
+        }
+        if !self.link && config.settings().backend() == Backend::Symlink {
+            println!("Run `shdw bootstrap` to create their symlinks");
+        }
+        code
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Help {
+    /// Alias or shdw command to explain
+    name: Option<String>,
+}
+
+impl Help {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let mut command = Cli::command().bin_name("shdw");
+        command.build();
+        let Some(name) = &self.name else {
+            let _ = command.print_help();
+            return ExitCode::Success;
+        };
+        // shdw's own commands keep their usual help; anything else is an
+        // alias, documented like a small man page.
+        if let Some(subcommand) = command.find_subcommand_mut(name) {
+            let _ = subcommand.print_help();
+            return ExitCode::Success;
+        }
+        let Some(alias) = config.aliases().get(name) else {
+            log::error(format!("No alias or shdw command named {}", name));
+            return ExitCode::CommandNotFound;
+        };
+        Self::print_alias(alias)
+    }
+
+    /// The page `shdw help <alias>` prints, which a shim also prints for
+    /// `--shdw-help`.
+    pub fn print_alias(alias: &Alias) -> ExitCode {
+        println!("{} → {}", alias.name(), alias.command());
+        if let Some(description) = alias.description() {
+            println!("  {}", description);
+        }
+        if let Some(notes) = alias.notes() {
+            println!();
+            for line in notes.trim_end().lines() {
+                println!("  {}", line);
+            }
+        }
+        if !alias.examples().is_empty() {
+            println!();
+            println!("Examples:");
+            for example in alias.examples() {
+                println!("  {}", example);
+            }
+        }
+        println!();
+        if alias.links() {
+            println!(
+                "Run `{} --shdw-raw ...` for the original {}.",
+                alias.name(),
+                alias.name()
+            );
+            println!("--shdw-why prints what would run instead, --shdw-trace as it runs.");
+        }
+        println!("Run `shdw show {}` for details.", alias.name());
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+#[command(group(ArgGroup::new("target").required(true).args(["archive", "direnv", "eval", "fish_abbr"])))]
+pub struct Export {
+    /// Write the config and its metadata to this .tar.gz, for `shdw import
+    /// --archive` on another machine
+    #[arg(long, value_name = "FILE")]
+    archive: Option<PathBuf>,
+    /// Print a .envrc snippet that activates the aliases in the project's
+    /// .shdw/config.toml while direnv has the directory loaded
+    #[arg(long)]
+    direnv: bool,
+    /// Print a function per alias for `eval`, for machines where symlinks
+    /// can't be created
+    #[arg(long)]
+    eval: bool,
+    /// Shell to print functions for with --eval (defaults to the current one)
+    #[arg(long, value_enum, requires = "eval")]
+    shell: Option<Shell>,
+    /// Print an `abbr` per alias for fish, skipping aliases that need a shim
+    #[arg(long)]
+    fish_abbr: bool,
+}
+
+/// For `shdw export --direnv`. The project's aliases are layered over the
+/// user's, so every other alias keeps working inside the project; direnv
+/// restores the variable and `PATH` on leaving the directory, which is all
+/// deactivating takes.
+const ENVRC: &str = r#"# shdw: project-local aliases from .shdw/config.toml
+export SHDW_PROJECT_CONFIG="$PWD/.shdw/config.toml"
+PATH_add .shdw/bin
+watch_file .shdw/config.toml
+shdw sync --project >&2
+"#;
+
+impl Export {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        if self.eval {
+            let shell = self.shell.unwrap_or_else(Shell::detect);
+            print!(
+                "{}",
+                init::eval_functions(shell, &config, &shdw_command(shell))
+            );
+            return ExitCode::Success;
+        }
+        if self.fish_abbr {
+            print!("{}", init::fish_abbreviations(&config));
+            return ExitCode::Success;
+        }
+        let Some(archive) = &self.archive else {
+            print!("{}", ENVRC);
+            return ExitCode::Success;
+        };
+        match archive::export(&config, archive) {
+            Ok(()) => {
+                println!(
+                    "Exported {} aliases to {}",
+                    config.aliases().len(),
+                    archive.display()
+                );
+                ExitCode::Success
+            }
+            Err(e) => {
+                log::error(format!("Failed to export: {}", e));
+                ExitCode::GeneralError
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Bake {
+    /// Config file to bake in (default: the current config)
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Where to write the baked binary
+    #[arg(long, value_name = "FILE")]
+    out: PathBuf,
+}
+
+impl Bake {
+    pub fn execute(&self, _config: Config) -> ExitCode {
+        let path = self.config.clone().unwrap_or_else(Config::config_path);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::error(format!("Failed to read {}: {}", path.display(), e));
+                return ExitCode::ConfigError;
+            }
+        };
+        if let Err(e) = Config::from_baked(&contents) {
+            log::error(format!("{}: {}", path.display(), e));
+            return ExitCode::ConfigError;
+        }
+        let written = env::current_exe().and_then(|exe| bake::write(&exe, &contents, &self.out));
+        if let Err(e) = written {
+            log::error(format!("Failed to write {}: {}", self.out.display(), e));
+            return ExitCode::GeneralError;
+        }
+        // The config invalidates the signature it was copied with, and Apple
+        // silicon kills unsigned binaries on launch.
+        if let Err(e) = gatekeeper::sign(&self.out) {
+            warning::warn(format!(
+                "could not sign {} ({}), so macOS may kill it on launch (\"killed: 9\")",
+                self.out.display(),
+                e
+            ));
+        }
+        println!("Baked {} into {}", path.display(), self.out.display());
+        println!(
+            "Run `{} install-multicall` where it's installed to link its aliases",
+            self.out.display()
+        );
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Grep {
+    /// Regular expression to look for
+    pattern: String,
+    /// Match regardless of case
+    #[arg(short, long)]
+    ignore_case: bool,
+}
+
+impl Grep {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let pattern = match self.pattern.parse::<Pattern>() {
+            Ok(pattern) if self.ignore_case => pattern.ignoring_case(),
+            Ok(pattern) => pattern,
+            Err(e) => {
+                log::error(&e);
+                return ExitCode::InvalidArguments;
+            }
+        };
+        let terminal = output::terminal();
+
+        let mut aliases: Vec<_> = config.aliases().values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+        let mut found = false;
+        for alias in aliases {
+            let mut fields = vec![
+                ("name".to_string(), alias.name().to_string()),
+                ("command".to_string(), alias.command().to_string()),
+            ];
+            if let Some(description) = alias.description() {
+                fields.push(("description".to_string(), description.to_string()));
+            }
+            for (name, value) in alias.env() {
+                let value = match value {
+                    EnvValue::Plain(value) => value.clone(),
+                    // The source, never the secret itself.
+                    EnvValue::Secret(source) => format!("from {}", source),
+                };
+                fields.push((format!("env {}", name), format!("{}={}", name, value)));
+            }
+
+            for (field, text) in fields {
+                let Some((start, end)) = pattern.find(&text) else {
+                    continue;
+                };
+                found = true;
+                let text = format!(
+                    "{}{}{}",
+                    &text[..start],
+                    terminal.paint(Style::Match, &text[start..end]),
+                    &text[end..]
+                );
+                println!("{}: {}: {}", alias.name(), field, text);
+            }
+        }
+        match found {
+            true => ExitCode::Success,
+            false => ExitCode::GeneralError,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct WhichWins {
+    /// Command name to resolve
+    name: String,
+}
+
+impl WhichWins {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let matches = which::find_all(&self.name);
+        if matches.is_empty() {
+            println!("No executable named {} on PATH", self.name);
+            return ExitCode::CommandNotFound;
+        }
+
+        let alias = config.aliases().get(&self.name);
+        println!("PATH resolution for {}:", self.name);
+        for (i, path) in matches.iter().enumerate() {
+            let owner = if which::is_shadow(path) {
+                match alias {
+                    Some(alias) => format!(" (shadow → {})", alias.command()),
+                    None => " (shadow, but no alias configured)".to_string(),
+                }
+            } else if let Some(manager) = which::shim_manager(path) {
+                format!(" ({} shim)", manager)
+            } else {
+                String::new()
+            };
+            let wins = if i == 0 { "  ← wins" } else { "" };
+            println!("  {}. {}{}{}", i + 1, path.display(), owner, wins);
+        }
+
+        let shadow = matches.iter().position(|path| which::is_shadow(path));
+        println!();
+        match (alias, shadow) {
+            (None, _) => println!("{} is not shadowed", self.name),
+            (Some(_), Some(0)) => println!("The shadow wins"),
+            (Some(alias), Some(_)) => println!(
+                "{} comes first, so the shadow is bypassed. Move {} earlier on PATH.",
+                matches[0].display(),
+                alias.link_dir(config.settings()).display()
+            ),
+            (Some(alias), None) => println!(
+                "The shadow symlink is not on PATH. Run `shdw bootstrap` or add {} to PATH.",
+                alias.link_dir(config.settings()).display()
+            ),
+        }
+
+        let competing: Vec<_> = matches
+            .iter()
+            .filter_map(|path| which::shim_manager(path).map(|manager| (manager, path)))
+            .collect();
+        for (manager, path) in competing {
+            println!(
+                "Note: {} also manages {} ({})",
+                manager,
+                self.name,
+                path.display()
+            );
+        }
+
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct ExitCodes;
+
+impl ExitCodes {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let overrides = config.settings().exit_codes();
+        println!("{:<6}{:<20}Meaning", "Code", "Name");
+        for code in ExitCode::ALL {
+            let remapped = match overrides.get(&code) {
+                Some(_) => format!(" (remapped from {})", code as i32),
+                None => String::new(),
+            };
+            println!(
+                "{:<6}{:<20}{}{}",
+                code.code(overrides),
+                code.name(),
+                code.description(),
+                remapped
+            );
+        }
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Completions {
+    /// Shell to generate completions for
+    shell: Shell,
+}
+
+impl Completions {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let mut aliases: Vec<_> = config.aliases().values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+        match completions::generate(self.shell, &aliases) {
+            Some(script) => {
+                print!("{}", script);
+                ExitCode::Success
+            }
+            None => {
+                log::error(format!(
+                    "Completions are not supported for {:?}",
+                    self.shell
+                ));
+                ExitCode::InvalidArguments
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Init {
+    /// Shell to generate the startup snippet for
+    shell: Shell,
+    /// Also define each alias as a shell function (PowerShell only), for
+    /// machines where symlinks can't be created
+    #[arg(long)]
+    functions: bool,
+}
+
+impl Init {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let subcommands: Vec<String> = Cli::command()
+            .get_subcommands()
+            .filter(|cmd| !cmd.is_hide_set())
+            .map(|cmd| cmd.get_name().to_string())
+            .collect();
+
+        print!(
+            "{}",
+            init::generate(self.shell, &config, &subcommands, self.functions)
+        );
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct SyncCommand {
+    /// Link the project's aliases into the bin directory next to its config,
+    /// .shdw/bin for $SHDW_PROJECT_CONFIG, and drop links for removed ones;
+    /// see `shdw export --direnv`
+    #[arg(long)]
+    project: bool,
+}
+
+impl SyncCommand {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        if self.project {
+            return self.sync_project(&config);
+        }
+        if config.settings().backend() != Backend::Shell {
+            println!("settings.backend is \"symlink\"; `shdw bootstrap` creates the symlinks");
+            return ExitCode::Success;
+        }
+        match init::sync(&config) {
+            Ok(paths) => {
+                for path in &paths {
+                    println!("Wrote {}", path.display());
+                }
+                let shell = Shell::detect();
+                match init::functions_file(shell) {
+                    Some(path) => println!(
+                        "Run `source {}` to update the functions in open shells",
+                        path.display()
+                    ),
+                    None => println!("Open a new shell to update the functions"),
+                }
+                config.settings().hooks().run(Event::Sync, &[]);
+                ExitCode::Success
+            }
+            Err(e) => {
+                log::error(format!("Failed to write alias functions: {}", e));
+                ExitCode::GeneralError
+            }
+        }
+    }
+
+    fn sync_project(&self, config: &Config) -> ExitCode {
+        let Some(project) = Config::project_path() else {
+            log::error(
+                "--project needs SHDW_PROJECT_CONFIG set to the project's .shdw/config.toml, as \
+                 the snippet from `shdw export --direnv` does",
+            );
+            return ExitCode::InvalidArguments;
+        };
+        let dir = project.with_file_name("bin");
+        let settings = config.settings().clone().with_bin_path(dir.clone());
+        let mut code = ExitCode::Success;
+        let mut linked = 0;
+        let aliases = config.aliases().values();
+        for alias in aliases.filter(|alias| alias.is_project() && alias.links()) {
+            match alias.create_symlink(&settings) {
+                Ok(()) => linked += 1,
+                Err(e) => {
+                    log::error(format!("Failed to link {}: {}", alias.name(), e));
+                    code = ExitCode::GeneralError;
+                }
+            }
+        }
+        let entries = fs::read_dir(&dir).into_iter().flatten().flatten();
+        for entry in entries {
+            let path = entry.path();
+            // On Windows shims are `name.exe`.
+            let name = match cfg!(windows) {
+                true => path.file_stem(),
+                false => path.file_name(),
+            };
+            let gone = name.and_then(|name| name.to_str()).is_none_or(|name| {
+                config
+                    .aliases()
+                    .get(name)
+                    .is_none_or(|alias| !alias.is_project() || !alias.links())
+            });
+            if gone && which::is_shadow(&path) {
+                if let Err(e) = fs::remove_file(&path) {
+                    log::error(format!("Failed to remove {}: {}", path.display(), e));
+                    code = ExitCode::GeneralError;
+                }
+            }
+        }
+        println!("Linked {} aliases in {}", linked, dir.display());
+        config.settings().hooks().run(Event::Sync, &[]);
+        code
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Info {
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+impl Info {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let settings = config.settings();
+        let mut aliases: Vec<_> = config.aliases().values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let bin_paths: BTreeSet<&Path> = std::iter::once(settings.bin_path().as_path())
+            .chain(
+                aliases
+                    .iter()
+                    .filter(|alias| alias.links())
+                    .map(|alias| alias.link_dir(settings)),
+            )
+            .collect();
+        let disabled = aliases.iter().filter(|alias| !alias.links()).count();
+        let ok = aliases
+            .iter()
+            .filter(|alias| {
+                alias.links()
+                    && matches!(alias.link_state(settings), LinkState::Linked(_))
+                    && alias.program().and_then(which::find).is_some()
+            })
+            .count();
+        let broken = aliases.len() - ok - disabled;
+        let binary = env::current_exe().ok();
+        let platform = format!("{}-{}", env::consts::OS, env::consts::ARCH);
+        let provenance = Provenance::current();
+        let mut exit_codes: Vec<_> = settings.exit_codes().iter().collect();
+        exit_codes.sort_by_key(|(code, _)| code.name());
+
+        if self.format == Format::Json {
+            let report =
+                Value::object([
+                    ("version", Value::from(provenance.version)),
+                    ("commit", Value::from(provenance.commit)),
+                    ("target", Value::from(provenance.target)),
+                    ("config_path", Value::from(Config::config_path().as_path())),
+                    ("config_version", Value::Number(config.version().into())),
+                    ("platform", Value::from(platform)),
+                    ("binary", Value::from(binary.as_deref())),
+                    (
+                        "settings",
+                        Value::object([
+                            ("bin_path", Value::from(settings.bin_path().as_path())),
+                            ("always_use_raw", Value::from(settings.always_use_raw())),
+                            (
+                                "exit_codes",
+                                Value::object(exit_codes.iter().map(|(code, value)| {
+                                    (code.name(), Value::from(**value as i64))
+                                })),
+                            ),
+                        ]),
+                    ),
+                    (
+                        "bin_paths",
+                        Value::Array(
+                            bin_paths
+                                .iter()
+                                .map(|dir| {
+                                    Value::object([
+                                        ("path", Value::from(*dir)),
+                                        (
+                                            "path_position",
+                                            Value::from(which::path_position(dir).map(|p| p + 1)),
+                                        ),
+                                    ])
+                                })
+                                .collect(),
+                        ),
+                    ),
+                    (
+                        "aliases",
+                        Value::object([
+                            ("total", Value::from(aliases.len())),
+                            ("ok", Value::from(ok)),
+                            ("broken", Value::from(broken)),
+                            ("disabled", Value::from(disabled)),
+                        ]),
+                    ),
+                ]);
+            println!("{}", report);
+            return ExitCode::Success;
+        }
+
+        println!("{}", provenance.summary());
+        println!(
+            "config:    {} (version {})",
+            Config::config_path().display(),
+            config.version()
+        );
+        match &binary {
+            Some(binary) => println!("binary:    {}", binary.display()),
+            None => println!("binary:    unknown"),
+        }
+        println!("platform:  {}", platform);
+        println!("settings:  always_use_raw = {}", settings.always_use_raw());
+        for (code, value) in &exit_codes {
+            println!("           exit code {} = {}", code.name(), value);
+        }
+        println!("bin paths:");
+        for dir in &bin_paths {
+            match which::path_position(dir) {
+                Some(position) => {
+                    println!("    {} (PATH position {})", dir.display(), position + 1)
+                }
+                None => println!("    {} (not on PATH)", dir.display()),
+            }
+        }
+        println!(
+            "aliases:   {} total, {} ok, {} broken, {} not linked",
+            aliases.len(),
+            ok,
+            broken,
+            disabled
+        );
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct PromptState {
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+impl PromptState {
+    /// Whether shadows are in effect and how many are broken, for a prompt
+    /// segment. It runs on every prompt, so it only looks at the links and
+    /// PATH, never for the replacements.
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let settings = config.settings();
+        let aliases: Vec<&Alias> = config.aliases().values().filter(|a| a.links()).collect();
+        let mut linked = BTreeSet::new();
+        let mut broken = 0usize;
+        for alias in aliases.iter().filter(|alias| alias.wants_symlink(settings)) {
+            match alias.link_state(settings) {
+                LinkState::Linked(_) => {
+                    linked.insert(alias.link_dir(settings));
+                }
+                _ => broken += 1,
+            }
+        }
+        let active = match settings.backend() {
+            // Whether the shell sourced the functions can't be told from here.
+            Backend::Shell => !aliases.is_empty(),
+            Backend::Symlink => linked.iter().any(|dir| which::path_position(dir).is_some()),
+        };
+
+        if self.format == Format::Json {
+            // shdw has no pausing or profiles yet; the keys are there so
+            // prompt configs written now keep working once it does.
+            let state = Value::object([
+                ("active", Value::from(active)),
+                ("paused", Value::from(false)),
+                ("count", Value::from(aliases.len())),
+                ("broken", Value::from(broken)),
+                ("profile", Value::Null),
+            ]);
+            println!("{}", state);
+            return ExitCode::Success;
+        }
+
+        let mut line = format!("{} shadows", aliases.len());
+        if broken > 0 {
+            line.push_str(&format!(", {} broken", broken));
+        }
+        if !active {
+            line.push_str(" (inactive)");
+        }
+        println!("{}", line);
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct ConfigCommand {
+    #[command(subcommand)]
+    command: ConfigSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum ConfigSubcommand {
+    /// Print a JSON Schema for config.toml
+    Schema(Schema),
+    /// Print the config with templates and inheritance resolved away
+    #[command(visible_alias = "freeze")]
+    Resolve(Resolve),
+    /// Print the settings an alias runs with, and where each comes from
+    Effective(EffectiveSettings),
+}
+
+impl ConfigCommand {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        match &self.command {
+            ConfigSubcommand::Schema(cmd) => cmd.execute(config),
+            ConfigSubcommand::Resolve(cmd) => cmd.execute(config),
+            ConfigSubcommand::Effective(cmd) => cmd.execute(config),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct EffectiveSettings {
+    /// Name of the alias
+    name: String,
+    /// Resolve as for a shim run with --shdw-raw
+    #[arg(long, short = 'R')]
+    raw: bool,
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+impl EffectiveSettings {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let alias = match config.aliases().find(&self.name) {
+            Ok(alias) => alias,
+            Err(e) => {
+                log::error(&e);
+                return e.into();
+            }
+        };
+        let effective = Effective::resolve(alias, config.settings(), self.raw);
+        if self.format == Format::Json {
+            println!("{}", effective.to_json());
+            return ExitCode::Success;
+        }
+        let rows = effective.rows();
+        let width = rows
+            .iter()
+            .map(|(_, value, _)| value.len())
+            .max()
+            .unwrap_or(0);
+        for (name, value, layer) in rows {
+            println!("{:<15} {:<width$}  ({})", name, value, layer, width = width);
+        }
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Resolve;
+
+impl Resolve {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        match config.frozen().to_toml() {
+            Ok(toml) => {
+                print!("{}", toml);
+                ExitCode::Success
+            }
+            Err(e) => {
+                log::error(&e);
+                e.into()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Schema {
+    /// Print an annotated example config using every option instead
+    #[arg(long)]
+    example: bool,
+}
+
+impl Schema {
+    pub fn execute(&self, _config: Config) -> ExitCode {
+        match self.example {
+            true => print!("{}", schema::example()),
+            false => println!("{}", schema::json_schema()),
+        }
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct GroupCommand {
+    #[command(subcommand)]
+    command: GroupSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum GroupSubcommand {
+    /// Run every alias in a group, one after another
+    Run(GroupRun),
+    /// List groups and their aliases
+    #[command(visible_alias = "ls")]
+    List(GroupList),
+}
+
+impl GroupCommand {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        match &self.command {
+            GroupSubcommand::Run(cmd) => cmd.execute(config),
+            GroupSubcommand::List(cmd) => cmd.execute(config),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct GroupRun {
+    /// Name of the group
+    name: String,
+    /// Stop at the first alias that fails
+    #[arg(long)]
+    fail_fast: bool,
+}
+
+impl GroupRun {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let Some(members) = config.group(&self.name) else {
+            log::error(format!("Group not found: {}", self.name));
+            return ExitCode::CommandNotFound;
+        };
+
+        let mut results: Vec<(&str, Option<ExitCode>)> = Vec::new();
+        let mut failed = false;
+        for member in members {
+            if failed && self.fail_fast {
+                results.push((member, None));
+                continue;
+            }
+            let code = match config.aliases().find(member) {
+                Ok(alias) => {
+                    eprintln!("==> {}: {}", member, alias.command());
+                    let effective = Effective::resolve(alias, config.settings(), false);
+                    stats::set_enabled(effective.stats.value);
+                    alias.execute::<&str>(&[], effective.raw.value)
+                }
+                Err(e) => {
+                    eprintln!("==> {}", e);
+                    e.into()
+                }
+            };
+            failed |= code != ExitCode::Success;
+            results.push((member, Some(code)));
+        }
+
+        let succeeded = results
+            .iter()
+            .filter(|(_, code)| *code == Some(ExitCode::Success))
+            .count();
+        let width = members.iter().map(String::len).max().unwrap_or_default();
+        eprintln!();
+        eprintln!(
+            "{}: {} of {} succeeded",
+            self.name,
+            succeeded,
+            results.len()
+        );
+        for (member, code) in &results {
+            match code {
+                Some(code) => eprintln!(
+                    "  {:width$}  {} ({})",
+                    member,
+                    code.name(),
+                    code.code(config.settings().exit_codes()),
+                ),
+                None => eprintln!("  {:width$}  skipped", member),
+            }
+        }
+        match failed {
+            true => ExitCode::CommandFailed,
+            false => ExitCode::Success,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct GroupList;
+
+impl GroupList {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        if config.groups().is_empty() {
+            println!("No groups defined");
+        }
+        for (name, members) in config.groups() {
+            println!("{}: {}", name, members.join(", "));
+        }
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct ResolveCommand {
+    /// Only print the command line, with no explanation when the name
+    /// isn't shadowed
+    #[arg(long, short)]
+    quiet: bool,
+    /// Name of the command
+    name: String,
+    /// Arguments it would be given
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+impl ResolveCommand {
+    /// Prints what running `name args...` would actually run, shell-quoted
+    /// on one line, and exits 0 if a shadow answers it, 1 if it doesn't.
+    pub fn execute(&self, config: Config) -> ExitCode {
+        // As in a shim, `--raw` after `--` is an operand.
+        let mut raw = false;
+        let mut operands = false;
+        let args: Vec<&String> = self
+            .args
+            .iter()
+            .filter(|arg| {
+                operands |= *arg == "--";
+                let flag = !operands && (*arg == "--raw" || *arg == "-R");
+                raw |= flag;
+                !flag
+            })
+            .collect();
+
+        let resolution = match config.aliases().get(&self.name) {
+            Some(alias) => alias.resolve(&args, raw),
+            None => Resolution::original(self.name.clone().into(), &args, "is not a shdw alias"),
+        };
+        Self::report(&self.name, &resolution, self.quiet)
+    }
+
+    /// Print `resolution` as `shdw resolve` does; also what a shim prints for
+    /// `--shdw-why`.
+    pub fn report(name: &str, resolution: &Resolution, quiet: bool) -> ExitCode {
+        println!("{}", Self::command_line(resolution));
+        match resolution.reason() {
+            None => ExitCode::Success,
+            Some(reason) => {
+                if !quiet {
+                    eprintln!("{} {}", name, reason);
+                }
+                ExitCode::GeneralError
+            }
+        }
+    }
+
+    /// `resolution`'s argv, shell-quoted on one line.
+    pub fn command_line(resolution: &Resolution) -> String {
+        let quoted: Vec<String> = resolution
+            .argv()
+            .iter()
+            .map(|arg| quote::posix(&arg.to_string_lossy()))
+            .collect();
+        quoted.join(" ")
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Expand {
+    /// Where the cursor is in the buffer, in characters; where it ends up
+    /// is printed on a second line
+    #[arg(long)]
+    cursor: Option<usize>,
+    /// The command line being edited
+    #[arg(allow_hyphen_values = true)]
+    buffer: String,
+}
+
+impl Expand {
+    /// Prints the buffer with its first word replaced by what that alias
+    /// runs, for shell keybindings that expand aliases in place. Exits 1,
+    /// printing the buffer as it was, when the first word isn't shadowed.
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let chars: Vec<char> = self.buffer.chars().collect();
+        let start = chars.iter().take_while(|c| c.is_whitespace()).count();
+        let len = chars[start..]
+            .iter()
+            .take_while(|c| !c.is_whitespace())
+            .count();
+        let word: String = chars[start..start + len].iter().collect();
+        let resolution = config
+            .aliases()
+            .get(&word)
+            .map(|alias| alias.resolve::<&str>(&[], false))
+            .filter(|resolution| resolution.reason().is_none());
+        let Some(resolution) = resolution else {
+            println!("{}", self.buffer);
+            if let Some(cursor) = self.cursor {
+                println!("{}", cursor);
+            }
+            return ExitCode::GeneralError;
+        };
+
+        let replacement = ResolveCommand::command_line(&resolution);
+        let before: String = chars[..start].iter().collect();
+        let after: String = chars[start + len..].iter().collect();
+        println!("{}{}{}", before, replacement, after);
+        if let Some(cursor) = self.cursor {
+            let grown = replacement.chars().count();
+            let cursor = match cursor {
+                cursor if cursor <= start => cursor,
+                cursor if cursor <= start + len => start + grown,
+                cursor => (cursor + grown - len).min(chars.len() + grown - len),
+            };
+            println!("{}", cursor);
+        }
+        ExitCode::Success
+    }
+}
+
+/// How functions printed for `shell` should call shdw: by name when this
+/// binary is what PATH finds, else by its full path.
+fn shdw_command(shell: Shell) -> String {
+    if which::find("shdw").is_some_and(|path| which::is_shadow(&path)) {
+        return "shdw".to_string();
+    }
+    let Ok(exe) = env::current_exe() else {
+        return "shdw".to_string();
+    };
+    let exe = exe.display().to_string();
+    match shell {
+        Shell::Pwsh => format!("& {}", quote::pwsh(&exe)),
+        _ => quote::word(shell, &exe),
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Run {
+    /// Name of the alias to run
+    name: String,
+    /// Run the original command instead of the replacement
+    #[arg(long, short = 'R')]
+    raw: bool,
+    /// Collect the command's output and print it once it exits, with its
+    /// exit code and how long it took
+    #[arg(long)]
+    capture: bool,
+    /// Output format for --capture
+    #[arg(long, value_enum, default_value_t, requires = "capture")]
+    format: Format,
+    /// Arguments passed to the command
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+impl Run {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let alias = match config.aliases().find(&self.name) {
+            Ok(alias) => alias,
+            Err(e) => {
+                log::error(&e);
+                return e.into();
+            }
+        };
+        let effective = Effective::resolve(alias, config.settings(), self.raw);
+        let raw = effective.raw.value;
+        stats::set_enabled(effective.stats.value);
+        if !self.capture {
+            return alias.execute(&self.args, raw);
+        }
+
+        let captured = match alias.capture(&self.args, raw) {
+            Ok(captured) => captured,
+            Err(code) => return code,
+        };
+        let duration_ms = captured.duration.as_millis() as i64;
+        if self.format == Format::Json {
+            let report = Value::object([
+                (
+                    "stdout",
+                    Value::from(String::from_utf8_lossy(&captured.stdout).into_owned()),
+                ),
+                (
+                    "stderr",
+                    Value::from(String::from_utf8_lossy(&captured.stderr).into_owned()),
+                ),
+                (
+                    "exit_code",
+                    Value::from(captured.exit_code.map(|code| code as i64)),
+                ),
+                ("duration_ms", Value::from(duration_ms)),
+            ]);
+            println!("{}", report);
+        } else {
+            let _ = io::stdout().write_all(&captured.stdout);
+            let _ = io::stderr().write_all(&captured.stderr);
+            let ended = match captured.exit_code {
+                Some(code) => format!("exited with {}", code),
+                None => "was terminated by a signal".to_string(),
+            };
+            log::info(format!("{} {} after {}ms", self.name, ended, duration_ms));
+        }
+        let _ = io::stdout().flush();
+        alias.exit_code(&captured.status)
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Adopt {
+    /// Directory to scan (default: settings.bin_path)
+    dir: Option<PathBuf>,
+    /// Adopt everything found without asking
+    #[arg(long, short)]
+    yes: bool,
+    /// Only show what would be adopted
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl Adopt {
+    pub fn execute(&self, mut config: Config) -> ExitCode {
+        let dir = self
+            .dir
+            .clone()
+            .unwrap_or_else(|| config.settings().bin_path().clone());
+        let (candidates, skipped) = adopt::scan(&dir);
+        for skip in &skipped {
+            println!("Skipping {}: {}", skip.path.display(), skip.reason);
+        }
+        if candidates.is_empty() {
+            println!("Nothing to adopt in {}", dir.display());
+            return ExitCode::Success;
+        }
+
+        let bin_path = (dir != *config.settings().bin_path()).then(|| dir.clone());
+        let backups = state::file("adopted");
+        let mut code = ExitCode::Success;
+        let mut adopted = Vec::new();
+        for candidate in candidates {
+            if config.aliases().contains(&candidate.name) {
+                println!("Skipping {}: already an alias", candidate.name);
+                continue;
+            }
+            let summary = format!(
+                "{} → {} ({} {})",
+                candidate.name,
+                candidate.command,
+                candidate.kind,
+                candidate.path.display()
+            );
+            if self.dry_run {
+                println!("Would adopt {}", summary);
+                continue;
+            }
+            if !self.yes && !confirm(&format!("Adopt {}?", summary)) {
+                continue;
+            }
+
+            let alias = Alias::builder(&candidate.name, candidate.command)
+                .bin_path(bin_path.clone())
+                .build();
+            let alias = match alias.and_then(|alias| config.check_add(&alias).map(|_| alias)) {
+                Ok(alias) => alias,
+                Err(e) => {
+                    log::error(format!("Failed to adopt {}: {}", candidate.name, e));
+                    code = e.into();
+                    continue;
+                }
+            };
+
+            // The symlink replaces the script, so keep a copy of it.
+            if candidate.kind == "wrapper" {
+                let backup = backups.join(candidate.path.file_name().unwrap_or_default());
+                // Removed here, so the symlink doesn't move it aside as well.
+                let copied = fs::create_dir_all(&backups)
+                    .and_then(|_| fs::copy(&candidate.path, &backup))
+                    .and_then(|_| fs::remove_file(&candidate.path));
+                if let Err(e) = copied {
+                    log::error(format!(
+                        "Failed to back up {}: {}",
+                        candidate.path.display(),
+                        e
+                    ));
+                    code = ExitCode::GeneralError;
+                    continue;
+                }
+                println!("Saved {} to {}", candidate.path.display(), backup.display());
+            }
+            let link = alias.link_file(config.settings());
+            let record = alias.clone();
+            let result = alias
+                .create_symlink(config.settings())
+                .and_then(|_| config.add(alias));
+            match result {
+                Ok(()) => {
+                    println!("Adopted {}", candidate.name);
+                    adopted.push(candidate.name.clone());
+                    update_state(|state| {
+                        state.record_link(&candidate.name, link);
+                        state.record_alias(&record, Source::Adopt);
+                    });
+                }
+                Err(e) => {
+                    log::error(format!("Failed to adopt {}: {}", candidate.name, e));
+                    code = e.into();
+                }
+            }
+        }
+        if !adopted.is_empty() {
+            let adopted: Vec<_> = adopted.iter().map(String::as_str).collect();
+            config.settings().hooks().run(Event::Add, &adopted);
+        }
+        code
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct CheckConfig {
+    /// Config file to check (default: the current config)
+    path: Option<PathBuf>,
+}
+
+impl CheckConfig {
+    pub fn execute(&self, _config: Config) -> ExitCode {
+        let path = self.path.clone().unwrap_or_else(Config::config_path);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::error(format!("Failed to read {}: {}", path.display(), e));
+                return ExitCode::ConfigError;
+            }
+        };
+
+        let mut problems = Vec::new();
+        match toml::from_str::<toml::Table>(&contents) {
+            Ok(table) => problems.extend(
+                schema::unknown_keys(&table)
+                    .into_iter()
+                    .map(|key| format!("Unknown key '{}'", key)),
+            ),
+            Err(e) => problems.push(e.to_string()),
+        }
+        if problems.is_empty() {
+            match Config::from_file(&path) {
+                Ok(config) => {
+                    problems.extend(config.validate());
+                    problems.extend(config.link_collisions());
+                }
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+
+        if problems.is_empty() {
+            println!("{} is valid", path.display());
+            return ExitCode::Success;
+        }
+        for problem in &problems {
+            println!("{}: {}", path.display(), problem.trim_end());
+        }
+        ExitCode::ConfigError
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Lint {
+    /// Rewrite the commands that have a safe fix, e.g. dropping quotes shdw
+    /// would pass on
+    #[arg(long)]
+    fix: bool,
+}
+
+impl Lint {
+    pub fn execute(&self, mut config: Config) -> ExitCode {
+        let lints = lint::lint(&config);
+        if lints.is_empty() {
+            println!("Nothing to lint in {} aliases", config.aliases().len());
+            return ExitCode::Success;
+        }
+
+        // A later fix for an alias builds on its earlier ones.
+        let mut fixes = BTreeMap::new();
+        let mut left = 0;
+        for lint in &lints {
+            match (&lint.fix, self.fix) {
+                (Some(fix), true) => {
+                    println!("{}: {} (fixed)", lint.alias, lint.message);
+                    fixes.insert(lint.alias.as_str(), fix.as_str());
+                }
+                (Some(fix), false) => {
+                    println!("{}: {}", lint.alias, lint.message);
+                    println!("  --fix: command = {}", toml::Value::from(fix.as_str()));
+                    left += 1;
+                }
+                (None, _) => {
+                    println!("{}: {}", lint.alias, lint.message);
+                    left += 1;
+                }
+            }
+        }
+        if !fixes.is_empty() {
+            for (name, fix) in &fixes {
+                if let Some(alias) = config.aliases_mut().get_mut(*name) {
+                    alias.set_command(fix);
+                }
+            }
+            if let Err(e) = config.save() {
+                log::error(&e);
+                return e.into();
+            }
+            println!("Fixed {} aliases", fixes.len());
+            sync_functions(&config);
+        }
+        match left {
+            0 => ExitCode::Success,
+            _ => ExitCode::GeneralError,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+#[command(group(
+    ArgGroup::new("source")
+        .required(true)
+        .args(["file", "stdin", "scripts", "archive"])
+))]
+pub struct Import {
+    /// File of shell alias definitions, e.g. a stow package's .bash_aliases
+    file: Option<PathBuf>,
+    /// Read the output of the shell's `alias` builtin from standard input
+    #[arg(long)]
+    stdin: bool,
+    /// Directory of wrapper scripts and symlinks to import, left in place
+    #[arg(long, value_name = "DIR")]
+    scripts: Option<PathBuf>,
+    /// Archive written by `shdw export --archive`, e.g. from another machine
+    #[arg(long, value_name = "FILE")]
+    archive: Option<PathBuf>,
+    /// Add everything found without asking
+    #[arg(long, short)]
+    yes: bool,
+    /// Only show what would be added
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl Import {
+    pub fn execute(&self, mut config: Config) -> ExitCode {
+        // What to add, by name, with where each came from.
+        let mut aliases: BTreeMap<String, (Alias, String)> = BTreeMap::new();
+        let skip = |origin: &str, reason: String| println!("Skipping {}: {}", origin, reason);
+
+        if let Some(path) = &self.archive {
+            let (archived, metadata) = match archive::unpack(path) {
+                Ok(unpacked) => unpacked,
+                Err(e) => {
+                    log::error(format!("Failed to read {}: {}", path.display(), e));
+                    return ExitCode::GeneralError;
+                }
+            };
+            println!(
+                "Archive written by shdw {} on {}",
+                metadata.shdw, metadata.exported
+            );
+            let origin = path.display().to_string();
+            for (name, alias) in archived.aliases().iter() {
+                if let Some(problem) = import_problem(&config, alias) {
+                    skip(&origin, problem);
+                    continue;
+                }
+                if let Some(program) = alias.program().filter(|p| which::find(p).is_none()) {
+                    skip(
+                        &origin,
+                        format!("{} runs {}, which isn't installed", name, program),
+                    );
+                    continue;
+                }
+                // The exporting machine's default bin directory becomes
+                // this one's; other directories move along with home.
+                let bin_path = alias
+                    .bin_path()
+                    .as_ref()
+                    .map(|dir| archive::remap(dir, &metadata))
+                    .filter(|dir| *dir != archive::remap(&metadata.bin_path, &metadata));
+                let alias = alias.frozen().with_bin_path(bin_path);
+                aliases.insert(name.clone(), (alias, origin.clone()));
+            }
+        } else {
+            let (found, rejected) = match (&self.file, &self.scripts) {
+                (Some(file), _) => match fs::read_to_string(file) {
+                    Ok(text) => import::shell_aliases(&text, false, &file.display().to_string()),
+                    Err(e) => {
+                        log::error(format!("Failed to read {}: {}", file.display(), e));
+                        return ExitCode::GeneralError;
+                    }
+                },
+                (None, Some(dir)) => import::scripts(dir),
+                (None, None) => match io::read_to_string(io::stdin()) {
+                    Ok(text) => import::shell_aliases(&text, true, "stdin"),
+                    Err(e) => {
+                        log::error(format!("Failed to read standard input: {}", e));
+                        return ExitCode::GeneralError;
+                    }
+                },
+            };
+
+            for reject in rejected {
+                skip(&reject.origin, reject.reason);
+            }
+            // Like the shell, the last definition of a name wins.
+            for found in found {
+                match Alias::builder(&found.name, found.command).build() {
+                    Ok(alias) => match import_problem(&config, &alias) {
+                        Some(problem) => skip(&found.origin, problem),
+                        None => {
+                            aliases.insert(found.name, (alias, found.origin));
+                        }
+                    },
+                    Err(e) => skip(&found.origin, e.to_string()),
+                }
+            }
+        }
+        if aliases.is_empty() {
+            println!("Nothing to import");
+            return ExitCode::Success;
+        }
+
+        println!("To add:");
+        for (alias, origin) in aliases.values() {
+            println!("  {} → {}  ({})", alias.name(), alias.command(), origin);
+        }
+        if self.dry_run {
+            return ExitCode::Success;
+        }
+        if !self.yes {
+            // Standard input already held the aliases; there's nothing left
+            // to read an answer from.
+            if self.stdin {
+                println!("Re-run with --yes to add them");
+                return ExitCode::Success;
+            }
+            let noun = if aliases.len() == 1 {
+                "alias"
+            } else {
+                "aliases"
+            };
+            if !confirm(&format!("Add {} {}?", aliases.len(), noun)) {
+                return ExitCode::Success;
+            }
+        }
+
+        let mut code = ExitCode::Success;
+        let mut added = Vec::new();
+        for (name, (alias, _)) in aliases {
+            let link = alias.link_file(config.settings());
+            let mut wants_symlink = alias.wants_symlink(config.settings());
+            let record = alias.clone();
+            let result = config
+                .check_add(&alias)
+                .and_then(|_| match wants_symlink {
+                    true => link_or_defer(&alias, config.settings())
+                        .map(|linked| wants_symlink = linked),
+                    false => Ok(()),
+                })
+                .and_then(|_| config.add(alias));
+            match result {
+                Ok(()) => {
+                    println!("Added alias: {}", name);
+                    update_state(|state| {
+                        if wants_symlink {
+                            state.record_link(&name, link);
+                        }
+                        state.record_alias(&record, Source::Import);
+                    });
+                    added.push(name);
+                }
+                Err(e) => {
+                    log::error(format!("Failed to add {}: {}", name, e));
+                    code = e.into();
+                }
+            }
+        }
+        sync_functions(&config);
+        if !added.is_empty() {
+            let added: Vec<_> = added.iter().map(String::as_str).collect();
+            config.settings().hooks().run(Event::Add, &added);
+        }
+        if self.file.is_some() || self.stdin {
+            println!(
+                "Remove the old definitions from your shell config; shell aliases win over shims"
+            );
+        }
+        code
+    }
+}
+
+/// Why `alias` can't be imported into `config`, if it can't, whichever
+/// source it came from: the checks `shdw add` makes, with a builtin name
+/// only refused under `builtins = "refuse"`.
+fn import_problem(config: &Config, alias: &Alias) -> Option<String> {
+    let name = alias.name();
+    if let Some(problem) = name_problem(name) {
+        return Some(problem);
+    }
+    if let Err(e) = config.check_add(alias) {
+        return Some(e.to_string());
+    }
+    if let Some(builtin) = import::runs_builtin(alias.command()) {
+        return Some(format!("{} runs the shell builtin {}", name, builtin));
+    }
+    let refused = config.settings().builtins() == BuiltinPolicy::Refuse;
+    match builtin_of(name) {
+        Some(shell) if refused && alias.wants_symlink(config.settings()) => Some(format!(
+            "{} is a {} builtin, which the shell runs instead (settings.builtins = \"refuse\")",
+            name,
+            shell.name()
+        )),
+        _ => None,
+    }
+}
+
+/// Suggest aliases for long commands you type often
+#[derive(Clone, Debug, Parser)]
+pub struct Suggest {
+    /// Suggest aliases for long commands typed often in the shell's history
+    #[arg(long, required = true)]
+    from_history: bool,
+    /// Shell whose history to read (default: the current one)
+    #[arg(long)]
+    shell: Option<Shell>,
+    /// How often a command must have been typed
+    #[arg(long, default_value_t = 5)]
+    min_count: usize,
+    /// How many suggestions to make
+    #[arg(long, default_value_t = 10)]
+    limit: usize,
+    /// Only print the suggestions
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl Suggest {
+    pub fn execute(&self, mut config: Config) -> ExitCode {
+        let shell = self.shell.unwrap_or_else(Shell::detect);
+        let Some(history) = history::read(shell) else {
+            match history::file(shell) {
+                Some(path) => log::error(format!("Could not read {}", path.display())),
+                None => log::error(format!("shdw can't read {} history", shell.name())),
+            }
+            return ExitCode::GeneralError;
+        };
+
+        let existing: BTreeSet<String> = config
+            .aliases()
+            .values()
+            .map(|alias| alias.command().to_string())
+            .collect();
+        let suggestions = history::suggest(&history, self.min_count, &existing, |name| {
+            config.aliases().contains(name) || which::find(name).is_some()
+        });
+        if suggestions.is_empty() {
+            println!("Nothing typed often enough to suggest");
+            return ExitCode::Success;
+        }
+
+        let interactive = !self.dry_run && io::stdin().is_terminal();
+        let mut code = ExitCode::Success;
+        for suggestion in suggestions.into_iter().take(self.limit) {
+            println!(
+                "{} → {}  (typed {} times)",
+                suggestion.name, suggestion.command, suggestion.count
+            );
+            if !interactive {
+                continue;
+            }
+            let Some(name) = ask_name(&suggestion.name) else {
+                break;
+            };
+            let Some(name) = name else {
+                continue;
+            };
+            if config.aliases().contains(&name) {
+                log::error(format!("{} is already an alias", name));
+                continue;
+            }
+
+            let alias = Alias::builder(&name, suggestion.command).build();
+            let alias = match alias.and_then(|alias| config.check_add(&alias).map(|_| alias)) {
+                Ok(alias) => alias,
+                Err(e) => {
+                    log::error(format!("Failed to add {}: {}", name, e));
+                    code = e.into();
+                    continue;
+                }
+            };
+            let record = alias.clone();
+            let link = alias.link_file(config.settings());
+            let mut wants_symlink = alias.wants_symlink(config.settings());
+            if wants_symlink {
+                match link_or_defer(&alias, config.settings()) {
+                    Ok(linked) => wants_symlink = linked,
+                    Err(e) => {
+                        log::error(format!("Failed to add {}: {}", name, e));
+                        code = e.into();
+                        continue;
+                    }
+                }
+            }
+            match config.add(alias) {
+                Ok(()) => {
+                    println!("Added alias: {}", name);
+                    update_state(|state| {
+                        if wants_symlink {
+                            state.record_link(&name, link);
+                        }
+                        state.record_alias(&record, Source::Suggest);
+                    });
+                }
+                Err(e) => {
+                    log::error(format!("Failed to add {}: {}", name, e));
+                    code = e.into();
+                }
+            }
+        }
+        if !interactive && !self.dry_run {
+            println!("Run `shdw suggest --from-history` in a terminal to add them");
+        }
+        sync_functions(&config);
+        code
+    }
+}
+
+/// Ask whether to add a suggested alias: `Some(Some(name))` to add it, under
+/// a new name if one was typed, `Some(None)` to skip it and `None` to stop.
+fn ask_name(suggested: &str) -> Option<Option<String>> {
+    print!("  [a]dd, [r]ename, [s]kip or [q]uit? [s] ");
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok()?;
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "a" | "add" | "y" | "yes" => Some(Some(suggested.to_string())),
+        "r" | "rename" => {
+            print!("  name: ");
+            let _ = io::stdout().flush();
+            let mut name = String::new();
+            io::stdin().lock().read_line(&mut name).ok()?;
+            let name = name.trim();
+            Some((!name.is_empty()).then(|| name.to_string()))
+        }
+        "q" | "quit" => None,
+        _ => Some(None),
+    }
+}
+
+/// Ask a yes/no question on stdin; anything but "y" or "yes" is a no.
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Open {
+    /// Name of the alias
+    name: String,
+}
+
+impl Open {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let alias = match config.aliases().find(&self.name) {
+            Ok(alias) => alias,
+            Err(e) => {
+                log::error(&e);
+                return e.into();
+            }
+        };
+        if let Some(subscription) = alias.subscription() {
+            log::error(format!(
+                "{} comes from subscription {}, which the next update would undo edits to; define your own {} to replace it",
+                self.name, subscription, self.name
+            ));
+            return ExitCode::InvalidArguments;
+        }
+        let path = match alias.is_project() {
+            true => Config::project_path().unwrap_or_else(Config::config_path),
+            false => Config::config_path(),
+        };
+        let line = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| editor::line_of(&contents, &self.name));
+        match editor::open(&path, line) {
+            Ok(status) if status.success() => ExitCode::Success,
+            Ok(status) => {
+                log::error(format!("The editor exited with {}", status));
+                ExitCode::CommandFailed
+            }
+            Err(e) => {
+                log::error(format!("Failed to start the editor: {}", e));
+                ExitCode::CommandFailed
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Show {
+    /// Name of the alias
+    name: String,
+}
+
+impl Show {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let alias = match config.aliases().find(&self.name) {
+            Ok(alias) => alias,
+            Err(e) => {
+                log::error(&e);
+                return e.into();
+            }
+        };
+        let settings = config.settings();
+
+        let terminal = output::terminal();
+        println!("{}", terminal.paint(Style::Bold, &alias.to_string()));
+        match alias.wants_symlink(settings) {
+            true => println!("  link:        {}", alias.link_state(settings)),
+            false if alias.links() => {
+                println!("  link:        shell function (backend = \"shell\")")
+            }
+            false => println!("  link:        none (link = false)"),
+        }
+        match alias
+            .program()
+            .map(|program| (program, which::find(program)))
+        {
+            Some((_, Some(path))) => println!("  replacement: {}", path.display()),
+            Some((program, None)) => println!("  replacement: {} (not installed)", program),
+            None => println!("  replacement: none (empty command)"),
+        }
+        if let Some(record) = State::load().alias(alias.name()) {
+            println!(
+                "  source:      {}, created {}, updated {}",
+                record.source,
+                tee::utc_date(record.created),
+                tee::utc_date(record.updated)
+            );
+        }
+        for (i, (name, value)) in alias.env().iter().enumerate() {
+            let label = if i == 0 { "env:" } else { "" };
+            match value {
+                EnvValue::Plain(_) => println!("  {:<12} {}", label, name),
+                EnvValue::Secret(source) => println!("  {:<12} {} (from {})", label, name, source),
+            }
+        }
+        if !alias.links() {
+            return ExitCode::Success;
+        }
+
+        let competitors = competitors::find(alias.name());
+        for (i, competitor) in competitors.iter().enumerate() {
+            let label = if i == 0 { "competing:" } else { "" };
+            println!("  {:<12} {}", label, competitor);
+        }
+        println!(
+            "  effective:   {}",
+            competitors::winner(alias.name(), &competitors)
+        );
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Stats {
+    /// Output format; prom is for node_exporter's textfile collector
+    #[arg(long, value_enum, default_value_t)]
+    format: StatsFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum StatsFormat {
+    #[default]
+    Human,
+    Json,
+    Prom,
+}
+
+impl Stats {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let stats = stats::all();
+        match self.format {
+            StatsFormat::Prom => print!("{}", stats::to_prometheus(&stats)),
+            StatsFormat::Json => {
+                let report = Value::object(stats.iter().map(|(name, counters)| {
+                    (
+                        name.as_str(),
+                        Value::object([
+                            ("invocations", Value::from(counters.invocations as i64)),
+                            ("failures", Value::from(counters.failures as i64)),
+                            ("duration_ms", Value::from(counters.duration_ms as i64)),
+                        ]),
+                    )
+                }));
+                println!("{}", report);
+            }
+            StatsFormat::Human if stats.is_empty() => match config.settings().stats() {
+                true => println!("No aliases have run since stats were turned on"),
+                false => {
+                    println!("Stats are off; set `stats = true` under [settings] to count runs")
+                }
+            },
+            StatsFormat::Human => {
+                let width = stats.keys().map(String::len).max().unwrap_or(0).max(5);
+                println!(
+                    "{:width$}  {:>6}  {:>6}  {:>9}",
+                    "ALIAS", "RUNS", "FAILED", "TIME"
+                );
+                for (name, counters) in &stats {
+                    println!(
+                        "{:width$}  {:>6}  {:>6}  {:>8.1}s",
+                        name,
+                        counters.invocations,
+                        counters.failures,
+                        counters.duration_ms as f64 / 1000.0
+                    );
+                }
+            }
+        }
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Gc {
+    /// Delete logs not written to for this long
+    #[arg(long, default_value = "30d")]
+    older_than: HumanDuration,
+    /// Trim logs larger than this to their most recent output
+    #[arg(long, default_value = "10M")]
+    max_log_size: ByteSize,
+    /// Only show what would be cleaned
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl Gc {
+    pub fn execute(&self, _config: Config) -> ExitCode {
+        let options = gc::Options {
+            older_than: self.older_than.as_duration(),
+            max_log_size: self.max_log_size.bytes(),
+            dry_run: self.dry_run,
+        };
+        let actions = match gc::collect(&options) {
+            Ok(actions) => actions,
+            Err(e) => {
+                log::error(format!("Failed to clean {}: {}", state::dir().display(), e));
+                return ExitCode::GeneralError;
+            }
+        };
+
+        let (remove, trim, free) = match self.dry_run {
+            true => ("Would remove", "Would trim", "Would free"),
+            false => ("Removed", "Trimmed", "Freed"),
+        };
+        for action in &actions {
+            match action {
+                gc::Action::Removed { path, bytes } => {
+                    println!(
+                        "{} {} ({})",
+                        remove,
+                        path.display(),
+                        gc::approximate(*bytes)
+                    )
+                }
+                gc::Action::Trimmed { path, from, to } => println!(
+                    "{} {} from {} to {}",
+                    trim,
+                    path.display(),
+                    gc::approximate(*from),
+                    gc::approximate(*to)
+                ),
+                gc::Action::Forgot { name, path } => println!(
+                    "{} stale link record for {} ({})",
+                    remove,
+                    name,
+                    path.display()
+                ),
+            }
+        }
+        let freed: u64 = actions.iter().map(gc::Action::freed).sum();
+        match actions.is_empty() {
+            true => println!("Nothing to clean"),
+            false => println!("{} {}", free, gc::approximate(freed)),
+        }
+        ExitCode::Success
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Uninstall {
+    /// Also delete config.toml and the state directory (state, logs,
+    /// dispatch cache)
+    #[arg(long)]
+    purge: bool,
+    /// Purge without asking
+    #[arg(long, short)]
+    yes: bool,
+    /// Only show what would be removed
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl Uninstall {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let settings = config.settings();
+        let state = State::load();
+        let remove = match self.dry_run {
+            true => "Would remove",
+            false => "Removed",
+        };
+
+        // The same rule as `shdw remove`: only links shdw created, whether
+        // an alias still accounts for them or not.
+        let mut links = BTreeMap::new();
+        for alias in config.aliases().values() {
+            let recorded = state.links().get(alias.name()).map(|record| &record.path);
+            match alias.link_state(settings) {
+                LinkState::Linked(path) => {
+                    links.insert(path, alias.name().to_string());
+                }
+                LinkState::Foreign(path, _) if recorded == Some(&path) => {
+                    links.insert(path, alias.name().to_string());
+                }
+                LinkState::Foreign(path, _) | LinkState::NotASymlink(path)
+                    if alias.wants_symlink(settings) =>
+                {
+                    warning::warn(format!(
+                        "leaving {}, which shdw didn't create",
+                        path.display()
+                    ));
+                }
+                _ => {}
+            }
+        }
+        for (name, path) in stale_links(&state, &config) {
+            links.insert(path, name);
+        }
+
+        let mut failed = false;
+        for path in links.keys() {
+            if !self.dry_run {
+                if let Err(e) = fs::remove_file(path) {
+                    log::error(format!("Failed to remove {}: {}", path.display(), e));
+                    failed = true;
+                    continue;
+                }
+            }
+            println!("{} {}", remove, path.display());
+            if !self.dry_run {
+                restore_diverted(path);
+                unregister_app_path(path);
+            }
+        }
+        // They call `shdw run`, so they'd break along with the links.
+        for shell in [Shell::Posix, Shell::Fish, Shell::Pwsh] {
+            let Some(path) = init::functions_file(shell).filter(|path| path.exists()) else {
+                continue;
+            };
+            if !self.dry_run {
+                if let Err(e) = fs::remove_file(&path) {
+                    log::error(format!("Failed to remove {}: {}", path.display(), e));
+                    failed = true;
+                    continue;
+                }
+            }
+            println!("{} {}", remove, path.display());
+        }
+        if !self.dry_run {
+            update_state(|state| {
+                for name in links.values() {
+                    state.forget_link(name);
+                }
+            });
+        }
+
+        if self.purge {
+            failed |= !self.purge();
+        }
+
+        let shell = Shell::detect();
+        println!();
+        println!(
+            "Remove these lines from {}, if you added them:",
+            shell.rc_file()
+        );
+        for line in shell.startup_lines() {
+            println!("  {}", line);
+        }
+        if let Ok(exe) = env::current_exe() {
+            println!("Then delete shdw itself: {}", exe.display());
+        }
+
+        match failed {
+            true => ExitCode::GeneralError,
+            false => ExitCode::Success,
+        }
+    }
+
+    /// Delete config.toml and the state directory. The config directory
+    /// itself goes only if nothing else is in it.
+    fn purge(&self) -> bool {
+        let config = Config::config_path();
+        let state_dir = state::dir();
+        let targets: Vec<&Path> = [config.as_path(), state_dir.as_path()]
+            .into_iter()
+            .filter(|path| path.exists())
+            .collect();
+        if targets.is_empty() {
+            return true;
+        }
+        if self.dry_run {
+            for path in &targets {
+                println!("Would remove {}", path.display());
+            }
+            return true;
+        }
+        let listed: Vec<String> = targets
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        if !self.yes && !confirm(&format!("Delete {}?", listed.join(" and "))) {
+            println!("Keeping config and state");
+            return true;
+        }
+
+        let mut ok = true;
+        for path in targets {
+            let removed = match path.is_dir() {
+                true => fs::remove_dir_all(path),
+                false => fs::remove_file(path),
+            };
+            match removed {
+                Ok(()) => println!("Removed {}", path.display()),
+                Err(e) => {
+                    log::error(format!("Failed to remove {}: {}", path.display(), e));
+                    ok = false;
+                }
+            }
+        }
+        if let Some(dir) = config.parent() {
+            let _ = fs::remove_dir(dir);
+        }
+        ok
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct CommandNotFound {
+    /// The command the shell couldn't find
+    name: String,
+    /// Its arguments
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+impl CommandNotFound {
+    pub fn execute(&self, config: Config) -> ExitCode {
+        let Some(alias) = config.aliases().get(&self.name) else {
+            return ExitCode::CommandNotFound;
+        };
+        let settings = config.settings();
+        let reason = match alias.link_state(settings) {
+            _ if !alias.links() => "but has link = false, so only `shdw run` runs it".to_string(),
+            LinkState::Linked(_) => {
+                format!("but {} is not on PATH", alias.link_dir(settings).display())
+            }
+            LinkState::Missing(path) => format!("but its symlink {} is missing", path.display()),
+            state => format!("but {}", state),
+        };
+        eprintln!("shdw: {} is an alias, {}", self.name, reason);
+
+        // Bootstrap can't help an alias that is meant to have no symlink.
+        let fixable = alias.links();
+        if !io::stdin().is_terminal() {
+            match fixable {
+                true => eprintln!(
+                    "shdw: run it with `shdw run {}`, or fix it with `shdw bootstrap`",
+                    self.name
+                ),
+                false => eprintln!("shdw: run it with `shdw run {}`", self.name),
+            }
+            return ExitCode::CommandNotFound;
+        }
+        match fixable {
+            true => eprint!("[r]un it now, [b]ootstrap and run it, or do [n]othing? "),
+            false => eprint!("[r]un it now, or do [n]othing? "),
+        }
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+        let _ = io::stdin().lock().read_line(&mut answer);
+        let effective = Effective::resolve(alias, settings, false);
+        stats::set_enabled(effective.stats.value);
+        let raw = effective.raw.value;
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "r" | "run" => alias.execute(&self.args, raw),
+            "b" | "bootstrap" if fixable => {
+                let code = Bootstrap {
+                    install_commands: false,
+                }
+                .execute(config.clone());
+                match code {
+                    ExitCode::Success => alias.execute(&self.args, raw),
+                    code => code,
+                }
+            }
+            _ => ExitCode::CommandNotFound,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Daemon;
+
+impl Daemon {
+    #[cfg(unix)]
+    pub fn execute(&self, config: Config) -> ExitCode {
+        match daemon::serve(config) {
+            Ok(()) => ExitCode::Success,
+            Err(e) => {
+                log::error(format!(
+                    "Failed to run the daemon on {}: {}",
+                    daemon::socket_path().display(),
+                    e
+                ));
+                ExitCode::GeneralError
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn execute(&self, _config: Config) -> ExitCode {
+        log::error("shdw daemon needs Unix domain sockets, which this platform doesn't have");
+        ExitCode::GeneralError
+    }
+}
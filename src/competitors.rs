@@ -0,0 +1,171 @@
+use crate::which;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Startup files that commonly define aliases, relative to the home directory.
+const RC_FILES: &[&str] = &[
+    ".bashrc",
+    ".bash_aliases",
+    ".bash_profile",
+    ".profile",
+    ".zshrc",
+    ".zsh_aliases",
+    ".config/fish/config.fish",
+    ".config/nushell/config.nu",
+    ".config/powershell/Microsoft.PowerShell_profile.ps1",
+    "Documents/PowerShell/Microsoft.PowerShell_profile.ps1",
+];
+
+/// Something other than the shadow that also intercepts a name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Competitor {
+    /// A version manager's shim (mise, asdf, ...) on PATH.
+    Shim {
+        manager: &'static str,
+        path: PathBuf,
+    },
+    /// A link to a different shdw binary, e.g. a second install.
+    OtherShadow(PathBuf),
+    /// An alias, abbreviation or function in a shell startup file.
+    ShellAlias { file: PathBuf, line: usize },
+}
+
+impl fmt::Display for Competitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Competitor::Shim { manager, path } => {
+                write!(f, "{} shim at {}", manager, path.display())
+            }
+            Competitor::OtherShadow(path) => {
+                write!(f, "another shadow install at {}", path.display())
+            }
+            Competitor::ShellAlias { file, line } => {
+                write!(f, "shell alias in {}:{}", file.display(), line)
+            }
+        }
+    }
+}
+
+/// Everything besides our own shadow that intercepts `name`.
+pub fn find(name: &str) -> Vec<Competitor> {
+    let mut competitors: Vec<_> = which::find_all(name)
+        .into_iter()
+        .filter(|path| !which::is_shadow(path))
+        .filter_map(|path| {
+            if let Some(manager) = which::shim_manager(&path) {
+                Some(Competitor::Shim { manager, path })
+            } else if is_shdw(&path) {
+                Some(Competitor::OtherShadow(path))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let Some(home) = dirs::home_dir() else {
+        return competitors;
+    };
+    let fish_function = home.join(format!(".config/fish/functions/{}.fish", name));
+    if fish_function.is_file() {
+        competitors.push(Competitor::ShellAlias {
+            file: fish_function,
+            line: 1,
+        });
+    }
+    for file in RC_FILES.iter().map(|file| home.join(file)) {
+        let Ok(contents) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for (i, line) in contents.lines().enumerate() {
+            if defines(line, name) {
+                competitors.push(Competitor::ShellAlias {
+                    file: file.clone(),
+                    line: i + 1,
+                });
+            }
+        }
+    }
+    competitors
+}
+
+/// Which of the things intercepting `name` actually runs, in a sentence.
+pub fn winner(name: &str, competitors: &[Competitor]) -> String {
+    if let Some(alias) = competitors
+        .iter()
+        .find(|c| matches!(c, Competitor::ShellAlias { .. }))
+    {
+        return format!("the {} runs first in interactive shells", alias);
+    }
+    let Some(first) = which::find_all(name).into_iter().next() else {
+        return format!("nothing named {} is on PATH", name);
+    };
+    if which::is_shadow(&first) {
+        return "the shadow wins".to_string();
+    }
+    match competitors.iter().find(|c| match c {
+        Competitor::Shim { path, .. } | Competitor::OtherShadow(path) => *path == first,
+        Competitor::ShellAlias { .. } => false,
+    }) {
+        Some(competitor) => format!("the {} wins", competitor),
+        None => format!("{} wins", first.display()),
+    }
+}
+
+/// Whether `path` resolves to a binary called shdw.
+fn is_shdw(path: &Path) -> bool {
+    fs::canonicalize(path)
+        .ok()
+        .and_then(|path| path.file_stem().map(|stem| stem == "shdw"))
+        .unwrap_or(false)
+}
+
+/// Whether a startup file line defines `name` as an alias, abbreviation or
+/// function in any of the shells shadow supports.
+fn defines(line: &str, name: &str) -> bool {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return false;
+    }
+    let mut words = line.split_whitespace();
+    let Some(first) = words.next() else {
+        return false;
+    };
+    let names_it = |word: Option<&str>| {
+        word.is_some_and(|word| {
+            let word = word.trim_matches(['\'', '"']);
+            word == name
+                || word
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.starts_with('='))
+        })
+    };
+    match first {
+        "alias" | "abbr" | "Set-Alias" | "New-Alias" => {
+            names_it(words.find(|word| !word.starts_with('-')))
+        }
+        "function" => names_it(words.next().map(|word| word.trim_end_matches("()"))),
+        _ => first == format!("{}()", name) || (first == name && words.next() == Some("()")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defines() {
+        assert!(defines("alias ls='eza'", "ls"));
+        assert!(defines("  alias -g ls=\"eza\"", "ls"));
+        assert!(defines("alias ls eza", "ls"));
+        assert!(defines("alias ls = eza", "ls"));
+        assert!(defines("abbr --add ls eza", "ls"));
+        assert!(defines("function ls", "ls"));
+        assert!(defines("ls() { eza \"$@\"; }", "ls"));
+        assert!(defines("ls () {", "ls"));
+        assert!(defines("Set-Alias -Name ls -Value eza", "ls"));
+        assert!(!defines("# alias ls=eza", "ls"));
+        assert!(!defines("alias lsd=eza", "ls"));
+        assert!(!defines("ls -la", "ls"));
+    }
+}
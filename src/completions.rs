@@ -0,0 +1,129 @@
+use crate::aliases::Alias;
+use crate::quote;
+use crate::shell::Shell;
+
+/// Shell code that makes each shadowed name complete like its replacement.
+pub fn generate(shell: Shell, aliases: &[&Alias]) -> Option<String> {
+    // Aliases that just add flags to the command they shadow already complete,
+    // and unlinked ones shadow nothing.
+    let aliases: Vec<_> = aliases
+        .iter()
+        .copied()
+        .filter(|alias| alias.links() && alias.program() != Some(alias.name()))
+        .collect();
+    let aliases = aliases.as_slice();
+    let script = match shell {
+        Shell::Bash => bash(aliases),
+        Shell::Zsh => zsh(aliases),
+        Shell::Fish => fish(aliases),
+        Shell::Nushell => nushell(aliases),
+        Shell::Pwsh | Shell::Posix => return None,
+    };
+    Some(script)
+}
+
+/// Bash has no notion of completing one command like another, so borrow the
+/// replacement's completion function and call it with the rewritten line.
+fn bash(aliases: &[&Alias]) -> String {
+    let mut out = String::from("declare -gA _shdw_replacements=(\n");
+    for alias in aliases {
+        out.push_str(&format!(
+            "    [{}]={}\n",
+            quote::posix(alias.name()),
+            quote::posix(alias.command())
+        ));
+    }
+    out.push_str(
+        r#")
+
+_shdw_complete() {
+    local -a words
+    read -ra words <<<"${_shdw_replacements[${COMP_WORDS[0]}]}"
+    local cmd=${words[0]}
+    complete -p "$cmd" &>/dev/null || _completion_loader "$cmd" &>/dev/null
+    local spec func
+    spec=$(complete -p "$cmd" 2>/dev/null) || return
+    func=$(sed -n 's/.* -F \([^ ]*\) .*/\1/p' <<<"$spec")
+    [[ -n $func ]] || return
+    COMP_WORDS=("${words[@]}" "${COMP_WORDS[@]:1}")
+    COMP_CWORD=$((COMP_CWORD + ${#words[@]} - 1))
+    COMP_LINE=${COMP_WORDS[*]}
+    COMP_POINT=${#COMP_LINE}
+    "$func" "$cmd" "${COMP_WORDS[COMP_CWORD]}" "${COMP_WORDS[COMP_CWORD - 1]}"
+}
+"#,
+    );
+    if !aliases.is_empty() {
+        let names: Vec<_> = aliases.iter().map(|a| quote::posix(a.name())).collect();
+        out.push_str(&format!(
+            "complete -o default -F _shdw_complete {}\n",
+            names.join(" ")
+        ));
+    }
+    out
+}
+
+fn zsh(aliases: &[&Alias]) -> String {
+    aliases
+        .iter()
+        .filter_map(|alias| {
+            alias.program().map(|program| {
+                format!(
+                    "compdef {}\n",
+                    quote::posix(&format!("{}={}", alias.name(), program))
+                )
+            })
+        })
+        .collect()
+}
+
+fn fish(aliases: &[&Alias]) -> String {
+    aliases
+        .iter()
+        .map(|alias| {
+            format!(
+                "complete --command {} --wraps {}\n",
+                quote::fish(alias.name()),
+                quote::fish(alias.command())
+            )
+        })
+        .collect()
+}
+
+/// Nushell can't bridge completions per command, so wrap the external
+/// completer instead: spans starting with a shadowed name are rewritten to
+/// the replacement command before being handed to whatever completer
+/// (carapace, fish, ...) was configured before.
+fn nushell(aliases: &[&Alias]) -> String {
+    let mut out = String::from("let shdw_replacements = {\n");
+    for alias in aliases {
+        let argv: Vec<_> = alias
+            .command()
+            .split_whitespace()
+            .map(quote::nushell)
+            .collect();
+        out.push_str(&format!(
+            "    {}: [{}]\n",
+            quote::nushell(alias.name()),
+            argv.join(" ")
+        ));
+    }
+    out.push_str(
+        r#"}
+let shdw_previous_completer = $env.config.completions.external.completer?
+
+$env.config.completions.external.enable = true
+$env.config.completions.external.completer = {|spans|
+    let spans = if ($spans.0 in $shdw_replacements) {
+        ($shdw_replacements | get $spans.0) ++ ($spans | skip 1)
+    } else {
+        $spans
+    }
+    if $shdw_previous_completer != null {
+        do $shdw_previous_completer $spans
+    }
+}
+"#,
+    );
+    out
+}
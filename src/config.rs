@@ -1,7 +1,16 @@
-use crate::aliases::{Alias, Aliases};
-use crate::error::{Result, ShadowError};
+use crate::aliases::{self, Alias, Aliases};
+use crate::error::{ExitCode, Result, ShadowError};
+use crate::hooks::Hooks;
+use crate::inherit;
+use crate::log;
+use crate::paths;
+use crate::subscription;
+use crate::template::Template;
+use crate::warning;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
@@ -9,9 +18,30 @@ pub struct Config {
     version: u32,
     #[serde(default)]
     settings: Settings,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    templates: BTreeMap<String, Template>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Aliases::is_empty")]
     aliases: Aliases,
+    /// Aliases run one after another by `shdw group run`, by group name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<String, Vec<String>>,
+    /// Alias files others maintain, by name, layered beneath your own
+    /// aliases as last fetched by `shdw subscription update`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    subscriptions: BTreeMap<String, String>,
+    /// Set when the config was written by a newer shdw, whose format this
+    /// one only partly understands; such a config is never saved over.
+    #[serde(skip)]
+    read_only: bool,
+    /// Set when the config came baked into the binary by `shdw bake`, so
+    /// there's no file to save it to.
+    #[serde(skip)]
+    baked: bool,
+    /// Your own aliases that `$SHDW_PROJECT_CONFIG` defines too, kept to be
+    /// saved back as they were.
+    #[serde(skip)]
+    overridden: Vec<Alias>,
 }
 
 impl Config {
@@ -21,50 +51,278 @@ impl Config {
         Self::CURRENT_VERSION
     }
 
-    pub fn new() -> Result<Self> {
-        let config = Config {
+    /// A config with no aliases, for when there's no file yet.
+    pub fn new() -> Self {
+        Config {
             version: Self::CURRENT_VERSION,
             settings: Settings::default(),
+            templates: BTreeMap::new(),
             aliases: Aliases::default(),
+            groups: BTreeMap::new(),
+            subscriptions: BTreeMap::new(),
+            read_only: false,
+            baked: false,
+            overridden: Vec::new(),
+        }
+    }
+
+    /// The config, from wherever it can be read. Running aliases only ever
+    /// reads it, so a read-only HOME (a container, a live CD) only gets in
+    /// the way of commands that change something.
+    pub fn load() -> Result<Self> {
+        let path = Self::relocate(&Self::config_path());
+        let mut config = match path.exists() {
+            true => Self::from_file(&path)?,
+            false => {
+                // Written out for the user to find and edit, if it can be.
+                let config = Self::new();
+                if let Err(e) = config.save() {
+                    log::debug(format_args!("not creating a config: {}", e));
+                }
+                config
+            }
         };
-        config.save()?;
+        config.layer_subscriptions();
+        config.layer_project();
         Ok(config)
     }
 
-    pub fn load() -> Result<Self> {
-        if Self::config_path().exists() {
-            let contents = std::fs::read_to_string(Self::config_path())?;
-            let mut config: Config =
-                toml::from_str(&contents).map_err(|e| ShadowError::ConfigError(e.to_string()))?;
+    /// `$SHDW_PROJECT_CONFIG`, the config.toml of the project you're in, as
+    /// the snippet from `shdw export --direnv` sets it.
+    pub fn project_path() -> Option<PathBuf> {
+        env::var_os("SHDW_PROJECT_CONFIG")
+            .filter(|path| !path.is_empty())
+            .map(|path| paths::expand_home(Path::new(&path)))
+    }
 
-            if config.version < Self::CURRENT_VERSION {
-                config = config.migrate()?;
+    /// Add the aliases in the project's config over your own, which they
+    /// replace while you're in the project. Only its aliases count: the
+    /// settings are still yours. Like subscribed aliases, they're never
+    /// saved into config.toml.
+    fn layer_project(&mut self) {
+        let Some(path) = Self::project_path().filter(|path| path.exists()) else {
+            return;
+        };
+        let project = match Self::from_file(&path) {
+            Ok(project) => project,
+            Err(e) => {
+                warning::warn(format!("ignoring {}: {}", path.display(), e));
+                return;
+            }
+        };
+        for (name, alias) in project.aliases {
+            let alias = alias.frozen().with_bin_path(None).projected();
+            if let Some(mine) = self.aliases.insert(name, alias) {
+                if mine.is_own() {
+                    self.overridden.push(mine);
+                }
             }
+        }
+    }
 
-            Ok(config)
-        } else {
-            Self::new()
+    /// Add the aliases of every fetched subscription that you don't define
+    /// yourself; for a name two subscriptions share, the first by name wins.
+    /// They're never saved into config.toml.
+    fn layer_subscriptions(&mut self) {
+        for name in self.subscriptions.keys() {
+            let path = subscription::cached(name);
+            if !path.exists() {
+                continue;
+            }
+            let theirs = match Self::from_file(&path) {
+                Ok(theirs) => theirs,
+                Err(e) => {
+                    warning::warn(format!("ignoring subscription {}: {}", name, e));
+                    continue;
+                }
+            };
+            for (alias_name, alias) in theirs.aliases {
+                if !self.aliases.contains(&alias_name) {
+                    let alias = alias.frozen().with_bin_path(None).subscribed(name);
+                    self.aliases.insert(alias_name, alias);
+                }
+            }
         }
     }
 
-    fn migrate(self) -> Result<Self> {
-        match self.version {
-            _ => Ok(self),
+    /// The config `shdw bake` put in this binary.
+    pub fn from_baked(contents: &str) -> Result<Self> {
+        let mut config = Self::parse(contents)?;
+        config.baked = true;
+        Ok(config)
+    }
+
+    /// Load the config in `path`, which needn't be the current one.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let mut config: Config = toml::from_str(contents).map_err(|e| {
+            // A newer format may not parse at all; say that rather than
+            // whatever failed to parse.
+            let version = toml::from_str::<toml::Table>(contents)
+                .ok()
+                .and_then(|table| table.get("version")?.as_integer());
+            match version {
+                Some(version) if version > Self::CURRENT_VERSION as i64 => {
+                    ShadowError::ConfigError(Self::newer_message(version))
+                }
+                _ => ShadowError::ConfigError(e.to_string()),
+            }
+        })?;
+
+        if config.version > Self::CURRENT_VERSION {
+            warning::warn(format!(
+                "{}; loading it read-only",
+                Self::newer_message(config.version.into())
+            ));
+            config.read_only = true;
+        } else if config.version < Self::CURRENT_VERSION {
+            config = config.migrate()?;
+        }
+        config.resolve()?;
+        // Each one is fetched to a file of its name in the state directory.
+        if let Some(name) = config
+            .subscriptions
+            .keys()
+            .find(|name| !aliases::is_file_name(name))
+        {
+            return Err(ShadowError::ConfigError(format!(
+                "Invalid subscription name: '{}'",
+                name
+            )));
         }
+
+        Ok(config)
+    }
+
+    fn newer_message(version: i64) -> String {
+        format!(
+            "config written by a newer version of shdw (format {}, this one reads up to {}); upgrade shdw",
+            version,
+            Self::CURRENT_VERSION
+        )
+    }
+
+    /// Work out what each alias actually runs from `extends` and templates.
+    fn resolve(&mut self) -> Result<()> {
+        let extending: Vec<String> = self
+            .aliases
+            .values()
+            .filter(|alias| alias.extends().is_some())
+            .map(|alias| alias.name().to_string())
+            .collect();
+        // Most configs don't use extends; don't copy every table for nothing.
+        let tables: toml::Table = match extending.is_empty() {
+            true => toml::Table::new(),
+            false => self
+                .aliases
+                .iter()
+                .filter_map(|(name, alias)| {
+                    let table = alias.written()?.clone();
+                    Some((name.clone(), toml::Value::Table(table)))
+                })
+                .collect(),
+        };
+        for name in extending {
+            let error = |e: String| ShadowError::ConfigError(format!("Alias '{}': {}", name, e));
+            let merged = inherit::resolve(&name, &tables).map_err(error)?;
+            let written = match &tables[&name] {
+                toml::Value::Table(table) => table.clone(),
+                _ => unreachable!("alias tables are tables"),
+            };
+            let mut alias = Alias::from_table(merged, written).map_err(|e| error(e.to_string()))?;
+            alias.set_name(&name);
+            self.aliases.insert(name, alias);
+        }
+
+        for alias in self.aliases.values_mut() {
+            alias.apply_template(&self.templates)?;
+        }
+        Ok(())
+    }
+
+    fn migrate(self) -> Result<Self> {
+        // Version 1 is the only format so far; nothing to migrate yet.
+        Ok(self)
     }
 
     pub fn save(&self) -> Result<()> {
-        if let Some(parent) = Self::config_path().parent() {
-            std::fs::create_dir_all(parent)?;
+        if self.baked {
+            return Err(ShadowError::ConfigError(
+                "This shdw has its config baked in; edit the config and run `shdw bake` again"
+                    .to_string(),
+            ));
+        }
+        if self.read_only {
+            return Err(ShadowError::ConfigError(format!(
+                "Not saving {}: {}",
+                Self::config_path().display(),
+                Self::newer_message(self.version.into())
+            )));
+        }
+        let path = Self::config_path();
+        let contents = self.to_toml()?;
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, contents))
+            .map_err(|e| {
+                ShadowError::ConfigError(format!("Failed to save {}: {}", path.display(), e))
+            })
+    }
+
+    /// The config as dispatch sees it: every alias resolved to a plain
+    /// command, with no templates left.
+    pub fn frozen(&self) -> Config {
+        let mut aliases = Aliases::default();
+        for (name, alias) in self.aliases.iter() {
+            aliases.insert(name.clone(), alias.frozen());
+        }
+        Config {
+            version: self.version,
+            settings: self.settings.clone(),
+            templates: BTreeMap::new(),
+            aliases,
+            groups: self.groups.clone(),
+            subscriptions: self.subscriptions.clone(),
+            read_only: false,
+            baked: false,
+            overridden: self.overridden.clone(),
         }
+    }
 
-        let contents =
-            toml::to_string_pretty(self).map_err(|e| ShadowError::ConfigError(e.to_string()))?;
+    /// The config as TOML, the way `save` writes it.
+    pub fn to_toml(&self) -> Result<String> {
+        let restored;
+        let config = match self.overridden.is_empty() {
+            true => self,
+            false => {
+                let mut config = self.clone();
+                for alias in &self.overridden {
+                    if config
+                        .aliases
+                        .get(alias.name())
+                        .is_none_or(Alias::is_project)
+                    {
+                        config
+                            .aliases
+                            .insert(alias.name().to_string(), alias.clone());
+                    }
+                }
+                restored = config;
+                &restored
+            }
+        };
+        toml::to_string_pretty(config).map_err(|e| ShadowError::ConfigError(e.to_string()))
+    }
 
-        std::fs::write(Self::config_path(), contents)
-            .map_err(|e| ShadowError::ConfigError(e.to_string()))?;
+    pub fn version(&self) -> u32 {
+        self.version
+    }
 
-        Ok(())
+    pub fn is_baked(&self) -> bool {
+        self.baked
     }
 
     pub fn settings(&self) -> &Settings {
@@ -75,38 +333,377 @@ impl Config {
         &self.aliases
     }
 
+    pub fn aliases_mut(&mut self) -> &mut Aliases {
+        &mut self.aliases
+    }
+
+    /// The aliases in group `name`, in the order they run.
+    pub fn group(&self, name: &str) -> Option<&[String]> {
+        self.groups.get(name).map(Vec::as_slice)
+    }
+
+    pub fn groups(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.groups
+    }
+
+    pub fn subscriptions(&self) -> &BTreeMap<String, String> {
+        &self.subscriptions
+    }
+
+    /// Whether `name` is one of your own aliases, even while the project's
+    /// config replaces it.
+    pub fn owns(&self, name: &str) -> bool {
+        self.aliases.get(name).is_some_and(Alias::is_own)
+            || self.overridden.iter().any(|alias| alias.name() == name)
+    }
+
+    /// Why `alias` can't be added, if it can't, for checking before its
+    /// link is made: `add` checks the same.
+    pub fn check_add(&self, alias: &Alias) -> Result<()> {
+        self.check_new(alias)?;
+        self.check_protected(alias)
+    }
+
+    /// Refuse `alias` if it's named after one of your own aliases.
+    pub fn check_new(&self, alias: &Alias) -> Result<()> {
+        match self.owns(alias.name()) {
+            true => Err(ShadowError::AliasExists(alias.name().to_string())),
+            false => Ok(()),
+        }
+    }
+
+    /// Refuse `alias` if it would shadow a command in settings.protected.
+    /// One that isn't linked only runs through `shdw run`, which is safe.
+    pub fn check_protected(&self, alias: &Alias) -> Result<()> {
+        if !alias.links() || !self.settings.is_protected(alias.name()) {
+            return Ok(());
+        }
+        Err(ShadowError::InvalidAlias(format!(
+            "{} is protected: if its shadow breaks, you may have no way left to fix it. Use \
+             `shdw add --i-know-what-im-doing` to shadow it anyway, or `--no-link` to keep it \
+             for `shdw run`.",
+            alias.name()
+        )))
+    }
+
+    /// Add `alias`, which can take over a subscribed alias' name.
     pub fn add(&mut self, alias: Alias) -> Result<()> {
+        self.check_add(&alias)?;
+        self.insert(alias)
+    }
+
+    /// `add`, for `shdw add --i-know-what-im-doing`: `alias` may shadow a
+    /// protected command.
+    pub fn add_protected(&mut self, alias: Alias) -> Result<()> {
+        self.check_new(&alias)?;
+        self.insert(alias)
+    }
+
+    fn insert(&mut self, alias: Alias) -> Result<()> {
         let name = alias.name();
-        if self.aliases.contains(name) {
-            return Err(ShadowError::AliasExists(name.to_string()));
-        }
         self.aliases.insert(name.to_string(), alias);
         self.save()?;
         Ok(())
     }
 
-    pub fn remove(&mut self, name: &str) -> Result<()> {
-        if !self.aliases.contains(name) {
+    /// Refuse to remove `name` unless it's one of your own aliases, or a
+    /// project alias layered over one of yours, which is then what goes.
+    pub fn check_removable(&self, name: &str) -> Result<()> {
+        let Some(alias) = self.aliases.get(name) else {
             return Err(ShadowError::AliasNotFound(name.to_string()));
+        };
+        if let Some(subscription) = alias.subscription() {
+            return Err(ShadowError::InvalidAlias(format!(
+                "{} comes from subscription {}, which you can't change; define your own {} to replace it",
+                name, subscription, name
+            )));
+        }
+        if alias.is_project() && !self.overridden.iter().any(|alias| alias.name() == name) {
+            return Err(ShadowError::InvalidAlias(format!(
+                "{} comes from {}; remove it there",
+                name,
+                Self::project_path().unwrap_or_default().display()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        self.check_removable(name)?;
+        if self.aliases.get(name).is_some_and(Alias::is_project) {
+            self.overridden.retain(|alias| alias.name() != name);
+            return self.save();
         }
         self.aliases.remove(name);
         self.save()?;
         Ok(())
     }
 
-    fn config_path() -> PathBuf {
-        dirs::config_dir()
-            .expect("Could not find config directory")
-            .join("shdw/config.toml")
+    /// Problems that would keep aliases from working, one message each.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for code in ExitCode::ALL {
+            let value = code.code(self.settings.exit_codes());
+            if !(0..=255).contains(&value) {
+                problems.push(format!(
+                    "Exit code for {} must be between 0 and 255, got {}",
+                    code.name(),
+                    value
+                ));
+            }
+        }
+
+        let mut aliases: Vec<_> = self.aliases.values().collect();
+        aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+        for alias in aliases {
+            let name = alias.name();
+            problems.extend(aliases::name_problem(name));
+
+            match alias.program() {
+                None => problems.push(format!("Alias '{}' has an empty command", name)),
+                Some(program) if program == name && alias.links() => problems.push(format!(
+                    "Alias '{}' runs '{}', which resolves back to the shadow itself",
+                    name, program
+                )),
+                Some(_) => {}
+            }
+            if let (Some(min), Some(max)) = alias.arg_bounds() {
+                if min > max {
+                    problems.push(format!(
+                        "Alias '{}' has min_args {} above max_args {}",
+                        name, min, max
+                    ));
+                }
+            }
+        }
+
+        for (name, members) in &self.groups {
+            if members.is_empty() {
+                problems.push(format!("Group '{}' has no aliases", name));
+            }
+            for member in members {
+                if !self.aliases.contains(member) {
+                    problems.push(format!(
+                        "Group '{}' runs '{}', which is not an alias",
+                        name, member
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Aliases whose symlinks would land on the same file. Names differing
+    /// only in case count too: they collide on macOS and Windows, where a
+    /// dotfiles repository may end up.
+    pub fn link_collisions(&self) -> Vec<String> {
+        let mut links: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for alias in self.aliases.values().filter(|alias| alias.links()) {
+            let link = alias.link_file(&self.settings);
+            links
+                .entry(link.to_string_lossy().to_lowercase())
+                .or_default()
+                .push(alias.name());
+        }
+        links
+            .into_values()
+            .filter(|names| names.len() > 1)
+            .map(|mut names| {
+                names.sort();
+                format!(
+                    "Aliases {} would all link to the same file",
+                    names.join(", ")
+                )
+            })
+            .collect()
+    }
+
+    /// Other aliases whose symlink is the very file `alias`' would be on
+    /// this machine: the same name in bin directories that are the same
+    /// directory, or names differing only in case where file names don't.
+    /// Linking one of them replaces the other's link.
+    pub fn link_conflicts(&self, alias: &Alias) -> Vec<&str> {
+        let key = |alias: &Alias| {
+            let link = alias.link_file(&self.settings);
+            let dir = link
+                .parent()
+                .map(|dir| std::fs::canonicalize(dir).unwrap_or(dir.to_path_buf()));
+            let key = dir
+                .unwrap_or_default()
+                .join(link.file_name().unwrap_or_default());
+            let key = key.to_string_lossy().into_owned();
+            match cfg!(any(windows, target_os = "macos")) {
+                true => key.to_lowercase(),
+                false => key,
+            }
+        };
+        let ours = key(alias);
+        let mut conflicts: Vec<&str> = self
+            .aliases
+            .values()
+            .filter(|other| other.name() != alias.name() && other.links())
+            .filter(|other| key(other) == ours)
+            .map(|other| other.name())
+            .collect();
+        conflicts.sort();
+        conflicts
+    }
+
+    /// `$SHDW_CONFIG`, e.g. a config baked into an image, else
+    /// `config.toml` in the config directory.
+    pub fn config_path() -> PathBuf {
+        match env::var_os("SHDW_CONFIG").filter(|path| !path.is_empty()) {
+            Some(path) => paths::expand_home(Path::new(&path)),
+            None => paths::config_dir().join("config.toml"),
+        }
+    }
+
+    /// Move a config left in the platform default directory to `path`,
+    /// after `XDG_CONFIG_HOME` or `SHDW_STRICT_XDG` changed where that is.
+    /// A config named by `SHDW_CONFIG` or `SHDW_CONFIG_DIR` is a different
+    /// config, not a new home for this one, so nothing moves for those.
+    /// Returns where the config is now: still the old place if it can't be
+    /// moved, e.g. on a read-only filesystem.
+    fn relocate(path: &Path) -> PathBuf {
+        let Some(legacy) = paths::legacy_config_dir().map(|dir| dir.join("config.toml")) else {
+            return path.to_path_buf();
+        };
+        let overridden = ["SHDW_CONFIG", "SHDW_CONFIG_DIR"]
+            .iter()
+            .any(|var| env::var_os(var).is_some_and(|value| !value.is_empty()));
+        if overridden || path != paths::default_config_dir().join("config.toml") {
+            return path.to_path_buf();
+        }
+        if path.exists() || legacy == path || !legacy.exists() {
+            return path.to_path_buf();
+        }
+
+        let moved = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| match std::fs::rename(&legacy, path) {
+                Ok(()) => Ok(()),
+                Err(_) => std::fs::copy(&legacy, path).and_then(|_| std::fs::remove_file(&legacy)),
+            });
+        match moved {
+            Ok(()) => {
+                log::info(format!(
+                    "Moved config from {} to {}",
+                    legacy.display(),
+                    path.display()
+                ));
+                path.to_path_buf()
+            }
+            Err(e) => {
+                log::debug(format_args!(
+                    "reading {}, which can't move to {}: {}",
+                    legacy.display(),
+                    path.display(),
+                    e
+                ));
+                legacy
+            }
+        }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
     #[serde(default = "Settings::default_bin_path")]
     bin_path: PathBuf,
     #[serde(default)]
     always_use_raw: bool,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    exit_codes: HashMap<ExitCode, i32>,
+    #[serde(default, skip_serializing_if = "BuiltinPolicy::is_default")]
+    builtins: BuiltinPolicy,
+    #[serde(default, skip_serializing_if = "Backend::is_default")]
+    backend: Backend,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    help_banner: bool,
+    #[serde(
+        default = "Settings::default_protected",
+        skip_serializing_if = "Settings::is_default_protected"
+    )]
+    protected: Vec<String>,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    hooks: Hooks,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stats: bool,
+    #[serde(default, skip_serializing_if = "OnMissingConfig::is_default")]
+    on_missing_config: OnMissingConfig,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    app_paths: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    failure_footer: bool,
+}
+
+/// How aliases take over the commands they shadow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// A symlink to shdw in a bin directory early on PATH.
+    #[default]
+    Symlink,
+    /// A function in the interactive shell, defined by `shdw init`, for
+    /// machines where a bin directory can't go early on PATH. Scripts and
+    /// other programs see the original commands.
+    Shell,
+}
+
+impl Backend {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What `shdw add` does with an alias named after a shell builtin (`cd`,
+/// `test`, `echo`): the shell runs its builtin, never the symlink, so only
+/// scripts calling e.g. `/usr/bin/test` would ever reach the alias.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuiltinPolicy {
+    Allow,
+    #[default]
+    Warn,
+    Refuse,
+}
+
+impl BuiltinPolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What a shim does when shdw has no alias for it: the alias was removed
+/// and its link left behind, or the config is gone altogether. Without a
+/// config there's no setting, so `SHDW_ON_MISSING_CONFIG` can say instead.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnMissingConfig {
+    /// Say so and exit 127, as for any unknown command.
+    #[default]
+    Error,
+    /// Run the command the link hides, as though it weren't there.
+    Passthrough,
+}
+
+impl OnMissingConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The policy `SHDW_ON_MISSING_CONFIG` asks for, if it's set to one.
+    pub fn from_env() -> Option<Self> {
+        match env::var("SHDW_ON_MISSING_CONFIG").ok()?.as_str() {
+            "error" => Some(Self::Error),
+            "passthrough" => Some(Self::Passthrough),
+            _ => None,
+        }
+    }
 }
 
 impl Settings {
@@ -114,20 +711,326 @@ impl Settings {
         Self {
             bin_path,
             always_use_raw,
+            exit_codes: HashMap::new(),
+            builtins: BuiltinPolicy::default(),
+            backend: Backend::default(),
+            help_banner: false,
+            protected: Self::default_protected(),
+            hooks: Hooks::default(),
+            stats: false,
+            on_missing_config: OnMissingConfig::default(),
+            app_paths: false,
+            failure_footer: false,
         }
     }
 
-    fn default() -> Self {
-        Self::new(Self::default_bin_path(), false)
-    }
-
     pub fn bin_path(&self) -> &PathBuf {
         &self.bin_path
     }
 
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    /// Whether to count each alias' runs, failures and time, for `shdw stats`.
+    pub fn stats(&self) -> bool {
+        self.stats
+    }
+
+    /// Whether links are also registered under App Paths, on Windows.
+    pub fn app_paths(&self) -> bool {
+        self.app_paths
+    }
+
+    /// What shims without an alias do, with `SHDW_ON_MISSING_CONFIG` taking
+    /// precedence.
+    pub fn on_missing_config(&self) -> OnMissingConfig {
+        OnMissingConfig::from_env().unwrap_or(self.on_missing_config)
+    }
+
+    /// These settings with symlinks going to `bin_path` instead.
+    pub fn with_bin_path(mut self, bin_path: PathBuf) -> Self {
+        self.bin_path = bin_path;
+        self
+    }
+
+    pub fn always_use_raw(&self) -> bool {
+        self.always_use_raw
+    }
+
+    /// Remapped exit statuses from `[settings.exit_codes]`.
+    pub fn exit_codes(&self) -> &HashMap<ExitCode, i32> {
+        &self.exit_codes
+    }
+
+    pub fn builtins(&self) -> BuiltinPolicy {
+        self.builtins
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Whether shims note that they're shadowed when asked for `--help`.
+    pub fn help_banner(&self) -> bool {
+        self.help_banner
+    }
+
+    /// Whether shims say which replacement failed, after it exits non-zero.
+    pub fn failure_footer(&self) -> bool {
+        self.failure_footer
+    }
+
+    /// Commands shdw won't shadow except by `shdw add --i-know-what-im-doing`:
+    /// a broken shim for one of them can leave no way to fix the machine.
+    pub fn protected(&self) -> &[String] {
+        &self.protected
+    }
+
+    pub fn is_protected(&self, name: &str) -> bool {
+        self.protected.iter().any(|protected| protected == name)
+    }
+
+    fn default_protected() -> Vec<String> {
+        ["sudo", "sh", "bash", "env", "systemctl", "ssh"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    fn is_default_protected(protected: &[String]) -> bool {
+        protected == Self::default_protected()
+    }
+
     fn default_bin_path() -> PathBuf {
         dirs::executable_dir()
             .or_else(|| dirs::home_dir().map(|h| h.join(".local/bin")))
             .expect("Could not determine binary directory")
     }
 }
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new(Self::default_bin_path(), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64*, so every run generates the same configs and a failure
+    /// can be reproduced from the case number alone.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn string(&mut self, max_len: usize, pieces: &[&str]) -> String {
+            let len = 1 + self.below(max_len);
+            (0..len).map(|_| pieces[self.below(pieces.len())]).collect()
+        }
+    }
+
+    const NAME: &[&str] = &[
+        "a", "Z", "0", "-", "_", ".", "é", "日本", "🦀", " ", "\"", "'", "\\", "=", "[", "#",
+    ];
+    const TEXT: &[&str] = &[
+        "ls", " ", "--", "=", "é", "🦀", "\"", "'", "\\", "\t", "\n", "{", "}", "#", "$", "ъ",
+    ];
+
+    fn random_config(rng: &mut Rng) -> Config {
+        let mut aliases = Aliases::default();
+        for _ in 0..rng.below(8) {
+            let name = rng.string(12, NAME);
+            let max_len = [8, 40, 2000][rng.below(3)];
+            let command = rng.string(max_len, TEXT);
+            let description = (rng.below(2) == 0).then(|| rng.string(20, TEXT));
+            let bin_path = (rng.below(3) == 0).then(|| PathBuf::from(rng.string(10, NAME)));
+            let alias = Alias::new(name.clone(), command, description, bin_path)
+                .with_link(rng.below(4) != 0);
+            aliases.insert(name, alias);
+        }
+        Config {
+            version: Config::CURRENT_VERSION,
+            settings: Settings::default(),
+            templates: BTreeMap::new(),
+            aliases,
+            groups: BTreeMap::new(),
+            subscriptions: BTreeMap::new(),
+            read_only: false,
+            baked: false,
+            overridden: Vec::new(),
+        }
+    }
+
+    // Generated alias sets must survive save and load unchanged; loading
+    // back-fills names from the table keys and saving sorts them, so both
+    // directions get exercised.
+    #[test]
+    fn test_round_trip_random_configs() {
+        for case in 0..300 {
+            let mut rng = Rng(0x9e37_79b9_7f4a_7c15 ^ case);
+            let config = random_config(&mut rng);
+            let saved = config.to_toml().unwrap();
+            let mut loaded: Config = toml::from_str(&saved)
+                .unwrap_or_else(|e| panic!("case {} didn't load: {}\n{}", case, e, saved));
+            loaded.resolve().unwrap();
+
+            assert_eq!(loaded.to_toml().unwrap(), saved, "case {}", case);
+            assert_eq!(loaded.aliases.len(), config.aliases.len(), "case {}", case);
+            for (name, alias) in config.aliases.iter() {
+                let back = loaded.aliases.find(name).unwrap();
+                assert_eq!(back.name(), alias.name(), "case {}", case);
+                assert_eq!(back.command(), alias.command(), "case {}", case);
+                assert_eq!(back.description(), alias.description(), "case {}", case);
+                assert_eq!(back.bin_path(), alias.bin_path(), "case {}", case);
+                assert_eq!(back.links(), alias.links(), "case {}", case);
+            }
+        }
+    }
+
+    #[test]
+    fn test_newer_version_is_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        temp_env::with_var("SHDW_CONFIG_DIR", Some(dir.path()), || {
+            std::fs::write(&file, "version = 3\n\n[aliases.ls]\ncommand = \"eza\"\n").unwrap();
+            let config = Config::load().unwrap();
+            assert_eq!(config.aliases().find("ls").unwrap().command(), "eza");
+            let error = config.save().unwrap_err().to_string();
+            assert!(error.contains("newer version"), "{}", error);
+
+            std::fs::write(&file, "version = 3\naliases = [\"ls\"]\n").unwrap();
+            let error = Config::load().unwrap_err().to_string();
+            assert!(error.contains("newer version"), "{}", error);
+        });
+    }
+
+    #[test]
+    fn test_subscription_names_stay_in_the_state_dir() {
+        for name in ["../x", "a/b", "a\\b", ""] {
+            let contents = format!(
+                "[subscriptions]\n{:?} = \"https://example.invalid\"\n",
+                name
+            );
+            let error = Config::parse(&contents).unwrap_err().to_string();
+            assert!(error.contains("Invalid subscription name"), "{}", error);
+        }
+        assert!(Config::parse("[subscriptions]\nteam = \"https://example.invalid\"\n").is_ok());
+    }
+
+    #[test]
+    fn test_override_leaves_legacy_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("xdg/shdw/config.toml");
+        std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        std::fs::write(&legacy, "[aliases.ls]\ncommand = \"eza\"\n").unwrap();
+        let typo = dir.path().join("elsewhere/typo.toml");
+        let xdg = dir.path().join("xdg");
+        let vars = [
+            ("XDG_CONFIG_HOME", Some(xdg.as_os_str())),
+            ("SHDW_CONFIG", Some(typo.as_os_str())),
+            ("SHDW_CONFIG_DIR", None),
+            ("SHDW_STRICT_XDG", None),
+        ];
+        temp_env::with_vars(vars, || {
+            let config = Config::load().unwrap();
+            assert!(config.aliases().find("ls").is_err());
+        });
+        let elsewhere = dir.path().join("elsewhere");
+        let vars = [
+            ("XDG_CONFIG_HOME", Some(xdg.as_os_str())),
+            ("SHDW_CONFIG", None),
+            ("SHDW_CONFIG_DIR", Some(elsewhere.as_os_str())),
+            ("SHDW_STRICT_XDG", None),
+        ];
+        temp_env::with_vars(vars, || {
+            Config::load().unwrap();
+        });
+        assert_eq!(
+            std::fs::read_to_string(&legacy).unwrap(),
+            "[aliases.ls]\ncommand = \"eza\"\n"
+        );
+    }
+
+    #[test]
+    fn test_protected_aliases() {
+        let config: Config = toml::from_str("[settings]\nprotected = [\"ssh\"]\n").unwrap();
+        let ssh = Alias::builder("ssh", "mosh").build().unwrap();
+        let error = config.check_add(&ssh).unwrap_err().to_string();
+        assert!(error.contains("ssh is protected"), "{}", error);
+        assert!(config.check_new(&ssh).is_ok());
+        let unlinked = Alias::builder("ssh", "mosh").link(false).build().unwrap();
+        assert!(config.check_add(&unlinked).is_ok());
+        let sudo = Alias::builder("sudo", "doas").build().unwrap();
+        assert!(config.check_add(&sudo).is_ok());
+
+        let dir = tempfile::tempdir().unwrap();
+        temp_env::with_var("SHDW_CONFIG_DIR", Some(dir.path()), || {
+            let mut config = config.clone();
+            assert!(config.add(ssh.clone()).is_err());
+            assert!(config.aliases().find("ssh").is_err());
+            config.add_protected(ssh).unwrap();
+            assert_eq!(config.aliases().find("ssh").unwrap().command(), "mosh");
+        });
+    }
+
+    #[test]
+    fn test_validate_groups() {
+        let config: Config = toml::from_str(
+            r#"
+            [aliases]
+            ls = { command = "eza" }
+
+            [groups]
+            listings = ["ls", "ll"]
+            empty = []
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.group("listings").unwrap(), ["ls", "ll"]);
+        assert_eq!(
+            config.validate(),
+            [
+                "Group 'empty' has no aliases",
+                "Group 'listings' runs 'll', which is not an alias",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [settings]
+            bin_path = "{}"
+
+            [aliases]
+            ls = {{ command = "eza" }}
+            LS = {{ command = "lsd" }}
+            ll = {{ command = "eza -l", link = false }}
+            "#,
+            dir.path().display().to_string().replace('\\', "/")
+        ))
+        .unwrap();
+        let ls = config.aliases().get("ls").unwrap();
+        let expected: &[&str] = match cfg!(any(windows, target_os = "macos")) {
+            true => &["LS"],
+            false => &[],
+        };
+        assert_eq!(config.link_conflicts(ls), expected);
+        assert!(config
+            .link_conflicts(config.aliases().get("ll").unwrap())
+            .is_empty());
+    }
+}
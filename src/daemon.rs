@@ -0,0 +1,149 @@
+use crate::cache::Entry;
+
+/// The socket `shdw daemon` listens on, in the state directory.
+#[cfg(unix)]
+pub fn socket_path() -> std::path::PathBuf {
+    crate::state::file("daemon.sock")
+}
+
+/// Ask a running daemon for alias `name`. `None` when no daemon is
+/// listening, it's too slow to answer, it doesn't know the alias or serves
+/// a different config, or its answer is stale by the same test the dispatch
+/// cache uses; the shim then falls back to the cache or the config itself.
+#[cfg(unix)]
+pub fn query(name: &str) -> Option<Entry> {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket_path()).ok()?;
+    stream.set_read_timeout(Some(unix::TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(unix::TIMEOUT)).ok()?;
+    write!(stream, "{}", unix::request(name)).ok()?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).ok()?;
+    Entry::from_toml(name, &reply).filter(|entry| !entry.is_stale())
+}
+
+#[cfg(not(unix))]
+pub fn query(_name: &str) -> Option<Entry> {
+    None
+}
+
+#[cfg(unix)]
+pub use unix::serve;
+
+#[cfg(unix)]
+mod unix {
+    use super::socket_path;
+    use crate::cache::{Entry, Sources};
+    use crate::config::Config;
+    use crate::log;
+    use std::fs;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::time::{Duration, Instant};
+
+    /// How long either side waits on the other before giving up.
+    pub const TIMEOUT: Duration = Duration::from_millis(200);
+    /// How often the daemon looks at the config's files for changes. Stat
+    /// calls are what's slow on a network home, so not on every request;
+    /// shims check what they're sent themselves.
+    const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// What a shim sends: the alias it wants and the config and project
+    /// config its environment names, a line each.
+    pub fn request(name: &str) -> String {
+        format!("{}\n{}\n", name, configs())
+    }
+
+    /// `$SHDW_CONFIG`'s path, or the default, and the project's, if any.
+    fn configs() -> String {
+        let project = Config::project_path().unwrap_or_default();
+        format!(
+            "{}\n{}",
+            Config::config_path().to_string_lossy(),
+            project.to_string_lossy()
+        )
+    }
+
+    struct Daemon {
+        config: Config,
+        sources: Sources,
+        checked: Instant,
+    }
+
+    impl Daemon {
+        fn reload_if_changed(&mut self) {
+            if self.checked.elapsed() < RELOAD_INTERVAL {
+                return;
+            }
+            self.checked = Instant::now();
+            if !self.sources.is_stale() {
+                return;
+            }
+            match Config::load() {
+                Ok(config) => {
+                    log::info(format!("Reloaded {}", Config::config_path().display()));
+                    self.sources = Sources::of(&config);
+                    self.config = config;
+                }
+                Err(e) => log::warning(format!("keeping the previous config: {}", e)),
+            }
+        }
+
+        fn answer(&mut self, stream: UnixStream) -> io::Result<()> {
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            let mut reader = BufReader::new(&stream);
+            let mut lines = [String::new(), String::new(), String::new()];
+            for line in &mut lines {
+                reader.read_line(line)?;
+            }
+            let [name, config, project] = lines.map(|line| line.trim_end().to_string());
+            // A shim with another SHDW_CONFIG or project gets nothing, and
+            // loads its own config.
+            if format!("{}\n{}", config, project) != configs() {
+                log::debug(format_args!("{}: asked about another config", name));
+                return Ok(());
+            }
+            self.reload_if_changed();
+            let reply = Entry::new(&name, &self.config, &self.sources)
+                .and_then(|entry| entry.to_toml())
+                .unwrap_or_default();
+            (&stream).write_all(reply.as_bytes())
+        }
+    }
+
+    /// Answer shims from `config`, held in memory, until killed.
+    pub fn serve(config: Config) -> io::Result<()> {
+        let path = socket_path();
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another daemon is already listening",
+            ));
+        }
+        // Left behind by a daemon that was killed.
+        let _ = fs::remove_file(&path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        log::info(format!("Listening on {}", path.display()));
+
+        let mut daemon = Daemon {
+            sources: Sources::of(&config),
+            config,
+            checked: Instant::now(),
+        };
+        for stream in listener.incoming() {
+            let answered = stream.and_then(|stream| daemon.answer(stream));
+            if let Err(e) = answered {
+                log::error(format!("Failed to answer a shim: {}", e));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use std::env;
+use std::process::Command;
+
+/// How many shims are running the current process, set by each for what
+/// it starts, and their aliases, outermost first.
+const DEPTH: &str = "SHDW_DEPTH";
+const CHAIN: &str = "SHDW_CHAIN";
+
+/// Aliases that run other shadowed commands nest a level or two; this many
+/// is aliases running each other in a circle.
+pub const LIMIT: u32 = 8;
+
+/// The aliases already running, from the environment shims leave behind.
+fn chain() -> (u32, Vec<String>) {
+    let depth = env::var(DEPTH)
+        .ok()
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(0);
+    let chain = env::var(CHAIN).unwrap_or_default();
+    (
+        depth,
+        chain.split_whitespace().map(str::to_string).collect(),
+    )
+}
+
+/// Mark `command` as run by alias `name`, or refuse to when the aliases
+/// running it are `LIMIT` deep, saying which they are.
+pub fn enter(command: &mut Command, name: &str) -> Result<(), String> {
+    let (depth, mut chain) = chain();
+    chain.push(name.to_string());
+    if depth >= LIMIT {
+        return Err(format!(
+            "aliases run each other more than {} deep: {}",
+            LIMIT,
+            chain.join(" -> ")
+        ));
+    }
+    command
+        .env(DEPTH, (depth + 1).to_string())
+        .env(CHAIN, chain.join(" "));
+    Ok(())
+}
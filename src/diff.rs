@@ -0,0 +1,124 @@
+use crate::config::Config;
+use std::collections::BTreeSet;
+
+/// How one alias differs between two configs.
+pub enum Change {
+    Added {
+        name: String,
+        command: String,
+    },
+    Removed {
+        name: String,
+        command: String,
+    },
+    /// The keys that differ, with the old and new value of each.
+    Changed {
+        name: String,
+        keys: Vec<KeyChange>,
+    },
+}
+
+/// A key that was set, unset or changed, with values written as TOML.
+pub struct KeyChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// What changes from `old` to `new`, alias by alias in name order. Aliases
+/// are compared as dispatch sees them, so editing a template shows up in
+/// every alias using it.
+pub fn aliases(old: &Config, new: &Config) -> Vec<Change> {
+    let names: BTreeSet<&String> = old.aliases().keys().chain(new.aliases().keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let name = name.clone();
+            match (old.aliases().get(&name), new.aliases().get(&name)) {
+                (None, Some(alias)) => Some(Change::Added {
+                    command: alias.command().to_string(),
+                    name,
+                }),
+                (Some(alias), None) => Some(Change::Removed {
+                    command: alias.command().to_string(),
+                    name,
+                }),
+                (Some(before), Some(after)) => {
+                    let keys = tables(
+                        toml::Table::try_from(before.frozen()).unwrap_or_default(),
+                        toml::Table::try_from(after.frozen()).unwrap_or_default(),
+                    );
+                    (!keys.is_empty()).then_some(Change::Changed { name, keys })
+                }
+                (None, None) => None,
+            }
+        })
+        .collect()
+}
+
+/// The keys of `[settings]` that differ.
+pub fn settings(old: &Config, new: &Config) -> Vec<KeyChange> {
+    tables(
+        toml::Table::try_from(old.settings()).unwrap_or_default(),
+        toml::Table::try_from(new.settings()).unwrap_or_default(),
+    )
+}
+
+fn tables(old: toml::Table, new: toml::Table) -> Vec<KeyChange> {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|key| KeyChange {
+            key: key.clone(),
+            old: old.get(key).map(ToString::to_string),
+            new: new.get(key).map(ToString::to_string),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases() {
+        let old: Config = toml::from_str(
+            r#"
+            [aliases]
+            ls = { command = "eza" }
+            la = { command = "eza --all" }
+            cat = { command = "bat" }
+            "#,
+        )
+        .unwrap();
+        let new: Config = toml::from_str(
+            r#"
+            [aliases]
+            ls = { command = "eza --icons", notify_after = "30s" }
+            ll = { command = "eza --long" }
+            cat = { command = "bat" }
+            "#,
+        )
+        .unwrap();
+
+        let changes = aliases(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(&changes[0], Change::Removed { name, .. } if name == "la"));
+        assert!(matches!(&changes[1], Change::Added { name, .. } if name == "ll"));
+        let Change::Changed { name, keys } = &changes[2] else {
+            panic!("ls should have changed");
+        };
+        assert_eq!(name, "ls");
+        let keys: Vec<_> = keys
+            .iter()
+            .map(|k| (k.key.as_str(), k.old.as_deref(), k.new.as_deref()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("command", Some("\"eza\""), Some("\"eza --icons\"")),
+                ("notify_after", None, Some("\"30s\"")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A duration written the way humans write it in config files: `500ms`,
+/// `30s`, `5m`, `2h`, `7d`. A bare number is read as seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HumanDuration(Duration);
+
+impl HumanDuration {
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let value: u64 = number
+            .parse()
+            .map_err(|_| format!("Invalid duration: {}", s))?;
+
+        let duration = match unit.trim() {
+            "ms" => Duration::from_millis(value),
+            "" | "s" => Duration::from_secs(value),
+            "m" => Duration::from_secs(value * 60),
+            "h" => Duration::from_secs(value * 60 * 60),
+            "d" => Duration::from_secs(value * 60 * 60 * 24),
+            other => return Err(format!("Invalid duration unit '{}' in: {}", other, s)),
+        };
+
+        Ok(Self(duration))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.0.as_millis();
+        let secs = self.0.as_secs();
+        if !millis.is_multiple_of(1000) {
+            write!(f, "{}ms", millis)
+        } else if secs != 0 && secs.is_multiple_of(86400) {
+            write!(f, "{}d", secs / 86400)
+        } else if secs != 0 && secs.is_multiple_of(3600) {
+            write!(f, "{}h", secs / 3600)
+        } else if secs != 0 && secs.is_multiple_of(60) {
+            write!(f, "{}m", secs / 60)
+        } else {
+            write!(f, "{}s", secs)
+        }
+    }
+}
+
+impl Serialize for HumanDuration {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_units() {
+        let parse = |s: &str| s.parse::<HumanDuration>().unwrap().as_duration();
+        assert_eq!(parse("250ms"), Duration::from_millis(250));
+        assert_eq!(parse("30s"), Duration::from_secs(30));
+        assert_eq!(parse("30"), Duration::from_secs(30));
+        assert_eq!(parse("5m"), Duration::from_secs(300));
+        assert_eq!(parse("2h"), Duration::from_secs(7200));
+        assert_eq!(parse("30d"), Duration::from_secs(30 * 86400));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!("".parse::<HumanDuration>().is_err());
+        assert!("s".parse::<HumanDuration>().is_err());
+        assert!("10y".parse::<HumanDuration>().is_err());
+        assert!("-5s".parse::<HumanDuration>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for s in ["250ms", "30s", "5m", "2h", "7d", "0s"] {
+            assert_eq!(s.parse::<HumanDuration>().unwrap().to_string(), s);
+        }
+    }
+}
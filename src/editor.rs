@@ -0,0 +1,96 @@
+use std::env;
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+/// The line (from 1) of `contents` where alias `name` is defined: its
+/// `[aliases.name]` header, or its key in an `[aliases]` table. A plain
+/// scan rather than a TOML parser, which would forget where things were.
+pub fn line_of(contents: &str, name: &str) -> Option<usize> {
+    let keys = [
+        name.to_string(),
+        format!("\"{}\"", name),
+        format!("'{}'", name),
+    ];
+    let mut table = String::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[').filter(|_| !line.starts_with("[[")) {
+            table = header
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect();
+            if keys.iter().any(|key| table == format!("aliases.{}", key)) {
+                return Some(index + 1);
+            }
+            continue;
+        }
+        let key = line.split('=').next().unwrap_or_default().trim();
+        if table == "aliases" && line.contains('=') && keys.iter().any(|k| k == key) {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+/// Open `path` at `line` in `$VISUAL` or `$EDITOR` (vi, or Notepad on
+/// Windows, without either), waiting for it to exit. Most editors take
+/// `+line`; the ones that don't take `path:line`.
+pub fn open(path: &Path, line: Option<usize>) -> io::Result<ExitStatus> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let mut command = Command::new(program);
+    command.args(words);
+
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match (line, name) {
+        (Some(line), "code" | "codium" | "cursor") => {
+            command
+                .arg("--goto")
+                .arg(format!("{}:{}", path.display(), line))
+        }
+        (Some(line), "subl" | "zed" | "hx" | "helix") => {
+            command.arg(format!("{}:{}", path.display(), line))
+        }
+        (Some(line), _) if name != "notepad" => command.arg(format!("+{}", line)).arg(path),
+        _ => command.arg(path),
+    };
+    command.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_of() {
+        let contents = r#"version = 1
+
+[aliases]
+cat = { command = "bat" }
+"la" = { command = "eza --all" }
+
+[aliases.ls]
+command = "eza"
+
+[ aliases."g++" ]
+command = "clang++"
+"#;
+        assert_eq!(line_of(contents, "cat"), Some(4));
+        assert_eq!(line_of(contents, "la"), Some(5));
+        assert_eq!(line_of(contents, "ls"), Some(7));
+        assert_eq!(line_of(contents, "g++"), Some(10));
+        assert_eq!(line_of(contents, "version"), None);
+        assert_eq!(line_of(contents, "command"), None);
+    }
+}
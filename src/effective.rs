@@ -0,0 +1,205 @@
+use crate::aliases::Alias;
+use crate::config::Settings;
+use crate::json::Value;
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where a setting's effective value came from. Earlier layers win: a flag
+/// on the command line over the environment, over the alias' own table,
+/// over `[settings]` (defaults included). shdw has no profiles, so nothing
+/// sits between the alias and `[settings]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Flag,
+    Env(&'static str),
+    Alias,
+    Settings,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::Flag => write!(f, "flag"),
+            Layer::Env(var) => write!(f, "env {}", var),
+            Layer::Alias => write!(f, "alias"),
+            Layer::Settings => write!(f, "settings"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub layer: Layer,
+}
+
+/// The settings one alias runs with, each from the first layer that sets
+/// it. Shims and `shdw run` go by these rather than `[settings]` alone.
+#[derive(Clone, Debug)]
+pub struct Effective {
+    /// Run the original: `--shdw-raw`, `SHDW_RAW`, `always_use_raw`.
+    pub raw: Resolved<bool>,
+    pub help_banner: Resolved<bool>,
+    pub failure_footer: Resolved<bool>,
+    pub stats: Resolved<bool>,
+    /// Where the alias' link goes; there's no flag or variable for it.
+    pub bin_path: Resolved<PathBuf>,
+}
+
+impl Effective {
+    /// `raw` is whether the command line asked for the original.
+    pub fn resolve(alias: &Alias, settings: &Settings, raw: bool) -> Self {
+        let flag = raw.then_some(Resolved {
+            value: true,
+            layer: Layer::Flag,
+        });
+        Self {
+            raw: first(
+                [flag, from_env("SHDW_RAW"), None],
+                settings.always_use_raw(),
+            ),
+            help_banner: first(
+                [
+                    None,
+                    from_env("SHDW_HELP_BANNER"),
+                    from_alias(alias.help_banner()),
+                ],
+                settings.help_banner(),
+            ),
+            failure_footer: first(
+                [
+                    None,
+                    from_env("SHDW_FAILURE_FOOTER"),
+                    from_alias(alias.failure_footer()),
+                ],
+                settings.failure_footer(),
+            ),
+            stats: first(
+                [None, from_env("SHDW_STATS"), from_alias(alias.stats())],
+                settings.stats(),
+            ),
+            bin_path: Resolved {
+                value: alias.link_dir(settings).to_path_buf(),
+                layer: match alias.bin_path() {
+                    Some(_) => Layer::Alias,
+                    None => Layer::Settings,
+                },
+            },
+        }
+    }
+
+    /// Each setting's name, value and layer, in the order they're listed.
+    pub fn rows(&self) -> [(&'static str, String, Layer); 5] {
+        let row =
+            |name, resolved: &Resolved<bool>| (name, resolved.value.to_string(), resolved.layer);
+        [
+            row("raw", &self.raw),
+            row("help_banner", &self.help_banner),
+            row("failure_footer", &self.failure_footer),
+            row("stats", &self.stats),
+            (
+                "bin_path",
+                self.bin_path.value.display().to_string(),
+                self.bin_path.layer,
+            ),
+        ]
+    }
+
+    /// `{"raw": {"value": false, "layer": "settings"}, ...}`.
+    pub fn to_json(&self) -> Value {
+        let entry = |value: Value, layer: Layer| {
+            Value::object([("value", value), ("layer", Value::from(layer.to_string()))])
+        };
+        let flag = |resolved: &Resolved<bool>| entry(Value::from(resolved.value), resolved.layer);
+        Value::object([
+            ("raw", flag(&self.raw)),
+            ("help_banner", flag(&self.help_banner)),
+            ("failure_footer", flag(&self.failure_footer)),
+            ("stats", flag(&self.stats)),
+            (
+                "bin_path",
+                entry(
+                    Value::from(self.bin_path.value.display().to_string()),
+                    self.bin_path.layer,
+                ),
+            ),
+        ])
+    }
+}
+
+/// The first of `layers` that's set, in precedence order (flag, env, alias),
+/// or else `[settings]`' value.
+fn first(layers: [Option<Resolved<bool>>; 3], setting: bool) -> Resolved<bool> {
+    layers.into_iter().flatten().next().unwrap_or(Resolved {
+        value: setting,
+        layer: Layer::Settings,
+    })
+}
+
+/// `var` as a switch, like `SHDW_ASCII`: on unless it's `0`. Empty is unset.
+fn from_env(var: &'static str) -> Option<Resolved<bool>> {
+    let value = env::var(var).ok().filter(|value| !value.is_empty())?;
+    Some(Resolved {
+        value: value != "0",
+        layer: Layer::Env(var),
+    })
+}
+
+fn from_alias(value: Option<bool>) -> Option<Resolved<bool>> {
+    value.map(|value| Resolved {
+        value,
+        layer: Layer::Alias,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_layers_in_order() {
+        let config: Config = toml::from_str(
+            "[settings]\nstats = true\nalways_use_raw = true\n\n\
+             [aliases.ls]\ncommand = \"eza\"\nhelp_banner = true\nstats = false\n",
+        )
+        .unwrap();
+        let alias = config.aliases().get("ls").unwrap();
+        let vars = [
+            ("SHDW_RAW", None),
+            ("SHDW_HELP_BANNER", Some("0")),
+            ("SHDW_FAILURE_FOOTER", None),
+            ("SHDW_STATS", None),
+        ];
+        temp_env::with_vars(vars, || {
+            let effective = Effective::resolve(alias, config.settings(), false);
+            assert_eq!(
+                effective.raw,
+                Resolved {
+                    value: true,
+                    layer: Layer::Settings
+                }
+            );
+            assert_eq!(
+                effective.help_banner,
+                Resolved {
+                    value: false,
+                    layer: Layer::Env("SHDW_HELP_BANNER")
+                }
+            );
+            assert_eq!(effective.failure_footer.layer, Layer::Settings);
+            assert_eq!(
+                effective.stats,
+                Resolved {
+                    value: false,
+                    layer: Layer::Alias
+                }
+            );
+            assert_eq!(effective.bin_path.layer, Layer::Settings);
+
+            let flagged = Effective::resolve(alias, config.settings(), true);
+            assert_eq!(flagged.raw.layer, Layer::Flag);
+        });
+    }
+}
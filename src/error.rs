@@ -1,16 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Exit statuses returned by `shdw` and by shadowed commands.
+///
+/// These numbers are part of the public CLI contract: existing values never
+/// change meaning. Environments with conflicting conventions can remap them
+/// under `[settings.exit_codes]` using the snake_case names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ExitCode {
+    /// The command (or the wrapped replacement) succeeded.
     Success = 0,
+    /// An unexpected failure, such as a symlink that could not be created.
     GeneralError = 1,
+    /// The config file could not be read, parsed, or written, or is invalid.
     ConfigError = 2,
+    /// An alias is misconfigured or was invoked with unusable arguments.
     InvalidArguments = 64,
+    /// No alias (or no executable) exists for the requested name.
     CommandNotFound = 127,
+    /// The wrapped command could not be started or exited unsuccessfully.
     CommandFailed = 128,
+    /// An alias with that name already exists.
     DuplicateCommand = 129,
 }
 
+impl ExitCode {
+    pub const ALL: [ExitCode; 7] = [
+        ExitCode::Success,
+        ExitCode::GeneralError,
+        ExitCode::ConfigError,
+        ExitCode::InvalidArguments,
+        ExitCode::CommandNotFound,
+        ExitCode::CommandFailed,
+        ExitCode::DuplicateCommand,
+    ];
+
+    /// The stable name used in `[settings.exit_codes]`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExitCode::Success => "success",
+            ExitCode::GeneralError => "general_error",
+            ExitCode::ConfigError => "config_error",
+            ExitCode::InvalidArguments => "invalid_arguments",
+            ExitCode::CommandNotFound => "command_not_found",
+            ExitCode::CommandFailed => "command_failed",
+            ExitCode::DuplicateCommand => "duplicate_command",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ExitCode::Success => "The command succeeded",
+            ExitCode::GeneralError => "Unexpected failure (e.g. a symlink could not be created)",
+            ExitCode::ConfigError => "The config could not be loaded, saved, or is invalid",
+            ExitCode::InvalidArguments => "Misconfigured alias or unusable arguments",
+            ExitCode::CommandNotFound => "No alias or executable with that name",
+            ExitCode::CommandFailed => "The wrapped command failed or could not be started",
+            ExitCode::DuplicateCommand => "An alias with that name already exists",
+        }
+    }
+
+    /// The numeric status after applying any configured remapping.
+    pub fn code(&self, overrides: &HashMap<ExitCode, i32>) -> i32 {
+        overrides.get(self).copied().unwrap_or(*self as i32)
+    }
+}
+
 impl From<ExitCode> for i32 {
     fn from(code: ExitCode) -> i32 {
         code as i32
@@ -24,7 +82,9 @@ impl From<ShadowError> for ExitCode {
             ShadowError::CommandExecutionError(_) => ExitCode::CommandFailed,
             ShadowError::ConfigError(_) => ExitCode::ConfigError,
             ShadowError::InvalidReplacement(_) => ExitCode::InvalidArguments,
+            ShadowError::InvalidAlias(_) => ExitCode::InvalidArguments,
             ShadowError::AliasExists(_) => ExitCode::DuplicateCommand,
+            ShadowError::Unwritable(..) => ExitCode::GeneralError,
         }
     }
 }
@@ -37,10 +97,28 @@ pub enum ShadowError {
     AliasExists(String),
     #[error("Failed to execute command: {0}")]
     CommandExecutionError(#[from] std::io::Error),
-    #[error("Failed to load config: {0}")]
+    #[error("{0}")]
     ConfigError(String),
     #[error("Invalid replacement command: {0}")]
     InvalidReplacement(String),
+    #[error("{0}")]
+    InvalidAlias(String),
+    /// A link can't be made because its directory is on a read-only or full
+    /// filesystem. Nothing is wrong with the alias: it can be linked later.
+    #[error("{} is on a {} filesystem", .0.display(), .1)]
+    Unwritable(PathBuf, &'static str),
+}
+
+impl ShadowError {
+    /// `ShadowError::Unwritable` for `dir` if that's why `e` happened.
+    pub fn unwritable(dir: &Path, e: &std::io::Error) -> Option<Self> {
+        let reason = match e.kind() {
+            std::io::ErrorKind::ReadOnlyFilesystem => "read-only",
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded => "full",
+            _ => return None,
+        };
+        Some(ShadowError::Unwritable(dir.to_path_buf(), reason))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ShadowError>;
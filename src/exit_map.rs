@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Exit statuses of the replacement to report as others, for scripts written
+/// against the original: `{ 2 = 1 }` makes a replacement's 2 look like the
+/// original's 1.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExitMap(BTreeMap<i32, i32>);
+
+impl ExitMap {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// What `code` is reported as, if it's translated.
+    pub fn get(&self, code: i32) -> Option<i32> {
+        self.0.get(&code).copied()
+    }
+}
+
+impl Serialize for ExitMap {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // TOML keys are always strings.
+        serializer.collect_map(self.0.iter().map(|(from, to)| (from.to_string(), to)))
+    }
+}
+
+impl<'de> Deserialize<'de> for ExitMap {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let table = BTreeMap::<String, i32>::deserialize(deserializer)?;
+        let mut map = BTreeMap::new();
+        for (from, to) in table {
+            let from = from.trim().parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "exit_map keys must be exit codes, not \"{}\"",
+                    from
+                ))
+            })?;
+            if !(0..=255).contains(&to) {
+                return Err(serde::de::Error::custom(format!(
+                    "exit_map values must be between 0 and 255, not {}",
+                    to
+                )));
+            }
+            map.insert(from, to);
+        }
+        Ok(Self(map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Serialize)]
+    struct Alias {
+        exit_map: ExitMap,
+    }
+
+    #[test]
+    fn test_parse_exit_map() {
+        let alias: Alias = toml::from_str("exit_map = { 2 = 1, 141 = 0 }").unwrap();
+        assert_eq!(alias.exit_map.get(2), Some(1));
+        assert_eq!(alias.exit_map.get(141), Some(0));
+        assert_eq!(alias.exit_map.get(1), None);
+        let written = toml::to_string(&alias).unwrap();
+        assert_eq!(
+            toml::from_str::<Alias>(&written).unwrap().exit_map,
+            alias.exit_map
+        );
+
+        assert!(toml::from_str::<Alias>("exit_map = { x = 1 }").is_err());
+        assert!(toml::from_str::<Alias>("exit_map = { 2 = 256 }").is_err());
+    }
+}
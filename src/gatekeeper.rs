@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Why macOS might refuse to run the executable at `path`, if it would.
+/// A quarantined download is blocked by Gatekeeper; a Mach-O binary whose
+/// signature doesn't check out (or, on Apple silicon, that has none) is
+/// killed as it starts, and the shell only says "killed: 9".
+pub fn problem(path: &Path) -> Option<String> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    if quarantined(path) {
+        return Some(format!(
+            "{} is quarantined, so Gatekeeper may block it (clear it with `xattr -d com.apple.quarantine {}`)",
+            path.display(),
+            path.display()
+        ));
+    }
+    if !is_mach_o(path) {
+        return None;
+    }
+    let verify = Command::new("codesign")
+        .args(["--verify", "--strict"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if verify.status.success() {
+        return None;
+    }
+    let unsigned = String::from_utf8_lossy(&verify.stderr).contains("not signed at all");
+    if unsigned && !cfg!(target_arch = "aarch64") {
+        return None;
+    }
+    Some(format!(
+        "{} has {} code signature, so macOS kills it on launch (\"killed: 9\"); \
+         sign it with `codesign --force --sign - {}`",
+        path.display(),
+        if unsigned { "no" } else { "an invalid" },
+        path.display()
+    ))
+}
+
+/// Sign `path` ad hoc, as a binary shdw wrote itself needs to run on Apple
+/// silicon. Elsewhere there's nothing to do.
+pub fn sign(path: &Path) -> Result<(), String> {
+    if !cfg!(target_os = "macos") {
+        return Ok(());
+    }
+    let output = Command::new("codesign")
+        .args(["--force", "--sign", "-"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("could not run codesign: {}", e))?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+fn quarantined(path: &Path) -> bool {
+    Command::new("xattr")
+        .args(["-p", "com.apple.quarantine"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Scripts have no signature to check.
+fn is_mach_o(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    let read = File::open(path).and_then(|mut file| file.read_exact(&mut magic));
+    read.is_ok()
+        && matches!(
+            u32::from_be_bytes(magic),
+            0xfeedface | 0xfeedfacf | 0xcefaedfe | 0xcffaedfe | 0xcafebabe
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mach_o() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("eza");
+        std::fs::write(&binary, [0xcf, 0xfa, 0xed, 0xfe, 0x0c, 0x00, 0x00, 0x01]).unwrap();
+        let script = dir.path().join("tool");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        assert!(is_mach_o(&binary));
+        assert!(!is_mach_o(&script));
+        assert!(!is_mach_o(&dir.path().join("missing")));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::state::{self, State};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// One thing garbage collection did (or would do).
+pub enum Action {
+    /// A log older than the age threshold was deleted.
+    Removed { path: PathBuf, bytes: u64 },
+    /// A log over the size threshold was cut down to its most recent output.
+    Trimmed { path: PathBuf, from: u64, to: u64 },
+    /// A link record that no longer points at shdw was dropped.
+    Forgot { name: String, path: PathBuf },
+}
+
+impl Action {
+    /// Bytes of disk freed.
+    pub fn freed(&self) -> u64 {
+        match self {
+            Action::Removed { bytes, .. } => *bytes,
+            Action::Trimmed { from, to, .. } => from - to,
+            Action::Forgot { .. } => 0,
+        }
+    }
+}
+
+pub struct Options {
+    pub older_than: Duration,
+    pub max_log_size: u64,
+    pub dry_run: bool,
+}
+
+/// Clean the state directory: old and oversized logs, and stale link records.
+/// Nothing outside the state directory is touched.
+pub fn collect(options: &Options) -> io::Result<Vec<Action>> {
+    let mut actions = Vec::new();
+    let now = SystemTime::now();
+
+    for path in files(&state::log_dir()) {
+        let metadata = fs::metadata(&path)?;
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if age > options.older_than {
+            if !options.dry_run {
+                fs::remove_file(&path)?;
+            }
+            actions.push(Action::Removed {
+                path,
+                bytes: metadata.len(),
+            });
+        } else if metadata.len() > options.max_log_size {
+            if !options.dry_run {
+                keep_tail(&path, options.max_log_size)?;
+            }
+            actions.push(Action::Trimmed {
+                path,
+                from: metadata.len(),
+                to: options.max_log_size,
+            });
+        }
+    }
+
+    let mut state = State::load();
+    let stale: Vec<_> = state
+        .links()
+        .iter()
+        .filter(|(_, record)| !record.is_ours())
+        .map(|(name, record)| (name.clone(), record.path.clone()))
+        .collect();
+    for (name, path) in stale {
+        state.forget_link(&name);
+        actions.push(Action::Forgot { name, path });
+    }
+    if !options.dry_run {
+        state.save().map_err(|e| io::Error::other(e.to_string()))?;
+    }
+
+    Ok(actions)
+}
+
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Cut `path` down to its last `size` bytes, starting at a line boundary.
+fn keep_tail(path: &Path, size: u64) -> io::Result<()> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(size)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let start = match tail.iter().position(|&b| b == b'\n') {
+        Some(newline) if len > size => newline + 1,
+        _ => 0,
+    };
+    OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(path)?
+        .write_all(&tail[start..])
+}
+
+/// `bytes` rounded to a unit, for reports.
+pub fn approximate(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = "";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    match unit {
+        "" => format!("{}B", bytes),
+        _ => format!("{:.1}{}", value, unit),
+    }
+}
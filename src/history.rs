@@ -0,0 +1,178 @@
+use crate::shell::Shell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// A command line that was typed often enough to be worth an alias.
+pub struct Suggestion {
+    pub name: String,
+    pub command: String,
+    pub count: usize,
+}
+
+/// The history file of `shell`, if it keeps one shdw knows how to read.
+pub fn file(shell: Shell) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    match shell {
+        Shell::Bash => Some(
+            env::var_os("HISTFILE")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".bash_history")),
+        ),
+        Shell::Zsh => Some(
+            env::var_os("HISTFILE")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".zsh_history")),
+        ),
+        Shell::Fish => Some(
+            dirs::data_dir()
+                .unwrap_or_else(|| home.join(".local/share"))
+                .join("fish/fish_history"),
+        ),
+        Shell::Nushell | Shell::Pwsh | Shell::Posix => None,
+    }
+}
+
+/// The command lines in a history file of `shell`, oldest first. Invalid
+/// UTF-8, which zsh writes for some characters, is replaced rather than
+/// failing the whole file.
+pub fn read(shell: Shell) -> Option<Vec<String>> {
+    let bytes = fs::read(file(shell)?).ok()?;
+    Some(parse(shell, &String::from_utf8_lossy(&bytes)))
+}
+
+fn parse(shell: Shell, text: &str) -> Vec<String> {
+    match shell {
+        // fish writes `- cmd: <command>` entries followed by `when:` and
+        // `paths:` lines of their own.
+        Shell::Fish => text
+            .lines()
+            .filter_map(|line| line.strip_prefix("- cmd: "))
+            .map(|command| command.replace("\\n", "\n").replace("\\\\", "\\"))
+            .collect(),
+        // zsh's extended history prefixes `: <start>:<elapsed>;`; bash with
+        // HISTTIMEFORMAT writes `#<time>` lines before each command.
+        _ => text
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| match line.strip_prefix(": ") {
+                Some(rest) => rest.split_once(';').map_or(line, |(_, command)| command),
+                None => line,
+            })
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+/// Commands in `history` that start with the same program, subcommands and
+/// flags at least `min_count` times, best savings first. A suggestion
+/// always has a flag in it, since a bare `git status` is short enough
+/// already, and is left out when an alias already runs it. Names are
+/// picked so that `taken` is false for them, e.g. not already commands.
+pub fn suggest(
+    history: &[String],
+    min_count: usize,
+    existing: &BTreeSet<String>,
+    taken: impl Fn(&str) -> bool,
+) -> Vec<Suggestion> {
+    // How often each prefix of each command line was typed.
+    let mut counts: BTreeMap<Vec<&str>, usize> = BTreeMap::new();
+    for line in history {
+        // Anything a shell has to interpret can't become an alias anyway.
+        if line.contains(|c: char| "|&;<>()$`{}*?!'\"\\\n".contains(c)) {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.first().is_none_or(|program| *program == "shdw") {
+            continue;
+        }
+        for len in 2..=words.len() {
+            *counts.entry(words[..len].to_vec()).or_default() += 1;
+        }
+    }
+
+    let mut candidates: Vec<(Vec<&str>, usize)> = counts
+        .iter()
+        .filter(|(words, count)| {
+            **count >= min_count
+                && words.iter().any(|word| word.starts_with('-'))
+                && !existing.contains(&words.join(" "))
+                // A longer command typed just as often is the better alias.
+                && !counts
+                    .range(words.to_vec()..)
+                    .skip(1)
+                    .take_while(|(longer, _)| longer.starts_with(words))
+                    .any(|(_, longer_count)| longer_count == *count)
+        })
+        .map(|(words, count)| (words.clone(), *count))
+        .collect();
+    candidates.sort_by_key(|(words, count)| std::cmp::Reverse(words.join(" ").len() * count));
+
+    let mut names = BTreeSet::new();
+    let mut suggestions = Vec::new();
+    for (words, count) in candidates {
+        let name = unique_name(&words, |name| names.contains(name) || taken(name));
+        names.insert(name.clone());
+        suggestions.push(Suggestion {
+            name,
+            command: words.join(" "),
+            count,
+        });
+    }
+    suggestions
+}
+
+/// The initials of the words (`kubectl get pods -n` → `kgpn`), made unique
+/// with a number if they have to be.
+fn unique_name(words: &[&str], taken: impl Fn(&str) -> bool) -> String {
+    let initials: String = words
+        .iter()
+        .filter_map(|word| word.trim_start_matches('-').chars().next())
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let base = if initials.len() < 2 {
+        words
+            .join("-")
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "")
+    } else {
+        initials
+    };
+    std::iter::once(base.clone())
+        .chain((2..).map(|n| format!("{}{}", base, n)))
+        .find(|name| !taken(name))
+        .expect("an unused name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest() {
+        let zsh = "\
+: 1700000000:0;kubectl get pods -n kube-system
+: 1700000001:0;kubectl get pods -n kube-system
+: 1700000002:0;kubectl get pods -n kube-system -o wide
+: 1700000003:0;git status
+: 1700000004:0;git status
+: 1700000005:0;git status
+: 1700000006:0;ls -l | less
+: 1700000007:0;ls -l | less
+: 1700000008:0;ls -l | less
+";
+        let history = parse(Shell::Zsh, zsh);
+        assert_eq!(history[0], "kubectl get pods -n kube-system");
+
+        let suggestions = suggest(&history, 3, &BTreeSet::new(), |name| name == "kgpnk");
+        let found: Vec<_> = suggestions
+            .iter()
+            .map(|s| (s.name.as_str(), s.command.as_str(), s.count))
+            .collect();
+        assert_eq!(found, [("kgpnk2", "kubectl get pods -n kube-system", 3)]);
+
+        let fish = "- cmd: cargo test --workspace\n  when: 1700000000\n";
+        assert_eq!(parse(Shell::Fish, fish), ["cargo test --workspace"]);
+    }
+}
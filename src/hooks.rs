@@ -0,0 +1,136 @@
+use crate::json::Value;
+use crate::secrets;
+use crate::warning;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+
+/// Commands to run after shdw changes something, from `[settings.hooks]`,
+/// e.g. to commit a dotfiles repository or re-run home-manager, or when a
+/// shim finds its replacement missing. A hook that's an http(s) URL is
+/// POSTed the event as JSON instead.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_add: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_remove: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_sync: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_missing: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// Aliases were added or replaced.
+    Add,
+    /// An alias was removed.
+    Remove,
+    /// The links or shell functions were brought up to date.
+    Sync,
+    /// A shim's replacement isn't installed (any more).
+    Missing,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Event::Add => "add",
+            Event::Remove => "remove",
+            Event::Sync => "sync",
+            Event::Missing => "missing",
+        }
+    }
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Run `event`'s hook, if there is one, through the shell, with
+    /// `SHDW_EVENT` set to the event and `SHDW_ALIAS` to the aliases it
+    /// concerns, separated by spaces. What it hooks has already happened,
+    /// so a hook that fails is a warning.
+    pub fn run(&self, event: Event, aliases: &[&str]) {
+        let hook = match event {
+            Event::Add => &self.on_add,
+            Event::Remove => &self.on_remove,
+            Event::Sync => &self.on_sync,
+            Event::Missing => &self.on_missing,
+        };
+        let Some(hook) = hook else {
+            return;
+        };
+        let status = match hook.starts_with("https://") || hook.starts_with("http://") {
+            true => post(hook, event, aliases),
+            false => secrets::shell(hook)
+                .stdin(Stdio::inherit())
+                .env("SHDW_EVENT", event.name())
+                .env("SHDW_ALIAS", aliases.join(" "))
+                .status(),
+        };
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => warning::warn(format!(
+                "the on_{} hook exited with {}",
+                event.name(),
+                status
+            )),
+            Err(e) => warning::warn(format!("could not run the on_{} hook: {}", event.name(), e)),
+        }
+    }
+}
+
+/// `{"event": "missing", "aliases": ["ls"]}` to `url`, through curl like
+/// subscriptions. A slow endpoint holds up the command only so long.
+fn post(url: &str, event: Event, aliases: &[&str]) -> io::Result<ExitStatus> {
+    let body = Value::object([
+        ("event", Value::from(event.name())),
+        ("aliases", Value::from(aliases.to_vec())),
+    ]);
+    Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--max-time", "10"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", &body.compact(), url])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+}
+
+static WATCHED: OnceLock<Hooks> = OnceLock::new();
+
+/// Keep `hooks` for `missing`, which runs where a shim only has the alias.
+pub fn watch(hooks: &Hooks) {
+    if hooks.on_missing.is_some() {
+        let _ = WATCHED.set(hooks.clone());
+    }
+}
+
+/// Run the `on_missing` hook, if `watch` was given one, for `alias`.
+pub fn missing(alias: &str) {
+    if let Some(hooks) = WATCHED.get() {
+        hooks.run(Event::Missing, &[alias]);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_sees_event_and_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("hook.log");
+        let hooks: Hooks = toml::from_str(&format!(
+            "on_add = 'echo \"$SHDW_EVENT $SHDW_ALIAS\" >> {}'",
+            log.display()
+        ))
+        .unwrap();
+        hooks.run(Event::Add, &["ls", "cat"]);
+        hooks.run(Event::Remove, &["ls"]);
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "add ls cat\n");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::adopt;
+use std::path::Path;
+
+/// An alias found in another setup, ready to be added.
+pub struct Found {
+    pub name: String,
+    pub command: String,
+    /// Where it was found, e.g. `~/.bash_aliases:12`.
+    pub origin: String,
+}
+
+/// A definition that can't become an alias, with the reason.
+pub struct Rejected {
+    pub origin: String,
+    pub reason: String,
+}
+
+/// Shell builtins: an alias for one of these can't become an executable.
+const BUILTINS: &[&str] = &[
+    ".", "builtin", "cd", "eval", "exec", "export", "history", "noglob", "popd", "pushd", "set",
+    "source", "unset",
+];
+
+/// Aliases defined in `text`, as written in an rc file (`alias ll='ls -l'`)
+/// or printed by bash, zsh or fish's `alias` builtin. With `bare`, lines
+/// without the `alias` keyword count too, as zsh prints them that way;
+/// in files such a line is a variable assignment instead.
+pub fn shell_aliases(text: &str, bare: bool, origin: &str) -> (Vec<Found>, Vec<Rejected>) {
+    let mut found = Vec::new();
+    let mut rejected = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let origin = format!("{}:{}", origin, number + 1);
+        match parse_definition(line, bare) {
+            None => {}
+            Some(Ok((name, command))) => found.push(Found {
+                name,
+                command,
+                origin,
+            }),
+            Some(Err(reason)) => rejected.push(Rejected { origin, reason }),
+        }
+    }
+    (found, rejected)
+}
+
+/// Wrapper scripts and symlinks in `dir`, the same ones `shdw adopt` would
+/// find, left where they are.
+pub fn scripts(dir: &Path) -> (Vec<Found>, Vec<Rejected>) {
+    let (candidates, skipped) = adopt::scan(dir);
+    let found = candidates
+        .into_iter()
+        .map(|candidate| Found {
+            name: candidate.name,
+            command: candidate.command,
+            origin: candidate.path.display().to_string(),
+        })
+        .collect();
+    let rejected = skipped
+        .into_iter()
+        .map(|skip| Rejected {
+            origin: skip.path.display().to_string(),
+            reason: skip.reason.to_string(),
+        })
+        .collect();
+    (found, rejected)
+}
+
+/// `None` for lines that don't define an alias at all.
+fn parse_definition(line: &str, bare: bool) -> Option<Result<(String, String), String>> {
+    let line = line.trim();
+    let rest = match line.strip_prefix("alias ") {
+        Some(rest) => rest.trim_start(),
+        None if bare && line.contains('=') && !line.starts_with('#') => line,
+        None => return None,
+    };
+    if rest.starts_with('-') {
+        return Some(Err("alias options (e.g. zsh's -g) aren't supported".into()));
+    }
+    // bash and zsh write `name=value`; fish writes `name value`.
+    let split = rest.find(|c: char| c == '=' || c.is_whitespace())?;
+    let name = &rest[..split];
+    let command = match unquote(rest[split + 1..].trim_start()) {
+        Some(command) => command.trim().to_string(),
+        None => return Some(Err("unbalanced quotes".into())),
+    };
+    Some(check(name, &command).map(|()| (name.to_string(), command)))
+}
+
+/// Undo shell quoting of an alias value, stopping at a trailing comment.
+/// `None` if a quote is left open.
+fn unquote(value: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => loop {
+                match chars.next()? {
+                    '\'' => break,
+                    c => out.push(c),
+                }
+            },
+            '"' => loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                        out.push(chars.next()?)
+                    }
+                    c => out.push(c),
+                }
+            },
+            '\\' => out.push(chars.next()?),
+            '#' if out.is_empty() || out.ends_with(char::is_whitespace) => break,
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// The shell builtin `command` runs, if it runs one: only a shell can.
+pub fn runs_builtin(command: &str) -> Option<&str> {
+    command
+        .split_whitespace()
+        .next()
+        .filter(|program| BUILTINS.contains(program))
+}
+
+/// Why `command` can't be run as alias `name`, if it can't.
+fn check(name: &str, command: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(['/', '\'', '"']) {
+        return Err(format!("{:?} is not a command name", name));
+    }
+    let Some(program) = command.split_whitespace().next() else {
+        return Err(format!("{} has an empty command", name));
+    };
+    if let Some(c) = command
+        .chars()
+        .find(|c| "|&;<>()$`{}*?!~'\"\\".contains(*c))
+    {
+        return Err(format!(
+            "{} uses shell syntax ({}) that only a shell can run",
+            name, c
+        ));
+    }
+    if let Some(builtin) = runs_builtin(command) {
+        return Err(format!("{} runs the shell builtin {}", name, builtin));
+    }
+    if program == name {
+        return Err(format!(
+            "{} runs itself, which would make its shim call itself",
+            name
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_aliases() {
+        let dump = "\
+alias ll='ls -l'
+alias gs=\"git status\"  # short
+la='eza --all'
+alias l 'eza --oneline'
+alias ..='cd ..'
+alias grep='grep --color=auto'
+alias pipe='ps aux | less'
+PATH=/usr/bin
+";
+        let (found, rejected) = shell_aliases(dump, false, "rc");
+        let found: Vec<_> = found
+            .iter()
+            .map(|f| (f.name.as_str(), f.command.as_str(), f.origin.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("ll", "ls -l", "rc:1"),
+                ("gs", "git status", "rc:2"),
+                ("l", "eza --oneline", "rc:4")
+            ]
+        );
+        let origins: Vec<_> = rejected.iter().map(|r| r.origin.as_str()).collect();
+        assert_eq!(origins, ["rc:5", "rc:6", "rc:7"]);
+
+        let (found, _) = shell_aliases(dump, true, "stdin");
+        assert!(found.iter().any(|f| f.name == "la"));
+    }
+}
@@ -0,0 +1,115 @@
+use toml::{Table, Value};
+
+/// Keys that describe what an alias runs. An alias setting any of them
+/// replaces the parent's command rather than inheriting it.
+const COMMAND_KEYS: &[&str] = &["command", "template"];
+
+/// The table for alias `name` with everything it `extends` merged in,
+/// parents first. `tables` holds every alias as written.
+pub fn resolve(name: &str, tables: &Table) -> Result<Table, String> {
+    resolve_chain(name, tables, &mut Vec::new())
+}
+
+fn resolve_chain<'a>(
+    name: &'a str,
+    tables: &'a Table,
+    chain: &mut Vec<&'a str>,
+) -> Result<Table, String> {
+    if chain.contains(&name) {
+        chain.push(name);
+        return Err(format!("inheritance cycle: {}", chain.join(" → ")));
+    }
+    let table = match tables.get(name) {
+        Some(Value::Table(table)) => table,
+        _ => {
+            return Err(match chain.last() {
+                Some(child) => format!("alias '{}' extends unknown alias '{}'", child, name),
+                None => format!("unknown alias '{}'", name),
+            })
+        }
+    };
+    let Some(parent) = table.get("extends") else {
+        return Ok(table.clone());
+    };
+    let Some(parent) = parent.as_str() else {
+        return Err(format!("'extends' of alias '{}' must be a string", name));
+    };
+
+    chain.push(name);
+    let parent = resolve_chain(parent, tables, chain)?;
+    chain.pop();
+    Ok(merge(parent, table))
+}
+
+/// `child` on top of `parent`: `env` tables are merged, `args` accumulate,
+/// and every other key set on the child replaces the parent's.
+fn merge(mut parent: Table, child: &Table) -> Table {
+    if COMMAND_KEYS.iter().any(|key| child.contains_key(*key)) {
+        // A new command or template makes the parent's template parameters
+        // and appended arguments meaningless.
+        let known = crate::schema::alias_keys();
+        parent.retain(|key, _| known.contains(&key) && key != "args");
+        for key in COMMAND_KEYS {
+            parent.remove(*key);
+        }
+    }
+
+    for (key, value) in child {
+        match (key.as_str(), parent.get_mut(key), value) {
+            ("env", Some(Value::Table(env)), Value::Table(child_env)) => {
+                env.extend(child_env.clone());
+            }
+            ("args", Some(Value::String(args)), Value::String(child_args)) => {
+                args.push(' ');
+                args.push_str(child_args);
+            }
+            _ => {
+                parent.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    parent.remove("extends");
+    parent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(toml: &str) -> Table {
+        toml.parse().unwrap()
+    }
+
+    #[test]
+    fn test_resolve_merges_parents() {
+        let tables = tables(
+            r#"
+            l = { command = "eza", env = { A = "1", B = "1" } }
+            ll = { extends = "l", args = "--long", env = { B = "2" } }
+            lla = { extends = "ll", args = "--all", nice = 5 }
+            "#,
+        );
+        let resolved = resolve("lla", &tables).unwrap();
+        assert_eq!(resolved["command"].as_str(), Some("eza"));
+        assert_eq!(resolved["args"].as_str(), Some("--long --all"));
+        assert_eq!(resolved["env"]["A"].as_str(), Some("1"));
+        assert_eq!(resolved["env"]["B"].as_str(), Some("2"));
+        assert_eq!(resolved["nice"].as_integer(), Some(5));
+        assert!(!resolved.contains_key("extends"));
+    }
+
+    #[test]
+    fn test_resolve_detects_cycles() {
+        let tables = tables(
+            r#"
+            a = { extends = "b" }
+            b = { extends = "c" }
+            c = { extends = "a" }
+            "#,
+        );
+        assert_eq!(
+            resolve("a", &tables),
+            Err("inheritance cycle: a → b → c → a".to_string())
+        );
+    }
+}
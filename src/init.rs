@@ -0,0 +1,370 @@
+use crate::aliases::Alias;
+use crate::completions;
+use crate::config::{Backend, Config};
+use crate::error::ExitCode;
+use crate::quote;
+use crate::shell::Shell;
+use crate::state;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A snippet for the shell's startup file: bin directories on PATH plus
+/// completions, and for PowerShell the extra glue it needs.
+pub fn generate(shell: Shell, config: &Config, subcommands: &[String], functions: bool) -> String {
+    let settings = config.settings();
+    let mut aliases: Vec<_> = config.aliases().values().filter(|a| a.links()).collect();
+    aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+    if settings.backend() == Backend::Shell {
+        // Functions need neither the bin directories nor the
+        // command-not-found hook, which only explains missing symlinks.
+        let mut out = String::new();
+        if shell == Shell::Pwsh {
+            out.push_str(&powershell(&aliases, subcommands, false));
+        } else if let Some(script) = completions::generate(shell, &aliases) {
+            out.push_str(&script);
+        }
+        out.push('\n');
+        out.push_str(&alias_functions(shell, &aliases));
+        return out;
+    }
+
+    let dirs: BTreeSet<&Path> = std::iter::once(settings.bin_path().as_path())
+        .chain(aliases.iter().map(|alias| alias.link_dir(settings)))
+        .collect();
+
+    let mut out = String::new();
+    for dir in dirs {
+        out.push_str(&path_line(shell, dir));
+        out.push('\n');
+    }
+
+    if shell == Shell::Pwsh {
+        out.push_str(&powershell(&aliases, subcommands, functions));
+    } else if let Some(script) = completions::generate(shell, &aliases) {
+        out.push('\n');
+        out.push_str(&script);
+    }
+
+    let not_found = ExitCode::CommandNotFound.code(settings.exit_codes());
+    if let Some(handler) = command_not_found(shell, not_found) {
+        out.push('\n');
+        out.push_str(&handler);
+    }
+    out
+}
+
+/// With `backend = "shell"`, a function per alias that runs it through
+/// `shdw run`, so functions and symlinks behave the same.
+fn alias_functions(shell: Shell, aliases: &[&Alias]) -> String {
+    functions_running(shell, aliases, "shdw")
+}
+
+fn functions_running(shell: Shell, aliases: &[&Alias], shdw: &str) -> String {
+    let mut out = String::new();
+    for alias in aliases {
+        // PowerShell drops a bare `--` on its way to a native command.
+        let separator = if shell == Shell::Pwsh { "'--'" } else { "--" };
+        let run = format!(
+            "{} run {} {}",
+            shdw,
+            quote::word(shell, alias.name()),
+            separator
+        );
+        out.push_str(&shell.function(alias.name(), &run));
+        out.push('\n');
+    }
+    out
+}
+
+/// Every linked alias as a shell function, for `eval` on machines where no
+/// symlink can be created, whatever `backend` says. Functions call shdw by
+/// path when it isn't on PATH. For bash, zsh and POSIX shells, names that
+/// aren't a POSIX function name are left out, with a comment saying so: a
+/// quote or space in one would break the whole `eval`.
+pub fn eval_functions(shell: Shell, config: &Config, shdw: &str) -> String {
+    let mut aliases: Vec<_> = config.aliases().values().filter(|a| a.links()).collect();
+    aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut out = String::new();
+    if matches!(shell, Shell::Bash | Shell::Zsh | Shell::Posix) {
+        let (valid, invalid): (Vec<&Alias>, Vec<&Alias>) = aliases
+            .into_iter()
+            .partition(|alias| posix_name(alias.name()));
+        for alias in invalid {
+            out.push_str(&format!(
+                "# {} is not a valid POSIX function name, skipped\n",
+                alias.name()
+            ));
+        }
+        aliases = valid;
+    }
+    out.push_str(&functions_running(shell, &aliases, shdw));
+    out
+}
+
+/// An `abbr` per alias for fish, which expands as you type so you see and
+/// can edit what runs. Aliases relying on options only a shim applies are
+/// left out, with a comment saying which.
+pub fn fish_abbreviations(config: &Config) -> String {
+    let mut aliases: Vec<_> = config.aliases().values().collect();
+    aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut out = String::new();
+    for alias in aliases {
+        if let Some(option) = alias.shim_only() {
+            out.push_str(&format!(
+                "# {} uses {}, which an abbreviation can't, skipped\n",
+                alias.name(),
+                option
+            ));
+            continue;
+        }
+        let words: Vec<String> = alias
+            .argv::<&str>(&[])
+            .iter()
+            .map(|word| quote::fish(&word.to_string_lossy()))
+            .collect();
+        out.push_str(&format!(
+            "abbr -a -- {} {}\n",
+            quote::fish(alias.name()),
+            quote::fish(&words.join(" "))
+        ));
+    }
+    out
+}
+
+fn posix_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Where `shdw sync` writes the alias functions for `shell`. Nushell can
+/// only source files known when it parses its config, so it has none.
+pub fn functions_file(shell: Shell) -> Option<PathBuf> {
+    let extension = match shell {
+        Shell::Bash | Shell::Zsh | Shell::Posix => "sh",
+        Shell::Fish => "fish",
+        Shell::Pwsh => "ps1",
+        Shell::Nushell => return None,
+    };
+    Some(state::file(&format!("functions.{}", extension)))
+}
+
+/// Regenerate the function files, so open shells can pick up changed
+/// aliases by sourcing them; new shells get them from `shdw init`.
+pub fn sync(config: &Config) -> io::Result<Vec<PathBuf>> {
+    let mut aliases: Vec<_> = config.aliases().values().filter(|a| a.links()).collect();
+    aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut written = Vec::new();
+    for shell in [Shell::Posix, Shell::Fish, Shell::Pwsh] {
+        let Some(path) = functions_file(shell) else {
+            continue;
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, alias_functions(shell, &aliases))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// A command-not-found hook that lets `shdw command-not-found` explain (and
+/// offer to fix) aliases whose symlink is missing, handing everything else to
+/// whatever hook was installed before, such as distro package suggestions.
+/// `not_found` is the status shdw exits with for names it doesn't know.
+fn command_not_found(shell: Shell, not_found: i32) -> Option<String> {
+    let script = match shell {
+        Shell::Bash => format!(
+            r#"if [ -z "${{_shdw_cnf_installed-}}" ] && declare -F command_not_found_handle >/dev/null; then
+    eval "_shdw_previous_cnf() $(declare -f command_not_found_handle | tail -n +2)"
+fi
+_shdw_cnf_installed=1
+command_not_found_handle() {{
+    shdw command-not-found "$@"
+    local status=$?
+    [ "$status" -eq {0} ] || return "$status"
+    if declare -F _shdw_previous_cnf >/dev/null; then
+        _shdw_previous_cnf "$@"
+        return
+    fi
+    printf 'bash: %s: command not found\n' "$1" >&2
+    return 127
+}}
+"#,
+            not_found
+        ),
+        Shell::Zsh => format!(
+            r#"if [ -z "${{_shdw_cnf_installed-}}" ] && (( ${{+functions[command_not_found_handler]}} )); then
+    functions[_shdw_previous_cnf]=$functions[command_not_found_handler]
+fi
+_shdw_cnf_installed=1
+command_not_found_handler() {{
+    shdw command-not-found "$@"
+    local exit_status=$?
+    [ "$exit_status" -eq {0} ] || return "$exit_status"
+    if (( ${{+functions[_shdw_previous_cnf]}} )); then
+        _shdw_previous_cnf "$@"
+        return
+    fi
+    printf 'zsh: command not found: %s\n' "$1" >&2
+    return 127
+}}
+"#,
+            not_found
+        ),
+        Shell::Fish => format!(
+            r#"if not set -q _shdw_cnf_installed; and functions -q fish_command_not_found
+    functions --copy fish_command_not_found _shdw_previous_cnf
+end
+set -g _shdw_cnf_installed 1
+function fish_command_not_found
+    shdw command-not-found $argv
+    set -l exit_status $status
+    test $exit_status -eq {0}; or return $exit_status
+    if functions -q _shdw_previous_cnf
+        _shdw_previous_cnf $argv
+    else
+        __fish_default_command_not_found_handler $argv
+    end
+end
+"#,
+            not_found
+        ),
+        Shell::Nushell | Shell::Pwsh | Shell::Posix => return None,
+    };
+    Some(script)
+}
+
+fn path_line(shell: Shell, dir: &Path) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh | Shell::Posix => format!(
+            "case \":$PATH:\" in *:{0}:*) ;; *) export PATH={0}:\"$PATH\" ;; esac",
+            quote::posix(&dir.display().to_string())
+        ),
+        Shell::Pwsh => format!(
+            "if (-not (($env:PATH -split [IO.Path]::PathSeparator) -contains {0})) {{\n    \
+             $env:PATH = {0} + [IO.Path]::PathSeparator + $env:PATH\n}}",
+            quote::pwsh(&dir.display().to_string())
+        ),
+        _ => shell.path_line(dir),
+    }
+}
+
+fn pwsh_list<'a>(items: impl IntoIterator<Item = &'a str>) -> String {
+    let items: Vec<_> = items.into_iter().map(quote::pwsh).collect();
+    format!("@({})", items.join(", "))
+}
+
+/// PowerShell resolves its own aliases (`ls`, `cat`, `rm`, ...) before any
+/// executable on PATH, so those are removed for shadowed names. With
+/// `functions`, each alias is also defined as a function that runs its
+/// replacement, for machines where the symlinks can't be created.
+fn powershell(aliases: &[&Alias], subcommands: &[String], functions: bool) -> String {
+    let names = pwsh_list(aliases.iter().map(|alias| alias.name()));
+    let mut out = format!(
+        "\nforeach ($name in {}) {{\n    \
+         if (Test-Path \"Alias:$name\") {{ Remove-Item \"Alias:$name\" -Force }}\n}}\n",
+        names
+    );
+
+    if functions {
+        out.push('\n');
+        for alias in aliases {
+            let mut argv = alias.command().split_whitespace();
+            let Some(program) = argv.next() else {
+                continue;
+            };
+            // Resolve the program as an application so an alias wrapping the
+            // command it shadows doesn't call its own function.
+            let invocation: Vec<_> = std::iter::once(format!(
+                "(Get-Command {} -CommandType Application -TotalCount 1)",
+                quote::pwsh(program)
+            ))
+            .chain(argv.map(quote::pwsh))
+            .collect();
+            out.push_str(&format!(
+                "function global:{} {{ & {} @args }}\n",
+                alias.name(),
+                invocation.join(" ")
+            ));
+        }
+    }
+
+    out.push_str(&format!(
+        r#"
+Register-ArgumentCompleter -Native -CommandName shdw -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $subcommands = {}
+    $aliases = {}
+    $words = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})
+    $position = if ($wordToComplete) {{ $words.Count - 1 }} else {{ $words.Count }}
+    $candidates = if ($position -le 1) {{
+        $subcommands
+    }} elseif ($words[1] -in 'remove', 'rm', 'delete', 'which-wins') {{
+        $aliases
+    }} else {{
+        @()
+    }}
+    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        pwsh_list(subcommands.iter().map(String::as_str)),
+        names
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fish_abbreviations() {
+        let config: Config = toml::from_str(
+            r#"
+            [aliases]
+            ls = { command = "eza --group-directories-first" }
+            grep = { command = "rg", env = { RIPGREP_CONFIG_PATH = "~/.rgrc" } }
+            dus = { command = "du -sh *" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            fish_abbreviations(&config),
+            "abbr -a -- dus 'du -sh \\'*\\''\n\
+             # grep uses env, which an abbreviation can't, skipped\n\
+             abbr -a -- ls 'eza --group-directories-first'\n"
+        );
+    }
+
+    #[test]
+    fn test_eval_functions_skip_unusable_names() {
+        let config: Config = toml::from_str(
+            r#"
+            [aliases]
+            ll = { command = "ls -l" }
+            "it's" = { command = "echo it is" }
+            "#,
+        )
+        .unwrap();
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Posix] {
+            assert_eq!(
+                eval_functions(shell, &config, "shdw"),
+                "# it's is not a valid POSIX function name, skipped\n\
+                 ll() { shdw run ll -- \"$@\"; }\n",
+                "{:?}",
+                shell
+            );
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::ExitCode;
+use crate::log;
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+
+/// Whether an alias may run more than once at a time, for replacements
+/// that corrupt their own state when two copies overlap.
+///
+/// Written in config as `single_instance = true` to fail straight away while
+/// another invocation runs, or `"wait"` to wait for it to finish.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SingleInstance {
+    #[default]
+    Off,
+    Fail,
+    Wait,
+}
+
+/// Held while the alias runs; the lock goes with the file when it's dropped,
+/// or when the process dies.
+pub struct Guard {
+    _file: File,
+}
+
+impl SingleInstance {
+    pub fn is_off(&self) -> bool {
+        *self == SingleInstance::Off
+    }
+
+    /// Take alias `name`'s lock, or say who holds it. `None` when the alias
+    /// isn't single-instance.
+    pub fn acquire(&self, name: &str) -> Result<Option<Guard>, ExitCode> {
+        if self.is_off() {
+            return Ok(None);
+        }
+        let dir = state::file("locks");
+        let path = dir.join(format!("{}.lock", name));
+        let file = fs::create_dir_all(&dir).and_then(|_| {
+            OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .read(true)
+                .write(true)
+                .open(&path)
+        });
+        let mut file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                log::error(format!("Failed to open {}: {}", path.display(), e));
+                return Err(ExitCode::GeneralError);
+            }
+        };
+
+        if file.try_lock().is_err() {
+            let mut holder = String::new();
+            let _ = file.read_to_string(&mut holder);
+            let holder = match holder.trim() {
+                "" => String::new(),
+                pid => format!(" (pid {})", pid),
+            };
+            if *self == SingleInstance::Fail {
+                log::error(format!(
+                    "{} is already running{}; it is single_instance, so this run was refused",
+                    name, holder
+                ));
+                return Err(ExitCode::GeneralError);
+            }
+            log::info(format!(
+                "Waiting for the running {}{} to finish",
+                name, holder
+            ));
+            if let Err(e) = file.lock() {
+                log::error(format!("Failed to lock {}: {}", path.display(), e));
+                return Err(ExitCode::GeneralError);
+            }
+        }
+
+        // Note who holds it, for the message above.
+        let _ = file.set_len(0);
+        let _ = file.rewind();
+        let _ = write!(file, "{}", std::process::id());
+        Ok(Some(Guard { _file: file }))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SingleInstanceDef {
+    Flag(bool),
+    Mode(String),
+}
+
+impl Serialize for SingleInstance {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let def = match self {
+            SingleInstance::Off => SingleInstanceDef::Flag(false),
+            SingleInstance::Fail => SingleInstanceDef::Flag(true),
+            SingleInstance::Wait => SingleInstanceDef::Mode("wait".to_string()),
+        };
+        def.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SingleInstance {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match SingleInstanceDef::deserialize(deserializer)? {
+            SingleInstanceDef::Flag(false) => Ok(SingleInstance::Off),
+            SingleInstanceDef::Flag(true) => Ok(SingleInstance::Fail),
+            SingleInstanceDef::Mode(mode) if mode == "wait" => Ok(SingleInstance::Wait),
+            SingleInstanceDef::Mode(mode) if mode == "fail" => Ok(SingleInstance::Fail),
+            SingleInstanceDef::Mode(mode) => Err(serde::de::Error::custom(format!(
+                "single_instance must be true, false, \"fail\" or \"wait\", not \"{}\"",
+                mode
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_instance_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        temp_env::with_var("SHDW_STATE_DIR", Some(dir.path()), || {
+            let first = SingleInstance::Fail.acquire("sync").unwrap();
+            assert!(first.is_some());
+            assert!(SingleInstance::Fail.acquire("sync").is_err());
+            assert!(SingleInstance::Off.acquire("sync").unwrap().is_none());
+            drop(first);
+            assert!(SingleInstance::Fail.acquire("sync").unwrap().is_some());
+        });
+    }
+}
@@ -0,0 +1,368 @@
+use std::fmt;
+
+/// A JSON value, just enough to print machine-readable reports.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// An object with keys in the given order.
+    pub fn object<K: Into<String>>(fields: impl IntoIterator<Item = (K, Value)>) -> Self {
+        Value::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Add a field to an object; other values are returned unchanged.
+    pub fn with(mut self, key: impl Into<String>, value: Value) -> Self {
+        if let Value::Object(fields) = &mut self {
+            fields.push((key.into(), value));
+        }
+        self
+    }
+
+    /// On a single line, for line-oriented consumers.
+    pub fn compact(&self) -> String {
+        Compact(self).to_string()
+    }
+
+    /// The field `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Read back what shdw printed, e.g. another machine's `shdw doctor
+    /// --format json`. Numbers are integers, as shdw only prints those.
+    pub fn parse(text: &str) -> Result<Value, String> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected '{}' after the value", c)),
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        let pad = |f: &mut fmt::Formatter<'_>, n: usize| write!(f, "{:width$}", "", width = n * 2);
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) if items.is_empty() => write!(f, "[]"),
+            Value::Array(items) => {
+                writeln!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    pad(f, indent + 1)?;
+                    item.write(f, indent + 1)?;
+                    writeln!(f, "{}", if i + 1 < items.len() { "," } else { "" })?;
+                }
+                pad(f, indent)?;
+                write!(f, "]")
+            }
+            Value::Object(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Value::Object(fields) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    pad(f, indent + 1)?;
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, indent + 1)?;
+                    writeln!(f, "{}", if i + 1 < fields.len() { "," } else { "" })?;
+                }
+                pad(f, indent)?;
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), String> {
+        for expected in word.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("expected '{}'", word));
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('n') => self.expect("null").map(|_| Value::Null),
+            Some('t') => self.expect("true").map(|_| Value::Bool(true)),
+            Some('f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some('"') => self.string().map(Value::String),
+            Some('[') => {
+                self.chars.next();
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&']').is_some() {
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Value::Array(items)),
+                        _ => return Err("expected ',' or ']' in an array".to_string()),
+                    }
+                }
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&'}').is_some() {
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    fields.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Value::Object(fields)),
+                        _ => return Err("expected ',' or '}' in an object".to_string()),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = self.chars.next_if(|c| *c == '-' || c.is_ascii_digit()) {
+                    number.push(c);
+                }
+                number
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| format!("bad number '{}'", number))
+            }
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let mut code = self.hex()?;
+                        // A character outside the BMP comes as a surrogate pair.
+                        if (0xd800..0xdc00).contains(&code) {
+                            self.expect("\\u")?;
+                            let low = self.hex()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err(format!(
+                                    "bad surrogate pair '\\u{:04x}\\u{:04x}'",
+                                    code, low
+                                ));
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        s.push(char::from_u32(code).ok_or("bad \\u escape")?);
+                    }
+                    Some(c) => s.push(c),
+                    None => return Err("unterminated string".to_string()),
+                },
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.chars.next()).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("bad \\u escape '{}'", digits))
+    }
+}
+
+struct Compact<'a>(&'a Value);
+
+impl fmt::Display for Compact<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { "," } else { "" }, Compact(item))?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    write!(f, "{}", if i > 0 { "," } else { "" })?;
+                    write_string(f, key)?;
+                    write!(f, ":{}", Compact(value))?;
+                }
+                write!(f, "}}")
+            }
+            scalar => scalar.write(f, 0),
+        }
+    }
+}
+
+/// Pretty-printed with two-space indentation.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Self {
+        Value::Number(n as i64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&std::path::Path> for Value {
+    fn from(path: &std::path::Path) -> Self {
+        Value::String(path.display().to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_nested() {
+        let value = Value::object([
+            ("name", Value::from("a \"b\"\n")),
+            ("count", Value::from(2usize)),
+            ("empty", Value::Array(vec![])),
+            ("items", Value::from(vec![Value::Null, Value::from(true)])),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            "{\n  \"name\": \"a \\\"b\\\"\\n\",\n  \"count\": 2,\n  \"empty\": [],\n  \
+             \"items\": [\n    null,\n    true\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_parse_what_was_printed() {
+        let value = Value::object([
+            ("name", Value::from("tab\t \"quoted\" \u{1}")),
+            ("count", Value::from(-12i64)),
+            ("empty", Value::object::<&str>([])),
+            ("items", Value::from(vec![Value::Null, Value::from(false)])),
+        ]);
+        assert_eq!(Value::parse(&value.to_string()), Ok(value.clone()));
+        assert_eq!(Value::parse(&value.compact()), Ok(value));
+        assert_eq!(
+            Value::parse(r#""\ud83d\ude00""#),
+            Ok(Value::from("\u{1f600}"))
+        );
+        assert!(Value::parse("[1, 2").is_err());
+        assert!(Value::parse("{} x").is_err());
+    }
+
+    #[test]
+    fn test_parse_bad_surrogates() {
+        assert_eq!(
+            Value::parse(r#""\ud800\u0041""#),
+            Err("bad surrogate pair '\\ud800\\u0041'".to_string())
+        );
+        assert!(Value::parse(r#""\udc00""#).is_err());
+        assert!(Value::parse(r#""\ud800x""#).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::config::Config;
+use crate::which;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Something about an alias that works but probably isn't what was meant,
+/// for `shdw lint`, with the command that would fix it when that's safe.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub alias: String,
+    pub message: String,
+    pub fix: Option<String>,
+}
+
+/// Every lint for the config's own aliases, by alias name. Subscribed and
+/// project aliases are fixed in their own files.
+pub fn lint(config: &Config) -> Vec<Lint> {
+    let mut aliases: Vec<_> = config
+        .aliases()
+        .values()
+        .filter(|alias| alias.is_own())
+        .collect();
+    aliases.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut lints = Vec::new();
+    let mut commands: BTreeMap<String, &str> = BTreeMap::new();
+    for alias in aliases {
+        let mut lint = |message: String, fix: Option<String>| {
+            lints.push(Lint {
+                alias: alias.name().to_string(),
+                message,
+                fix,
+            })
+        };
+        let words = alias
+            .command()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        match commands.get(&words) {
+            Some(first) => lint(format!("runs the same command as {}", first), None),
+            None => {
+                commands.insert(words, alias.name());
+            }
+        }
+        if alias.description().is_none() {
+            lint("has no description".to_string(), None);
+        }
+        if alias.links() && alias.original().is_none() {
+            lint(
+                format!(
+                    "shadows nothing: there's no other {} on PATH for --raw to run",
+                    alias.name()
+                ),
+                None,
+            );
+        }
+        // Only a command written out in the alias' own table is rewritten,
+        // not one it inherits.
+        let command = alias.written_command().unwrap_or(alias.command());
+        let own = alias
+            .written()
+            .is_some_and(|table| table.contains_key("command"));
+        let fixable = |fix: Option<String>| fix.filter(|_| own);
+        let mut command = command.to_string();
+        if let Some((message, fix)) = absolute_program(&command) {
+            let fix = fixable(fix);
+            command = fix.clone().unwrap_or(command);
+            lint(message, fix);
+        }
+        if let Some((message, fix)) = quoting(&command) {
+            lint(message, fixable(fix));
+        }
+    }
+    lints
+}
+
+/// A command run by absolute path, which differs between machines as
+/// package managers and home directories do; the bare name does when it
+/// finds the same program on PATH.
+fn absolute_program(command: &str) -> Option<(String, Option<String>)> {
+    let program = command.split_whitespace().next()?;
+    let path = Path::new(program);
+    if !path.is_absolute() {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    let in_home = dirs::home_dir().is_some_and(|home| path.starts_with(home));
+    let message = match in_home {
+        true => format!(
+            "runs {} in your home directory, which differs per machine",
+            program
+        ),
+        false => format!(
+            "runs {} by absolute path, which may differ per machine",
+            program
+        ),
+    };
+    let same = |found: &Path| fs::canonicalize(found).ok() == fs::canonicalize(path).ok();
+    let fix = which::find_all(name)
+        .into_iter()
+        .find(|found| !which::is_shadow(found))
+        .filter(|found| same(found))
+        .map(|_| command.replacen(program, name, 1));
+    Some((message, fix))
+}
+
+/// Quotes in a command, which shdw passes on as they are: it splits
+/// commands on whitespace, without a shell. Quotes around something
+/// without whitespace can just go; one argument with spaces in can't be
+/// written as a command at all.
+fn quoting(command: &str) -> Option<(String, Option<String>)> {
+    if !command.contains(['\'', '"']) {
+        return None;
+    }
+    let mut unquoted = String::new();
+    let mut quote = None;
+    for c in command.chars() {
+        match quote {
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None => unquoted.push(c),
+            Some(open) if c == open => quote = None,
+            Some(_) if c.is_whitespace() => {
+                return Some((
+                    "quotes an argument with spaces, but shdw splits commands on every space"
+                        .to_string(),
+                    None,
+                ))
+            }
+            Some(_) => unquoted.push(c),
+        }
+    }
+    let fix = quote.is_none().then_some(unquoted);
+    Some((
+        "has quotes, which shdw passes on as they are rather than removing like a shell"
+            .to_string(),
+        fix,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(
+            quoting("rg --glob '!*.min.js'").unwrap().1.as_deref(),
+            Some("rg --glob !*.min.js")
+        );
+        assert_eq!(quoting("git log --format=\"%h %s\"").unwrap().1, None);
+        assert_eq!(quoting("echo 'unbalanced").unwrap().1, None);
+        assert!(quoting("eza --icons").is_none());
+    }
+}
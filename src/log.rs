@@ -0,0 +1,137 @@
+use crate::json::Value;
+use clap::ValueEnum;
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How warnings, errors and notes are written to stderr. Reports a command
+/// was asked for, such as `check-config` problems, stay as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Lines for people
+    #[default]
+    Text,
+    /// One JSON object per line, for log shippers
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+/// 0 until chosen: shims never parse arguments, so they go by the
+/// environment alone.
+static FORMAT: AtomicU8 = AtomicU8::new(0);
+
+/// Use `format` from here on, as given by `--log-format`.
+pub fn set_format(format: LogFormat) {
+    FORMAT.store(format as u8 + 1, Ordering::Relaxed);
+}
+
+pub fn format() -> LogFormat {
+    match FORMAT.load(Ordering::Relaxed) {
+        0 => match env::var("SHDW_LOG_FORMAT") {
+            Ok(format) => LogFormat::from_str(&format, true).unwrap_or_default(),
+            Err(_) => LogFormat::Text,
+        },
+        n => LogFormat::value_variants()[n as usize - 1],
+    }
+}
+
+pub fn error(message: impl fmt::Display) {
+    emit(Level::Error, message);
+}
+
+pub fn warning(message: impl fmt::Display) {
+    emit(Level::Warning, message);
+}
+
+pub fn info(message: impl fmt::Display) {
+    emit(Level::Info, message);
+}
+
+/// Only written with `SHDW_DEBUG` set (to anything but `0`). Shims call it
+/// on every run, so pass `format_args!`: nothing is formatted otherwise.
+pub fn debug(message: impl fmt::Display) {
+    if debugging() {
+        emit(Level::Debug, message);
+    }
+}
+
+fn debugging() -> bool {
+    static DEBUG: OnceLock<bool> = OnceLock::new();
+    *DEBUG.get_or_init(|| env::var_os("SHDW_DEBUG").is_some_and(|debug| debug != "0"))
+}
+
+fn emit(level: Level, message: impl fmt::Display) {
+    match format() {
+        LogFormat::Text if level == Level::Warning => eprintln!("Warning: {}", message),
+        LogFormat::Text if level == Level::Debug => eprintln!("debug: {}", message),
+        LogFormat::Text => eprintln!("{}", message),
+        LogFormat::Json => eprintln!("{}", event(level, &message.to_string()).compact()),
+    }
+}
+
+/// The fields of a JSON line, which log pipelines may rely on: seconds since
+/// the epoch, the level, the name shdw ran as (an alias' name when running
+/// as its shim), its pid and the message.
+fn event(level: Level, message: &str) -> Value {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    let program = env::args_os()
+        .next()
+        .and_then(|arg0| {
+            let path = Path::new(&arg0);
+            let name = match cfg!(windows) {
+                true => path.file_stem(),
+                false => path.file_name(),
+            };
+            Some(name?.to_str()?.to_string())
+        })
+        .unwrap_or_else(|| "shdw".to_string());
+    Value::object([
+        ("ts", Value::from(ts)),
+        ("level", Value::from(level.name())),
+        ("program", Value::from(program)),
+        ("pid", Value::from(process::id() as i64)),
+        ("message", Value::from(message)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event() {
+        let line = event(Level::Warning, "could not save state: \"denied\"").compact();
+        assert!(line.starts_with("{\"ts\":"), "{}", line);
+        assert!(!line.contains('\n'));
+        assert!(line.contains("\"level\":\"warning\""));
+        assert!(line.ends_with("\"message\":\"could not save state: \\\"denied\\\"\"}"));
+        assert!(event(Level::Debug, "")
+            .compact()
+            .contains("\"level\":\"debug\""));
+    }
+}
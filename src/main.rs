@@ -1,8 +1,58 @@
+mod adopt;
 mod aliases;
+mod app_paths;
+mod archive;
+mod bake;
+mod bypass;
+mod cache;
 mod cli;
+mod clipboard;
 mod commands;
+mod competitors;
+mod completions;
 mod config;
+mod daemon;
+mod depth;
+mod diff;
+mod duration;
+mod editor;
+mod effective;
 mod error;
+mod exit_map;
+mod gatekeeper;
+mod gc;
+mod history;
+mod hooks;
+mod import;
+mod inherit;
+mod init;
+mod instance;
+mod json;
+mod lint;
+mod log;
+mod notify;
+mod output;
+mod packages;
+mod paths;
+mod pattern;
+mod preset;
+mod quote;
+mod redirect;
+mod rules;
+mod schema;
+mod secrets;
+mod shell;
+mod signals;
+mod size;
+mod state;
+mod stats;
+mod subscription;
+mod tee;
+mod template;
+mod umask;
+mod version;
+mod warning;
+mod which;
 
 use crate::cli::Cli;
 use crate::config::Config;
@@ -12,23 +62,83 @@ use std::path::Path;
 use std::process::exit;
 
 fn main() {
-    let args = env::args().next().unwrap();
-    let program_name = Path::new(&args)
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("shdw");
-    let config = match Config::load() {
+    let program_name = invoked_as();
+    let program_name = program_name.as_str();
+    let baked = bake::embedded();
+    // A shim does as little as it can: match its name, look the alias up
+    // in the daemon or the cache, and run it. A baked binary has its own
+    // config, which neither of them holds.
+    if program_name != "shdw" && baked.is_none() {
+        let (entry, from) = match daemon::query(program_name) {
+            Some(entry) => (Some(entry), "the daemon"),
+            None => (cache::get(program_name), "the cache"),
+        };
+        if let Some(entry) = entry {
+            log::debug(format_args!("{}: dispatched from {}", program_name, from));
+            let exit_code = Cli::execute_cached(&entry.alias, &entry.settings);
+            exit(exit_code.code(entry.settings.exit_codes()))
+        }
+        log::debug(format_args!(
+            "{}: not cached, loading the config",
+            program_name
+        ));
+    }
+
+    if program_name == "shdw" {
+        warning::collect();
+    }
+    let config = match &baked {
+        Some(contents) => Config::from_baked(contents),
+        None => Config::load(),
+    };
+    let config = match config {
         Ok(config) => config,
         Err(e) => {
-            eprintln!("Failed to load config: {}", e);
+            warning::finish();
+            log::error(format!("Failed to load config: {}", e));
             exit(ExitCode::ConfigError.into());
         }
     };
 
+    let exit_codes = config.settings().exit_codes().clone();
     let exit_code = match program_name {
         "shdw" => Cli::execute(config),
         command => Cli::execute_shadowed(config, command),
     };
 
-    exit(exit_code.into())
+    exit(exit_code.code(&exit_codes))
+}
+
+/// The name shdw was run as, which picks the alias a shim runs. `SHDW_AS`
+/// overrides it for wrappers that can't set argv[0], and is taken out of the
+/// environment so the alias' own commands don't inherit it. Otherwise it's
+/// argv[0]'s file name, whatever the path (`/home/me/.local/bin/ls`, `./ls`),
+/// without the `-` a login shell puts in front or, on Windows, `.exe`.
+fn invoked_as() -> String {
+    if let Some(name) = env::var_os("SHDW_AS") {
+        env::remove_var("SHDW_AS");
+        if let Some(name) = name.to_str().filter(|name| !name.is_empty()) {
+            return name.to_string();
+        }
+    }
+    let arg0 = env::args_os().next().unwrap_or_default();
+    if let Some(name) = program_name(Path::new(&arg0)) {
+        return name.to_string();
+    }
+    // argv[0] can be anything a wrapper liked; the binary's own name is the
+    // next best guess.
+    env::current_exe()
+        .ok()
+        .and_then(|exe| program_name(&exe).map(str::to_string))
+        .unwrap_or_else(|| "shdw".to_string())
+}
+
+fn program_name(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    let name = name.strip_prefix('-').unwrap_or(name);
+    let name = match cfg!(windows) && name.to_ascii_lowercase().ends_with(".exe") {
+        true => &name[..name.len() - ".exe".len()],
+        false => name,
+    };
+    (!name.is_empty()).then_some(name)
 }
@@ -0,0 +1,47 @@
+use crate::quote;
+use std::process::{Command, Stdio};
+
+/// Send a desktop notification using whatever the platform provides.
+///
+/// Notifications are best-effort: a missing notifier must never change the
+/// outcome of the wrapped command, so failures are silently ignored.
+pub fn send(title: &str, body: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_quote(body),
+            applescript_quote(title)
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else if cfg!(windows) {
+        let script = format!(
+            "[void][System.Reflection.Assembly]::LoadWithPartialName('System.Windows.Forms'); \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; \
+             $n.Visible = $true; \
+             $n.ShowBalloonTip(5000, {}, {}, 'Info'); \
+             Start-Sleep -Seconds 5; $n.Dispose()",
+            quote::pwsh(title),
+            quote::pwsh(body)
+        );
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=shdw", title, body]);
+        command
+    };
+
+    let _ = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+fn applescript_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,199 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::OnceLock;
+
+/// What the terminal on stdout can show, worked out once per run so that
+/// every command colors, truncates and draws symbols the same way.
+#[derive(Clone, Copy, Debug)]
+pub struct Terminal {
+    /// Columns to fit lines into; unknown when stdout isn't a terminal, in
+    /// which case lines are never cut.
+    pub width: Option<usize>,
+    pub color: bool,
+    pub unicode: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Style {
+    Bold,
+    Red,
+    Green,
+    Yellow,
+    /// Bold red, for the matched part of a line.
+    Match,
+}
+
+pub fn terminal() -> &'static Terminal {
+    static TERMINAL: OnceLock<Terminal> = OnceLock::new();
+    TERMINAL.get_or_init(|| {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let tty = io::stdout().is_terminal();
+        Terminal {
+            width: width(tty, var("COLUMNS")),
+            color: color(tty, var("NO_COLOR"), var("CLICOLOR"), var("CLICOLOR_FORCE")),
+            unicode: unicode(var("SHDW_ASCII"), var("TERM"), locale()),
+        }
+    })
+}
+
+impl Terminal {
+    /// `text` in `style`, if colors are on.
+    pub fn paint(&self, style: Style, text: &str) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        let code = match style {
+            Style::Bold => "1",
+            Style::Red => "31",
+            Style::Green => "32",
+            Style::Yellow => "33",
+            Style::Match => "1;31",
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+
+    pub fn arrow(&self) -> &'static str {
+        match self.unicode {
+            true => "→",
+            false => "->",
+        }
+    }
+
+    /// What goes before an entry of a tree, and before the entries under
+    /// it, when it's the `last` at its level or not.
+    pub fn branch(&self, last: bool) -> (&'static str, &'static str) {
+        match (self.unicode, last) {
+            (true, false) => ("├── ", "│   "),
+            (true, true) => ("└── ", "    "),
+            (false, false) => ("|-- ", "|   "),
+            (false, true) => ("`-- ", "    "),
+        }
+    }
+
+    /// A check mark or cross for a passing or failing line, in color.
+    pub fn mark(&self, ok: bool) -> String {
+        match (ok, self.unicode) {
+            (true, true) => self.paint(Style::Green, "✓"),
+            (true, false) => self.paint(Style::Green, "ok"),
+            (false, true) => self.paint(Style::Red, "✗"),
+            (false, false) => self.paint(Style::Red, "x"),
+        }
+    }
+
+    /// `line` cut to the terminal width, ending in an ellipsis if it was.
+    /// Only for plain text: escape codes would count as columns.
+    pub fn fit(&self, line: &str) -> String {
+        let Some(width) = self.width else {
+            return line.to_string();
+        };
+        if line.chars().count() <= width {
+            return line.to_string();
+        }
+        let ellipsis = if self.unicode { "…" } else { "..." };
+        let keep = width.saturating_sub(ellipsis.chars().count());
+        line.chars().take(keep).chain(ellipsis.chars()).collect()
+    }
+}
+
+/// `$COLUMNS` if set, else what the terminal reports.
+fn width(tty: bool, columns: Option<String>) -> Option<usize> {
+    if !tty {
+        return None;
+    }
+    if let Some(columns) = columns.and_then(|columns| columns.parse().ok()) {
+        return Some(columns);
+    }
+    query_width()
+}
+
+#[cfg(unix)]
+fn query_width() -> Option<usize> {
+    // SAFETY: TIOCGWINSZ only writes a winsize into the struct passed in.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    (ok && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(not(unix))]
+fn query_width() -> Option<usize> {
+    None
+}
+
+/// <https://no-color.org> and <https://bixense.com/clicolors/>: `NO_COLOR`
+/// wins, then `CLICOLOR_FORCE`, then `CLICOLOR=0`, then whether stdout is
+/// a terminal.
+fn color(
+    tty: bool,
+    no_color: Option<String>,
+    clicolor: Option<String>,
+    clicolor_force: Option<String>,
+) -> bool {
+    if no_color.is_some() {
+        return false;
+    }
+    if clicolor_force.is_some_and(|force| force != "0") {
+        return true;
+    }
+    if clicolor.is_some_and(|clicolor| clicolor == "0") {
+        return false;
+    }
+    tty
+}
+
+/// Unicode unless asked not to (`SHDW_ASCII`), on the Linux console, or
+/// under a locale that isn't UTF-8. No locale at all is taken as UTF-8,
+/// which every terminal of the last decade is.
+fn unicode(ascii: Option<String>, term: Option<String>, locale: Option<String>) -> bool {
+    if ascii.is_some_and(|ascii| ascii != "0") {
+        return false;
+    }
+    if term.is_some_and(|term| term == "linux" || term == "dumb") {
+        return false;
+    }
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => true,
+    }
+}
+
+/// The locale in effect for character types, by POSIX precedence.
+fn locale() -> Option<String> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection() {
+        let set = |s: &str| Some(s.to_string());
+        assert!(color(true, None, None, None));
+        assert!(!color(false, None, None, None));
+        assert!(!color(true, set("1"), None, set("1")));
+        assert!(color(false, None, set("0"), set("1")));
+        assert!(!color(true, None, set("0"), set("0")));
+
+        assert!(unicode(None, None, None));
+        assert!(unicode(None, set("xterm"), set("en_US.UTF-8")));
+        assert!(!unicode(None, None, set("C")));
+        assert!(!unicode(None, set("linux"), set("en_US.UTF-8")));
+        assert!(!unicode(set("1"), None, None));
+
+        assert_eq!(width(false, set("80")), None);
+        assert_eq!(width(true, set("100")), Some(100));
+
+        let narrow = Terminal {
+            width: Some(8),
+            color: false,
+            unicode: false,
+        };
+        assert_eq!(narrow.fit("ls -> eza --long"), "ls ->...");
+        assert_eq!(narrow.fit("ls -> x"), "ls -> x");
+    }
+}
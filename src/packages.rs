@@ -0,0 +1,198 @@
+use crate::which;
+
+/// A package manager shadow knows how to suggest install commands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Manager {
+    Brew,
+    Apt,
+    Dnf,
+    Pacman,
+    Scoop,
+    Cargo,
+}
+
+impl Manager {
+    /// Native managers first, cargo last since it builds from source.
+    const ALL: [Manager; 6] = [
+        Manager::Brew,
+        Manager::Apt,
+        Manager::Dnf,
+        Manager::Pacman,
+        Manager::Scoop,
+        Manager::Cargo,
+    ];
+
+    /// Package managers installed on this machine, in order of preference.
+    pub fn detect() -> Vec<Manager> {
+        Self::ALL
+            .into_iter()
+            .filter(|manager| which::find(manager.executable()).is_some())
+            .collect()
+    }
+
+    fn executable(&self) -> &'static str {
+        match self {
+            Manager::Brew => "brew",
+            Manager::Apt => "apt-get",
+            Manager::Dnf => "dnf",
+            Manager::Pacman => "pacman",
+            Manager::Scoop => "scoop",
+            Manager::Cargo => "cargo",
+        }
+    }
+
+    /// The argv that installs `package` with this manager.
+    pub fn install_argv(&self, package: &str) -> Vec<String> {
+        let argv: &[&str] = match self {
+            Manager::Brew => &["brew", "install"],
+            Manager::Apt => &["sudo", "apt-get", "install", "-y"],
+            Manager::Dnf => &["sudo", "dnf", "install", "-y"],
+            Manager::Pacman => &["sudo", "pacman", "-S", "--noconfirm"],
+            Manager::Scoop => &["scoop", "install"],
+            Manager::Cargo => &["cargo", "install"],
+        };
+        argv.iter()
+            .map(|arg| arg.to_string())
+            .chain(std::iter::once(package.to_string()))
+            .collect()
+    }
+}
+
+/// Where a commonly shadowed replacement can be installed from.
+pub struct Package {
+    program: &'static str,
+    brew: Option<&'static str>,
+    apt: Option<&'static str>,
+    dnf: Option<&'static str>,
+    pacman: Option<&'static str>,
+    scoop: Option<&'static str>,
+    cargo: Option<&'static str>,
+}
+
+/// Shorthand for entries whose package is named after the program everywhere
+/// except where noted.
+const fn same(program: &'static str) -> Package {
+    Package {
+        program,
+        brew: Some(program),
+        apt: Some(program),
+        dnf: Some(program),
+        pacman: Some(program),
+        scoop: Some(program),
+        cargo: Some(program),
+    }
+}
+
+const PACKAGES: &[Package] = &[
+    same("bat"),
+    Package {
+        apt: None,
+        dnf: None,
+        ..same("bottom")
+    }
+    .named("btm"),
+    Package {
+        scoop: None,
+        cargo: None,
+        ..same("btop")
+    },
+    Package {
+        apt: None,
+        dnf: None,
+        pacman: None,
+        cargo: None,
+        ..same("curlie")
+    },
+    Package {
+        scoop: Some("delta"),
+        ..same("git-delta")
+    }
+    .named("delta"),
+    Package {
+        cargo: None,
+        ..same("duf")
+    },
+    Package {
+        apt: None,
+        cargo: Some("du-dust"),
+        dnf: None,
+        ..same("dust")
+    },
+    same("eza"),
+    Package {
+        apt: Some("fd-find"),
+        dnf: Some("fd-find"),
+        cargo: Some("fd-find"),
+        ..same("fd")
+    },
+    Package {
+        scoop: None,
+        cargo: None,
+        ..same("gdu")
+    },
+    Package {
+        apt: None,
+        cargo: None,
+        ..same("helix")
+    }
+    .named("hx"),
+    same("lsd"),
+    Package {
+        cargo: None,
+        ..same("neovim")
+    }
+    .named("nvim"),
+    Package {
+        apt: None,
+        ..same("procs")
+    },
+    same("ripgrep").named("rg"),
+    Package {
+        apt: None,
+        ..same("sd")
+    },
+    Package {
+        dnf: None,
+        ..same("tealdeer")
+    }
+    .named("tldr"),
+    Package {
+        apt: None,
+        dnf: None,
+        ..same("xh")
+    },
+    same("zoxide"),
+];
+
+impl Package {
+    const fn named(self, program: &'static str) -> Self {
+        Self { program, ..self }
+    }
+
+    /// Look up the built-in entry for an executable name.
+    pub fn find(program: &str) -> Option<&'static Package> {
+        PACKAGES.iter().find(|package| package.program == program)
+    }
+
+    fn name_for(&self, manager: Manager) -> Option<&'static str> {
+        match manager {
+            Manager::Brew => self.brew,
+            Manager::Apt => self.apt,
+            Manager::Dnf => self.dnf,
+            Manager::Pacman => self.pacman,
+            Manager::Scoop => self.scoop,
+            Manager::Cargo => self.cargo,
+        }
+    }
+
+    /// Install argv for each of `managers` that carries this program.
+    pub fn install_commands(&self, managers: &[Manager]) -> Vec<Vec<String>> {
+        managers
+            .iter()
+            .filter_map(|manager| {
+                self.name_for(*manager)
+                    .map(|name| manager.install_argv(name))
+            })
+            .collect()
+    }
+}
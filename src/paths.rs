@@ -0,0 +1,173 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Expand a leading `~` to the home directory, as a shell would.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| path.to_path_buf()),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Where `config.toml` lives: `$SHDW_CONFIG_DIR`, else `$XDG_CONFIG_HOME/shdw`,
+/// else the platform's config directory (`~/.config/shdw` with
+/// `SHDW_STRICT_XDG=1`, even on macOS and Windows).
+pub fn config_dir() -> PathBuf {
+    env_dir("SHDW_CONFIG_DIR").unwrap_or_else(default_config_dir)
+}
+
+/// The config directory when `SHDW_CONFIG_DIR` isn't set.
+pub fn default_config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config", dirs::config_dir).join("shdw")
+}
+
+/// Where shdw keeps logs and other files between runs: `$SHDW_STATE_DIR`,
+/// else `$XDG_STATE_HOME/shdw`, else the platform's state (or cache)
+/// directory, or `~/.local/state/shdw` with `SHDW_STRICT_XDG=1`.
+pub fn state_dir() -> PathBuf {
+    if let Some(dir) = env_dir("SHDW_STATE_DIR") {
+        return dir;
+    }
+    xdg_dir("XDG_STATE_HOME", ".local/state", || {
+        dirs::state_dir().or_else(dirs::cache_dir)
+    })
+    .join("shdw")
+}
+
+/// The config directory used before any of the overrides above existed, so
+/// its files can be moved to the current location.
+pub fn legacy_config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("shdw"))
+}
+
+fn xdg_dir(var: &str, default: &str, platform: impl FnOnce() -> Option<PathBuf>) -> PathBuf {
+    if let Some(dir) = env_dir(var) {
+        return dir;
+    }
+    let home = || dirs::home_dir().map(|home| home.join(default));
+    match strict_xdg() {
+        true => home(),
+        false => platform().or_else(home),
+    }
+    .expect("Could not find home directory")
+}
+
+fn strict_xdg() -> bool {
+    env::var("SHDW_STRICT_XDG").is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false"))
+}
+
+/// An absolute directory from `var`; relative ones are ignored, as the XDG
+/// spec requires.
+fn env_dir(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+}
+
+/// What makes `dir` unsafe to have early on `PATH`: anyone else who can
+/// write to it can put their own `sudo` or `ls` in front of the real one.
+/// Directories owned by root are fine, as root can do that anyway.
+#[cfg(unix)]
+pub fn permission_problems(dir: &Path) -> Vec<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let Ok(metadata) = std::fs::metadata(dir) else {
+        return Vec::new();
+    };
+    let mut problems = Vec::new();
+    if world_writable(dir) {
+        problems.push("is writable by every user".to_string());
+    }
+    // SAFETY: geteuid has no preconditions and can't fail.
+    let uid = unsafe { libc::geteuid() };
+    if metadata.uid() != uid && metadata.uid() != 0 {
+        problems.push(format!(
+            "is owned by uid {}, not you (uid {})",
+            metadata.uid(),
+            uid
+        ));
+    }
+    problems
+}
+
+#[cfg(not(unix))]
+pub fn permission_problems(_dir: &Path) -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(unix)]
+pub fn world_writable(dir: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(dir).is_ok_and(|metadata| metadata.permissions().mode() & 0o002 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn world_writable(_dir: &Path) -> bool {
+    false
+}
+
+/// Take write permission on `dir` away from other users, as `chmod o-w`.
+#[cfg(unix)]
+pub fn remove_world_write(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = std::fs::metadata(dir)?.permissions();
+    permissions.set_mode(permissions.mode() & !0o002);
+    std::fs::set_permissions(dir, permissions)
+}
+
+#[cfg(not(unix))]
+pub fn remove_world_write(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_world_writable_bin_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(permission_problems(dir.path()).is_empty());
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert_eq!(
+            permission_problems(dir.path()),
+            ["is writable by every user"]
+        );
+        remove_world_write(dir.path()).unwrap();
+        assert!(!world_writable(dir.path()));
+    }
+
+    #[test]
+    fn test_config_dir_overrides() {
+        temp_env::with_vars(
+            [
+                ("SHDW_CONFIG_DIR", None),
+                ("XDG_CONFIG_HOME", Some("/xdg/config")),
+            ],
+            || assert_eq!(config_dir(), PathBuf::from("/xdg/config/shdw")),
+        );
+        temp_env::with_vars(
+            [
+                ("SHDW_CONFIG_DIR", Some("/dotfiles/shdw")),
+                ("XDG_CONFIG_HOME", Some("/xdg/config")),
+            ],
+            || assert_eq!(config_dir(), PathBuf::from("/dotfiles/shdw")),
+        );
+        temp_env::with_vars(
+            [
+                ("SHDW_CONFIG_DIR", None),
+                ("XDG_CONFIG_HOME", Some("relative")),
+                ("SHDW_STRICT_XDG", Some("1")),
+                ("HOME", Some("/home/me")),
+            ],
+            || assert_eq!(config_dir(), PathBuf::from("/home/me/.config/shdw")),
+        );
+    }
+}
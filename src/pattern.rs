@@ -0,0 +1,554 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A regular expression, for the few places in a config or on the command
+/// line that need one. It covers the everyday subset: literals, `.`,
+/// classes (`[a-z]`, `[^,]`, `\d`, `\w`, `\s`), anchors (`^`, `$`), groups
+/// with alternation (`(a|b)`) and the quantifiers `*`, `+`, `?` and
+/// `{n,m}`. Matching steps through the text once, following every way the
+/// pattern could match at the same time, so it takes time in proportion to
+/// the text however the pattern is written: shims check arguments anyone
+/// could have typed against it.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    source: String,
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+/// One step of a compiled pattern. `Split` tries its first branch before
+/// its second, which is what makes quantifiers greedy and alternation
+/// prefer the left side.
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+/// The most instructions a pattern may compile to; `(a{100}){100}` is about
+/// as big as that gets.
+const MAX_PROGRAM: u64 = 10_000;
+
+impl Pattern {
+    /// The same pattern, matching regardless of case.
+    pub fn ignoring_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find(text).is_some()
+    }
+
+    /// The byte range of the leftmost match in `text`. Of the matches
+    /// starting there, it's the one a backtracking matcher would find.
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        let chars: Vec<char> = text.chars().collect();
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut found = None;
+        for pos in 0..=chars.len() {
+            // Later starts rank below every thread already running.
+            if found.is_none() {
+                self.add(&mut current, 0, pos, chars.len(), pos);
+            }
+            if current.list.is_empty() {
+                break;
+            }
+            for &(pc, start) in &current.list {
+                let matched = match (&self.program[pc], chars.get(pos)) {
+                    (Inst::Match, _) => {
+                        // What's left ranks below this match.
+                        found = Some((start, pos));
+                        break;
+                    }
+                    (Inst::Char(c), Some(current)) => self.same(*current, *c),
+                    (Inst::Any, Some(_)) => true,
+                    (Inst::Class { ranges, negated }, Some(current)) => {
+                        self.in_class(*current, ranges) != *negated
+                    }
+                    _ => false,
+                };
+                if matched {
+                    self.add(&mut next, pc + 1, pos + 1, chars.len(), start);
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        found.map(|(start, end)| (offsets[start], offsets[end]))
+    }
+
+    /// Add a thread at `pc`, for a match begun at `start`, to `threads`,
+    /// following jumps and splits (first branches first) and checking
+    /// anchors at `pos` right away, so `threads` only holds ones waiting on
+    /// the next character, or a match.
+    fn add(&self, threads: &mut Threads, pc: usize, pos: usize, len: usize, start: usize) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if !threads.visit(pc) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Jump(to) => stack.push(to),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == len => stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                _ => threads.list.push((pc, start)),
+            }
+        }
+    }
+
+    fn same(&self, a: char, b: char) -> bool {
+        a == b || (self.ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+    }
+
+    fn in_class(&self, c: char, ranges: &[(char, char)]) -> bool {
+        let within = |c: char| ranges.iter().any(|(low, high)| (*low..=*high).contains(&c));
+        within(c)
+            || (self.ignore_case && (c.to_lowercase().any(within) || c.to_uppercase().any(within)))
+    }
+}
+
+/// The threads at one position in the text, by priority, with each
+/// instruction at most once.
+struct Threads {
+    list: Vec<(usize, usize)>,
+    seen: Vec<usize>,
+    generation: usize,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads {
+            list: Vec::new(),
+            seen: vec![0; len],
+            generation: 1,
+        }
+    }
+
+    /// Whether `pc` is new here, marking it seen.
+    fn visit(&mut self, pc: usize) -> bool {
+        let new = self.seen[pc] != self.generation;
+        self.seen[pc] = self.generation;
+        new
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.generation += 1;
+    }
+}
+
+/// How many instructions `node` compiles to, saturating rather than
+/// overflowing for absurd repetitions.
+fn size(node: &Node) -> u64 {
+    match node {
+        Node::Group(alternatives) => alternatives_size(alternatives),
+        Node::Repeat { node, min, max } => {
+            let one = size(node);
+            let optional = match max {
+                Some(max) => u64::from(max - min).saturating_mul(one + 1),
+                None => one + 2,
+            };
+            u64::from(*min).saturating_mul(one).saturating_add(optional)
+        }
+        _ => 1,
+    }
+}
+
+fn alternatives_size(alternatives: &[Vec<Node>]) -> u64 {
+    let sequences = alternatives
+        .iter()
+        .flatten()
+        .fold(0u64, |total, node| total.saturating_add(size(node)));
+    sequences.saturating_add(2 * (alternatives.len() as u64 - 1))
+}
+
+/// Turns parsed nodes into instructions for `Pattern::find`.
+#[derive(Default)]
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> usize {
+        self.program.push(inst);
+        self.program.len() - 1
+    }
+
+    fn alternatives(&mut self, alternatives: &[Vec<Node>]) {
+        let mut jumps = Vec::new();
+        for (i, sequence) in alternatives.iter().enumerate() {
+            let last = i + 1 == alternatives.len();
+            let split = (!last).then(|| self.push(Inst::Split(0, 0)));
+            for node in sequence {
+                self.node(node);
+            }
+            if let Some(split) = split {
+                jumps.push(self.push(Inst::Jump(0)));
+                self.program[split] = Inst::Split(split + 1, self.program.len());
+            }
+        }
+        let end = self.program.len();
+        for jump in jumps {
+            self.program[jump] = Inst::Jump(end);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Char(c) => {
+                self.push(Inst::Char(*c));
+            }
+            Node::Any => {
+                self.push(Inst::Any);
+            }
+            Node::Class { ranges, negated } => {
+                self.push(Inst::Class {
+                    ranges: ranges.clone(),
+                    negated: *negated,
+                });
+            }
+            Node::Start => {
+                self.push(Inst::Start);
+            }
+            Node::End => {
+                self.push(Inst::End);
+            }
+            Node::Group(alternatives) => self.alternatives(alternatives),
+            Node::Repeat { node, min, max } => {
+                for _ in 0..*min {
+                    self.node(node);
+                }
+                match max {
+                    None => {
+                        let split = self.push(Inst::Split(0, 0));
+                        self.node(node);
+                        self.push(Inst::Jump(split));
+                        self.program[split] = Inst::Split(split + 1, self.program.len());
+                    }
+                    Some(max) => {
+                        let splits: Vec<usize> = (*min..*max)
+                            .map(|_| {
+                                let split = self.push(Inst::Split(0, 0));
+                                self.node(node);
+                                split
+                            })
+                            .collect();
+                        let end = self.program.len();
+                        for split in splits {
+                            self.program[split] = Inst::Split(split + 1, end);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn alternatives(&mut self, depth: usize) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![Vec::new()];
+        while let Some(c) = self.chars.next() {
+            let node = match c {
+                '|' => {
+                    alternatives.push(Vec::new());
+                    continue;
+                }
+                ')' if depth > 0 => return Ok(alternatives),
+                ')' => return Err("unmatched )".into()),
+                '(' => {
+                    // Non-capturing groups mean the same here.
+                    if self.chars.peek() == Some(&'?') {
+                        self.chars.next();
+                        if self.chars.next() != Some(':') {
+                            return Err("only (?:...) groups are supported".into());
+                        }
+                    }
+                    Node::Group(self.alternatives(depth + 1)?)
+                }
+                '*' | '+' | '?' | '{' => {
+                    let sequence = alternatives.last_mut().expect("never empty");
+                    let Some(node) = sequence.pop() else {
+                        return Err(format!("{} has nothing to repeat", c));
+                    };
+                    if matches!(node, Node::Start | Node::End | Node::Repeat { .. }) {
+                        return Err(format!("{} has nothing to repeat", c));
+                    }
+                    let (min, max) = match c {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        '?' => (0, Some(1)),
+                        _ => self.bounds()?,
+                    };
+                    Node::Repeat {
+                        node: Box::new(node),
+                        min,
+                        max,
+                    }
+                }
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '[' => self.class()?,
+                '\\' => self.escape()?,
+                c => Node::Char(c),
+            };
+            alternatives.last_mut().expect("never empty").push(node);
+        }
+        match depth {
+            0 => Ok(alternatives),
+            _ => Err("unclosed (".into()),
+        }
+    }
+
+    fn bounds(&mut self) -> Result<(u32, Option<u32>), String> {
+        let mut inside = String::new();
+        loop {
+            match self.chars.next() {
+                Some('}') => break,
+                Some(c) => inside.push(c),
+                None => return Err("unclosed {".into()),
+            }
+        }
+        let number = |s: &str| {
+            s.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("invalid repetition {{{}}}", inside))
+        };
+        let (min, max) = match inside.split_once(',') {
+            None => {
+                let n = number(&inside)?;
+                (n, Some(n))
+            }
+            Some((min, "")) => (number(min)?, None),
+            Some((min, max)) => (number(min)?, Some(number(max)?)),
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(format!("invalid repetition {{{}}}", inside));
+        }
+        Ok((min, max))
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.chars.peek() == Some(&'^');
+        if negated {
+            self.chars.next();
+        }
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let low = match self.chars.next() {
+                None => return Err("unclosed [".into()),
+                Some(']') if !first => break,
+                Some('\\') => match self.chars.next() {
+                    Some(c) => match shorthand(c) {
+                        Some((shorthand, false)) => {
+                            ranges.extend(shorthand);
+                            first = false;
+                            continue;
+                        }
+                        Some((_, true)) => {
+                            return Err(format!("\\{} isn't supported inside [...]", c))
+                        }
+                        None => c,
+                    },
+                    None => return Err("trailing \\".into()),
+                },
+                Some(c) => c,
+            };
+            first = false;
+            let mut lookahead = self.chars.clone();
+            let high = match (lookahead.next(), lookahead.next()) {
+                (Some('-'), Some(high)) if high != ']' => {
+                    self.chars.next();
+                    self.chars.next();
+                    high
+                }
+                _ => low,
+            };
+            if high < low {
+                return Err(format!("invalid range {}-{}", low, high));
+            }
+            ranges.push((low, high));
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+
+    fn escape(&mut self) -> Result<Node, String> {
+        let c = self.chars.next().ok_or("trailing \\")?;
+        Ok(match shorthand(c) {
+            Some((ranges, negated)) => Node::Class { ranges, negated },
+            None if c.is_ascii_alphanumeric() => match c {
+                'n' => Node::Char('\n'),
+                't' => Node::Char('\t'),
+                _ => return Err(format!("unknown escape \\{}", c)),
+            },
+            None => Node::Char(c),
+        })
+    }
+}
+
+/// The ranges behind `\d`, `\w` and `\s`, and whether the escape is the
+/// negated (upper case) form.
+fn shorthand(c: char) -> Option<(Vec<(char, char)>, bool)> {
+    let ranges = match c.to_ascii_lowercase() {
+        'd' => vec![('0', '9')],
+        'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+        's' => vec![(' ', ' '), ('\t', '\r')],
+        _ => return None,
+    };
+    Some((ranges, c.is_ascii_uppercase()))
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().peekable(),
+        };
+        let alternatives = parser
+            .alternatives(0)
+            .map_err(|e| format!("Invalid pattern {:?}: {}", s, e))?;
+        if alternatives_size(&alternatives) >= MAX_PROGRAM {
+            return Err(format!("Invalid pattern {:?}: repeats too much", s));
+        }
+        let mut compiler = Compiler::default();
+        compiler.alternatives(&alternatives);
+        compiler.push(Inst::Match);
+        Ok(Self {
+            source: s.to_string(),
+            program: compiler.program,
+            ignore_case: false,
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern() {
+        let pattern = |s: &str| s.parse::<Pattern>().unwrap();
+        assert!(pattern("^(get|describe) pods").is_match("get pods -n kube-system"));
+        assert!(!pattern("^(get|describe) pods").is_match("delete pods"));
+        assert!(pattern(r"^--env=[a-z]+$").is_match("--env=staging"));
+        assert!(!pattern(r"^--env=[a-z]+$").is_match("--env=staging;rm"));
+        assert!(pattern(r"^\d{2,3}$").is_match("123"));
+        assert!(!pattern(r"^\d{2,3}$").is_match("1234"));
+        assert!(pattern("^a(b|c)*d$").is_match("abcbd"));
+        assert!(pattern("[^ ]+\\.rs").is_match("src/main.rs"));
+        assert_eq!(pattern("git").find("run légit git"), Some((7, 10)));
+        assert_eq!(pattern("GIT").ignoring_case().find("a Git"), Some((2, 5)));
+        assert!(pattern("^(a*)*b$").is_match("aaab"));
+
+        for invalid in ["(a", "a)", "*a", "[a", r"\q", "a{3,1}", "(a{1000}){1000}"] {
+            assert!(invalid.parse::<Pattern>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_long_input() {
+        let pattern = |s: &str| s.parse::<Pattern>().unwrap();
+        let long = "a".repeat(200_000);
+        assert!(pattern("^[a-z]+$").is_match(&long));
+        assert!(pattern("^(a|b)*$").is_match(&long));
+        assert!(!pattern("(a|b)*c").is_match(&long));
+        assert_eq!(
+            pattern("a+").find(&format!("x{}", long)),
+            Some((1, 200_001))
+        );
+    }
+
+    #[test]
+    fn test_pathological_pattern() {
+        let pattern = |s: &str| s.parse::<Pattern>().unwrap();
+        let started = std::time::Instant::now();
+        let text = "a".repeat(10_000);
+        // Backtracking tries 2^n ways to split the a's before failing.
+        assert!(!pattern("^(a|a)*b$").is_match(&text));
+        assert!(!pattern("^(a*)*b$").is_match(&text));
+        assert!(!pattern("^(a?){30}a{30}b$").is_match(&"a".repeat(30)));
+        assert!(pattern("^(a|a)*b$").is_match(&format!("{}b", text)));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_leftmost_first() {
+        let pattern = |s: &str| s.parse::<Pattern>().unwrap();
+        assert_eq!(pattern("a|ab").find("xab"), Some((1, 2)));
+        assert_eq!(pattern("ab|a").find("xab"), Some((1, 3)));
+        assert_eq!(pattern("a{2,3}").find("aaaa"), Some((0, 3)));
+        assert_eq!(pattern("(a|b)*b").find("abab"), Some((0, 4)));
+        assert_eq!(pattern("x*").find("yx"), Some((0, 0)));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::aliases::{Alias, Aliases};
+use crate::error::{Result, ShadowError};
+use serde::{Deserialize, Serialize};
+
+/// Who made a preset and what it's for: the `[preset]` table of a preset
+/// file. The rest of the file is `[aliases]` as in a config, so `shdw merge`
+/// reads presets as well.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PresetFile<'a> {
+    preset: &'a Metadata,
+    aliases: Aliases,
+}
+
+#[derive(Deserialize)]
+struct PresetHeader {
+    preset: Option<Metadata>,
+}
+
+/// A preset file holding `aliases`, resolved so they work without the
+/// templates and aliases they're built from, and without a `bin_path`,
+/// which only makes sense on this machine.
+pub fn to_toml(metadata: &Metadata, aliases: &[&Alias]) -> Result<String> {
+    let mut frozen = Aliases::default();
+    for alias in aliases {
+        let alias = alias.frozen().with_bin_path(None);
+        frozen.insert(alias.name().to_string(), alias);
+    }
+    let file = PresetFile {
+        preset: metadata,
+        aliases: frozen,
+    };
+    toml::to_string_pretty(&file).map_err(|e| ShadowError::ConfigError(e.to_string()))
+}
+
+/// The `[preset]` table of a preset file, if it is one.
+pub fn metadata(contents: &str) -> Result<Option<Metadata>> {
+    toml::from_str::<PresetHeader>(contents)
+        .map(|header| header.preset)
+        .map_err(|e| ShadowError::ConfigError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_preset_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.toml");
+        std::fs::write(
+            &config,
+            "[templates.pager]\ncommand = \"less -R\"\n\n\
+             [aliases.more]\ntemplate = \"pager\"\n\n\
+             [aliases.ls]\ncommand = \"eza\"\n",
+        )
+        .unwrap();
+        let config = Config::from_file(&config).unwrap();
+
+        let metadata = Metadata {
+            name: "pagers".to_string(),
+            description: Some("Nicer paging".to_string()),
+            author: None,
+        };
+        let contents = to_toml(&metadata, &[config.aliases().find("more").unwrap()]).unwrap();
+        assert_eq!(super::metadata(&contents).unwrap(), Some(metadata));
+
+        let preset = dir.path().join("pagers.toml");
+        std::fs::write(&preset, &contents).unwrap();
+        let preset = Config::from_file(&preset).unwrap();
+        assert_eq!(preset.aliases().len(), 1);
+        assert_eq!(preset.aliases().find("more").unwrap().command(), "less -R");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::shell::Shell;
+
+/// Characters no shell shdw writes for gives a meaning, so words made only of
+/// them need no quotes.
+fn plain(s: &str, extra: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_@+:,./-".contains(c) || extra.contains(c))
+}
+
+/// `s` as a single word in `shell`'s syntax, for the scripts `shdw init`,
+/// `export` and `completions` print.
+pub fn word(shell: Shell, s: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh | Shell::Posix => posix(s),
+        Shell::Fish => fish(s),
+        Shell::Nushell => nushell(s),
+        Shell::Pwsh => pwsh(s),
+    }
+}
+
+/// `s` as one POSIX shell word, quoted only if it has to be. zsh expands a
+/// leading `=` to a command's path, so such a word is quoted too.
+pub fn posix(s: &str) -> String {
+    match plain(s, "%=") && !s.starts_with('=') {
+        true => s.to_string(),
+        false => format!("'{}'", s.replace('\'', "'\\''")),
+    }
+}
+
+/// `s` as one fish word, where `\\` and `\'` are escapes inside single
+/// quotes too, and a leading `%` expands to a process ID.
+pub fn fish(s: &str) -> String {
+    match plain(s, "=") {
+        true => s.to_string(),
+        false => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'")),
+    }
+}
+
+/// `s` as a nushell string. Single quotes take everything but a single
+/// quote as it is; double quotes need `\` and `"` escaped. Neither
+/// interpolates `$`, which only `$"..."` does.
+pub fn nushell(s: &str) -> String {
+    match s.contains('\'') {
+        false => format!("'{}'", s),
+        true => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+/// `s` as a PowerShell verbatim string. PowerShell also ends one at the
+/// typographic single quotes, so those are doubled like `'`.
+pub fn pwsh(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('\'');
+    for c in s.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+            out.push(c);
+        }
+        out.push(c);
+    }
+    out.push('\'');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASES: &[&str] = &[
+        "",
+        "ls",
+        "--color=auto",
+        "two words",
+        "it's",
+        "say \"hi\"",
+        "$HOME",
+        "%PATH%",
+        "=ls",
+        "back\\slash",
+        "C:\\Program Files\\shdw.exe",
+        "*.rs",
+        "tab\there",
+        "naïve ✓",
+        "l’été",
+    ];
+
+    #[test]
+    fn test_quoting_per_shell() {
+        let expected: &[(&str, &str, &str, &str)] = &[
+            // (posix, fish, nushell, pwsh)
+            ("''", "''", "''", "''"),
+            ("ls", "ls", "'ls'", "'ls'"),
+            (
+                "--color=auto",
+                "--color=auto",
+                "'--color=auto'",
+                "'--color=auto'",
+            ),
+            ("'two words'", "'two words'", "'two words'", "'two words'"),
+            ("'it'\\''s'", "'it\\'s'", "\"it's\"", "'it''s'"),
+            (
+                "'say \"hi\"'",
+                "'say \"hi\"'",
+                "'say \"hi\"'",
+                "'say \"hi\"'",
+            ),
+            ("'$HOME'", "'$HOME'", "'$HOME'", "'$HOME'"),
+            ("%PATH%", "'%PATH%'", "'%PATH%'", "'%PATH%'"),
+            ("'=ls'", "=ls", "'=ls'", "'=ls'"),
+            (
+                "'back\\slash'",
+                "'back\\\\slash'",
+                "'back\\slash'",
+                "'back\\slash'",
+            ),
+            (
+                "'C:\\Program Files\\shdw.exe'",
+                "'C:\\\\Program Files\\\\shdw.exe'",
+                "'C:\\Program Files\\shdw.exe'",
+                "'C:\\Program Files\\shdw.exe'",
+            ),
+            ("'*.rs'", "'*.rs'", "'*.rs'", "'*.rs'"),
+            ("'tab\there'", "'tab\there'", "'tab\there'", "'tab\there'"),
+            ("'naïve ✓'", "'naïve ✓'", "'naïve ✓'", "'naïve ✓'"),
+            ("'l’été'", "'l’été'", "'l’été'", "'l’’été'"),
+        ];
+        for (s, (posix, fish, nushell, pwsh)) in CASES.iter().zip(expected) {
+            assert_eq!(word(Shell::Bash, s), *posix, "posix {:?}", s);
+            assert_eq!(word(Shell::Fish, s), *fish, "fish {:?}", s);
+            assert_eq!(word(Shell::Nushell, s), *nushell, "nushell {:?}", s);
+            assert_eq!(word(Shell::Pwsh, s), *pwsh, "pwsh {:?}", s);
+        }
+        assert_eq!(
+            nushell("it's \"quoted\" \\"),
+            "\"it's \\\"quoted\\\" \\\\\""
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_posix_round_trips_through_sh() {
+        for s in CASES.iter().filter(|s| !s.is_empty()) {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf %s {}", posix(s)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), *s);
+        }
+    }
+}
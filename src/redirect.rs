@@ -0,0 +1,92 @@
+use crate::paths;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+/// Where one of the replacement's output streams goes.
+///
+/// Written in config as `"inherit"`, `"null"`, a path (appended to), or a
+/// table `{ path = "...", append = false }` to truncate the file on each run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Output {
+    Inherit,
+    Null,
+    File { path: PathBuf, append: bool },
+}
+
+impl Output {
+    pub fn stdio(&self) -> io::Result<Stdio> {
+        match self {
+            Output::Inherit => Ok(Stdio::inherit()),
+            Output::Null => Ok(Stdio::null()),
+            Output::File { path, append } => {
+                let path = paths::expand_home(path);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let file = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(*append)
+                    .truncate(!*append)
+                    .open(&path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                Ok(file.into())
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OutputDef {
+    Simple(String),
+    File {
+        path: PathBuf,
+        #[serde(default = "OutputDef::default_append")]
+        append: bool,
+    },
+}
+
+impl OutputDef {
+    fn default_append() -> bool {
+        true
+    }
+}
+
+impl Serialize for Output {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let def = match self {
+            Output::Inherit => OutputDef::Simple("inherit".to_string()),
+            Output::Null => OutputDef::Simple("null".to_string()),
+            Output::File { path, append: true } => OutputDef::Simple(path.display().to_string()),
+            Output::File { path, append } => OutputDef::File {
+                path: path.clone(),
+                append: *append,
+            },
+        };
+        def.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Output {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match OutputDef::deserialize(deserializer)? {
+            OutputDef::Simple(s) if s == "inherit" => Output::Inherit,
+            OutputDef::Simple(s) if s == "null" => Output::Null,
+            OutputDef::Simple(s) => Output::File {
+                path: PathBuf::from(s),
+                append: true,
+            },
+            OutputDef::File { path, append } => Output::File { path, append },
+        })
+    }
+}
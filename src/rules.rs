@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+
+/// Extra arguments for the replacement, given only when the incoming
+/// arguments ask for a mode they matter in: `{ when = "-l", add = "--git" }`
+/// turns `ls -l` into `eza --git -l` but leaves `ls` as plain `eza`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArgRule {
+    /// The argument to look for. A single-letter flag such as `-l` is also
+    /// found among bundled ones (`-la`).
+    when: String,
+    /// Arguments to insert, split on whitespace like `command`.
+    add: String,
+}
+
+/// The arguments `rules` add for `args`, in rule order, to go between the
+/// alias' own arguments and the user's. Arguments after `--` are operands
+/// and never match, and an argument the user (or an earlier rule) already
+/// gave isn't added twice.
+pub fn extra_args<S: AsRef<OsStr>>(rules: &[ArgRule], args: &[S]) -> Vec<String> {
+    let args: Vec<&str> = args
+        .iter()
+        .filter_map(|arg| arg.as_ref().to_str())
+        .take_while(|arg| *arg != "--")
+        .collect();
+    let mut extra: Vec<String> = Vec::new();
+    for rule in rules {
+        if !args.iter().any(|arg| matches(&rule.when, arg)) {
+            continue;
+        }
+        for add in rule.add.split_whitespace() {
+            if !args.contains(&add) && !extra.iter().any(|a| a == add) {
+                extra.push(add.to_string());
+            }
+        }
+    }
+    extra
+}
+
+fn matches(when: &str, arg: &str) -> bool {
+    if when == arg {
+        return true;
+    }
+    let flag = match when.strip_prefix('-') {
+        Some(flag) if flag.len() == 1 && flag != "-" => flag,
+        _ => return false,
+    };
+    match arg.strip_prefix('-') {
+        Some(bundle) if !bundle.starts_with('-') => {
+            bundle.chars().all(|c| c.is_ascii_alphabetic()) && bundle.contains(flag)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_args() {
+        let rules: Vec<ArgRule> = toml::from_str::<toml::Table>(
+            r#"rules = [
+                { when = "-l", add = "--git" },
+                { when = "--long", add = "--git" },
+                { when = "--tree", add = "--level 2" },
+            ]"#,
+        )
+        .unwrap()["rules"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let extra = |args: &[&str]| extra_args(&rules, args);
+        assert_eq!(extra(&["-l"]), ["--git"]);
+        assert_eq!(extra(&["-la", "--long"]), ["--git"]);
+        assert_eq!(extra(&["-l", "--git"]), Vec::<String>::new());
+        assert_eq!(extra(&["--tree"]), ["--level", "2"]);
+        assert_eq!(extra(&["-n5", "src"]), Vec::<String>::new());
+        assert_eq!(extra(&["--", "-l"]), Vec::<String>::new());
+    }
+}
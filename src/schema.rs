@@ -0,0 +1,793 @@
+use crate::error::ExitCode;
+use crate::json::Value;
+
+/// The kind of value a config key holds.
+#[derive(Clone, Copy)]
+enum Kind {
+    String,
+    Path,
+    Boolean,
+    Integer { minimum: Option<i64> },
+    IntegerList,
+    StringList,
+    Duration,
+    Size,
+    Output,
+    OneOf(&'static [&'static str]),
+    ExitCodes,
+    ExitMap,
+    Limits,
+    Hooks,
+    Umask,
+    SingleInstance,
+    Env,
+    ArgRules,
+}
+
+/// One documented config key: everything `shdw config schema` prints about
+/// it. `example` is a TOML value that must parse as the real type.
+struct Property {
+    name: &'static str,
+    kind: Kind,
+    description: &'static str,
+    example: &'static str,
+}
+
+const SETTINGS: &[Property] = &[
+    Property {
+        name: "bin_path",
+        kind: Kind::Path,
+        description: "Directory where alias symlinks are created",
+        example: "\"/home/me/.local/bin\"",
+    },
+    Property {
+        name: "always_use_raw",
+        kind: Kind::Boolean,
+        description: "Always run the original command, as if --raw was passed",
+        example: "false",
+    },
+    Property {
+        name: "exit_codes",
+        kind: Kind::ExitCodes,
+        description: "Remap the exit statuses shdw uses (see `shdw exit-codes`)",
+        example: "{ command_failed = 1 }",
+    },
+    Property {
+        name: "builtins",
+        kind: Kind::OneOf(&["allow", "warn", "refuse"]),
+        description: "What `shdw add` does with aliases named after shell builtins (default: warn)",
+        example: "\"refuse\"",
+    },
+    Property {
+        name: "on_missing_config",
+        kind: Kind::OneOf(&["error", "passthrough"]),
+        description:
+            "What a shim without an alias does: fail, or run the command it hides (default: error)",
+        example: "\"passthrough\"",
+    },
+    Property {
+        name: "help_banner",
+        kind: Kind::Boolean,
+        description: "Before a shadowed command's --help, note which replacement answers it",
+        example: "true",
+    },
+    Property {
+        name: "failure_footer",
+        kind: Kind::Boolean,
+        description: "After a shadowed command fails, note on stderr which replacement ran",
+        example: "true",
+    },
+    Property {
+        name: "backend",
+        kind: Kind::OneOf(&["symlink", "shell"]),
+        description:
+            "Shadow commands with symlinks (default) or with shell functions defined by `shdw init`",
+        example: "\"shell\"",
+    },
+    Property {
+        name: "protected",
+        kind: Kind::StringList,
+        description: "Commands shdw only shadows with `shdw add --i-know-what-im-doing` \
+                      (default: sudo, sh, bash, env, systemctl, ssh)",
+        example: "[\"sudo\", \"sh\", \"bash\", \"env\", \"systemctl\", \"ssh\", \"doas\"]",
+    },
+    Property {
+        name: "stats",
+        kind: Kind::Boolean,
+        description: "Count each alias' runs, failures and time spent, for `shdw stats`",
+        example: "true",
+    },
+    Property {
+        name: "app_paths",
+        kind: Kind::Boolean,
+        description: "On Windows, also register links under the user's App Paths, for Win+R",
+        example: "true",
+    },
+    Property {
+        name: "hooks",
+        kind: Kind::Hooks,
+        description: "Shell commands to run after aliases are added or removed, or links synced",
+        example: "{ on_add = \"git -C ~/dotfiles commit -qam 'shdw: $SHDW_ALIAS'\" }",
+    },
+];
+
+const ALIAS: &[Property] = &[
+    Property {
+        name: "command",
+        kind: Kind::String,
+        description: "Replacement command line; arguments are appended",
+        example: "\"eza --icons\"",
+    },
+    Property {
+        name: "template",
+        kind: Kind::String,
+        description: "Use this template instead of a command; other string keys fill its {placeholders}",
+        example: "\"eza\"",
+    },
+    Property {
+        name: "extends",
+        kind: Kind::String,
+        description: "Inherit every option of this alias, overriding the ones set here (env is merged)",
+        example: "\"ls\"",
+    },
+    Property {
+        name: "args",
+        kind: Kind::String,
+        description: "Arguments appended to the command, e.g. one inherited with extends",
+        example: "\"--all\"",
+    },
+    Property {
+        name: "description",
+        kind: Kind::String,
+        description: "Shown by `shdw list`",
+        example: "\"ls with icons\"",
+    },
+    Property {
+        name: "notes",
+        kind: Kind::String,
+        description: "Longer documentation, printed by `shdw help <name>`",
+        example: "\"\"\"\nIcons need a Nerd Font.\nUse --raw for the original ls.\n\"\"\"",
+    },
+    Property {
+        name: "examples",
+        kind: Kind::StringList,
+        description: "Example invocations, printed by `shdw help <name>`",
+        example: "[\"ls --tree --level 2\"]",
+    },
+    Property {
+        name: "bin_path",
+        kind: Kind::Path,
+        description: "Create this alias's symlink here instead of settings.bin_path",
+        example: "\"/home/me/bin\"",
+    },
+    Property {
+        name: "link",
+        kind: Kind::Boolean,
+        description: "Create a symlink; when false the alias only runs through `shdw run`",
+        example: "false",
+    },
+    Property {
+        name: "notify_after",
+        kind: Kind::Duration,
+        description: "Send a desktop notification when the command runs longer than this",
+        example: "\"30s\"",
+    },
+    Property {
+        name: "retries",
+        kind: Kind::Integer { minimum: Some(0) },
+        description: "Run the command again this many times while it fails",
+        example: "3",
+    },
+    Property {
+        name: "retry_backoff",
+        kind: Kind::Duration,
+        description: "Delay before the first retry, doubled after each one",
+        example: "\"1s\"",
+    },
+    Property {
+        name: "retry_on",
+        kind: Kind::IntegerList,
+        description: "Only retry on these exit codes (default: any failure)",
+        example: "[75]",
+    },
+    Property {
+        name: "exit_map",
+        kind: Kind::ExitMap,
+        description: "Exit codes of the replacement to return as others, for scripts written for the original",
+        example: "{ 2 = 1 }",
+    },
+    Property {
+        name: "detach",
+        kind: Kind::Boolean,
+        description: "Start the command in the background, logging its output",
+        example: "true",
+    },
+    Property {
+        name: "single_instance",
+        kind: Kind::SingleInstance,
+        description: "Refuse to run while another invocation does (true), or wait for it (\"wait\")",
+        example: "\"wait\"",
+    },
+    Property {
+        name: "nice",
+        kind: Kind::Integer { minimum: None },
+        description: "Scheduling priority (Unix only)",
+        example: "10",
+    },
+    Property {
+        name: "limits",
+        kind: Kind::Limits,
+        description: "Resource limits (Unix only)",
+        example: "{ max_memory = \"2G\", open_files = 1024, cpu_time = \"10m\" }",
+    },
+    Property {
+        name: "umask",
+        kind: Kind::Umask,
+        description: "File mode creation mask for the command, in octal (Unix only)",
+        example: "\"027\"",
+    },
+    Property {
+        name: "inherit_stdin",
+        kind: Kind::Boolean,
+        description: "Give the command shdw's standard input (true) or none (false), for replacements that would wait on it",
+        example: "false",
+    },
+    Property {
+        name: "help_banner",
+        kind: Kind::Boolean,
+        description: "settings.help_banner for this alias only",
+        example: "true",
+    },
+    Property {
+        name: "failure_footer",
+        kind: Kind::Boolean,
+        description: "settings.failure_footer for this alias only",
+        example: "true",
+    },
+    Property {
+        name: "stats",
+        kind: Kind::Boolean,
+        description: "settings.stats for this alias only",
+        example: "false",
+    },
+    Property {
+        name: "stdout",
+        kind: Kind::Output,
+        description: "Where standard output goes: \"inherit\", \"null\" or a file",
+        example: "\"~/logs/build.log\"",
+    },
+    Property {
+        name: "stderr",
+        kind: Kind::Output,
+        description: "Where standard error goes: \"inherit\", \"null\" or a file",
+        example: "{ path = \"~/logs/build.err\", append = false }",
+    },
+    Property {
+        name: "env",
+        kind: Kind::Env,
+        description: "Environment variables for the replacement; { from = \"keyring:<service>\" }, \"command:<command>\" or \"file:<path>\" fetch secrets when it runs",
+        example: "{ PAGER = \"less -R\", GITHUB_TOKEN = { from = \"command:pass show github\" } }",
+    },
+    Property {
+        name: "min_args",
+        kind: Kind::Integer { minimum: Some(0) },
+        description: "Refuse to run with fewer arguments than this",
+        example: "1",
+    },
+    Property {
+        name: "max_args",
+        kind: Kind::Integer { minimum: Some(0) },
+        description: "Refuse to run with more arguments than this",
+        example: "2",
+    },
+    Property {
+        name: "args_pattern",
+        kind: Kind::String,
+        description: "Refuse to run unless the arguments, joined with spaces, match this regular expression",
+        example: "\"^(get|describe) \"",
+    },
+    Property {
+        name: "arg_rules",
+        kind: Kind::ArgRules,
+        description: "Extra arguments added when the incoming arguments include `when`",
+        example: "[{ when = \"-l\", add = \"--git\" }]",
+    },
+    Property {
+        name: "tee",
+        kind: Kind::String,
+        description: "Also append output to this file; {name}, {date} and {time} are filled in",
+        example: "\"~/logs/{name}-{date}.log\"",
+    },
+];
+
+const LIMITS: &[Property] = &[
+    Property {
+        name: "max_memory",
+        kind: Kind::Size,
+        description: "Maximum address space",
+        example: "\"2G\"",
+    },
+    Property {
+        name: "open_files",
+        kind: Kind::Integer { minimum: Some(0) },
+        description: "Maximum number of open file descriptors",
+        example: "1024",
+    },
+    Property {
+        name: "cpu_time",
+        kind: Kind::Duration,
+        description: "Maximum CPU time",
+        example: "\"10m\"",
+    },
+];
+
+const HOOKS: &[Property] = &[
+    Property {
+        name: "on_add",
+        kind: Kind::String,
+        description: "Run after `shdw add`, `merge`, `import` or `adopt` adds aliases",
+        example: "\"home-manager switch\"",
+    },
+    Property {
+        name: "on_remove",
+        kind: Kind::String,
+        description: "Run after `shdw remove`",
+        example: "\"home-manager switch\"",
+    },
+    Property {
+        name: "on_sync",
+        kind: Kind::String,
+        description: "Run after `shdw bootstrap` or `shdw sync`",
+        example: "\"home-manager switch\"",
+    },
+    Property {
+        name: "on_missing",
+        kind: Kind::String,
+        description: "Run when a shim's replacement isn't installed; a URL is POSTed JSON",
+        example: "\"https://hooks.example.com/shdw\"",
+    },
+];
+
+fn string(pattern: Option<&str>) -> Value {
+    let mut fields = vec![("type", Value::from("string"))];
+    if let Some(pattern) = pattern {
+        fields.push(("pattern", Value::from(pattern)));
+    }
+    Value::object(fields)
+}
+
+fn kind_schema(kind: Kind) -> Value {
+    match kind {
+        Kind::String | Kind::Path => string(None),
+        Kind::Boolean => Value::object([("type", Value::from("boolean"))]),
+        Kind::Integer { minimum } => {
+            let mut fields = vec![("type", Value::from("integer"))];
+            if let Some(minimum) = minimum {
+                fields.push(("minimum", Value::from(minimum)));
+            }
+            Value::object(fields)
+        }
+        Kind::StringList => {
+            Value::object([("type", Value::from("array")), ("items", string(None))])
+        }
+        Kind::IntegerList => Value::object([
+            ("type", Value::from("array")),
+            ("items", Value::object([("type", Value::from("integer"))])),
+        ]),
+        Kind::Duration => string(Some(r"^\s*[0-9]+\s*(ms|s|m|h|d)?\s*$")),
+        Kind::Size => string(Some(r"^\s*[0-9]+\s*([KkMmGgTt]([Ii]?[Bb])?|[Bb])?\s*$")),
+        Kind::SingleInstance => Value::object([(
+            "oneOf",
+            Value::Array(vec![
+                Value::object([("type", Value::from("boolean"))]),
+                Value::object([(
+                    "enum",
+                    Value::Array(vec![Value::from("fail"), Value::from("wait")]),
+                )]),
+            ]),
+        )]),
+        Kind::Umask => string(Some(r"^\s*0?[0-7]{1,3}\s*$")),
+        Kind::Output => Value::object([(
+            "oneOf",
+            Value::Array(vec![
+                string(None),
+                Value::object([
+                    ("type", Value::from("object")),
+                    (
+                        "properties",
+                        Value::object([
+                            ("path", string(None)),
+                            (
+                                "append",
+                                Value::object([
+                                    ("type", Value::from("boolean")),
+                                    ("default", Value::from(true)),
+                                ]),
+                            ),
+                        ]),
+                    ),
+                    ("required", Value::from(vec!["path"])),
+                    ("additionalProperties", Value::from(false)),
+                ]),
+            ]),
+        )]),
+        Kind::OneOf(values) => Value::object([
+            ("type", Value::from("string")),
+            ("enum", Value::from(values.to_vec())),
+        ]),
+        Kind::ExitCodes => Value::object([
+            ("type", Value::from("object")),
+            (
+                "properties",
+                Value::object(ExitCode::ALL.iter().map(|code| {
+                    (
+                        code.name(),
+                        Value::object([
+                            ("type", Value::from("integer")),
+                            ("minimum", Value::from(0i64)),
+                            ("maximum", Value::from(255i64)),
+                            ("description", Value::from(code.description())),
+                        ]),
+                    )
+                })),
+            ),
+            ("additionalProperties", Value::from(false)),
+        ]),
+        Kind::ExitMap => Value::object([
+            ("type", Value::from("object")),
+            ("propertyNames", string(Some(r"^\s*-?[0-9]+\s*$"))),
+            (
+                "additionalProperties",
+                Value::object([
+                    ("type", Value::from("integer")),
+                    ("minimum", Value::from(0i64)),
+                    ("maximum", Value::from(255i64)),
+                ]),
+            ),
+        ]),
+        Kind::Limits => object(LIMITS, &[]),
+        Kind::Hooks => object(HOOKS, &[]),
+        Kind::ArgRules => Value::object([
+            ("type", Value::from("array")),
+            (
+                "items",
+                Value::object([
+                    ("type", Value::from("object")),
+                    (
+                        "properties",
+                        Value::object([("when", string(None)), ("add", string(None))]),
+                    ),
+                    ("required", Value::from(vec!["when", "add"])),
+                    ("additionalProperties", Value::from(false)),
+                ]),
+            ),
+        ]),
+        Kind::Env => Value::object([
+            ("type", Value::from("object")),
+            (
+                "additionalProperties",
+                Value::object([(
+                    "oneOf",
+                    Value::Array(vec![
+                        string(None),
+                        Value::object([
+                            ("type", Value::from("object")),
+                            (
+                                "properties",
+                                Value::object([(
+                                    "from",
+                                    string(Some("^(keyring|command|file):.+$")),
+                                )]),
+                            ),
+                            ("required", Value::from(vec!["from"])),
+                            ("additionalProperties", Value::from(false)),
+                        ]),
+                    ]),
+                )]),
+            ),
+        ]),
+    }
+}
+
+fn object(properties: &[Property], required: &[&str]) -> Value {
+    let mut fields = vec![
+        ("type", Value::from("object")),
+        (
+            "properties",
+            Value::object(properties.iter().map(|property| {
+                let schema = kind_schema(property.kind)
+                    .with("description", Value::from(property.description));
+                (property.name, schema)
+            })),
+        ),
+    ];
+    if !required.is_empty() {
+        fields.push(("required", Value::from(required.to_vec())));
+    }
+    Value::object(fields)
+}
+
+/// Every key an alias table can have.
+pub fn alias_keys() -> Vec<&'static str> {
+    ALIAS.iter().map(|property| property.name).collect()
+}
+
+/// Keys in a parsed `config.toml` that shdw doesn't know, as dotted paths
+/// such as `aliases.ls.colour`. Serde ignores these, so a typo silently
+/// does nothing. String keys on an alias using a template are its
+/// parameters, not typos.
+pub fn unknown_keys(config: &toml::Table) -> Vec<String> {
+    let mut unknown = Vec::new();
+    for (key, value) in config {
+        match (key.as_str(), value) {
+            ("version", _) => {}
+            ("settings", toml::Value::Table(settings)) => {
+                check_table(settings, SETTINGS, "settings", false, &mut unknown)
+            }
+            ("templates", toml::Value::Table(templates)) => {
+                for (name, template) in templates {
+                    let path = format!("templates.{}", name);
+                    if let toml::Value::Table(template) = template {
+                        check_keys(template, &["command"], &path, &mut unknown);
+                    }
+                }
+            }
+            ("groups" | "subscriptions", toml::Value::Table(_)) => {}
+            ("aliases", toml::Value::Table(aliases)) => {
+                for (name, alias) in aliases {
+                    let path = format!("aliases.{}", name);
+                    if let toml::Value::Table(alias) = alias {
+                        let params = alias.contains_key("template");
+                        check_table(alias, ALIAS, &path, params, &mut unknown);
+                    }
+                }
+            }
+            _ => unknown.push(key.clone()),
+        }
+    }
+    unknown
+}
+
+/// `extra` allows keys missing from `properties`, if their values are strings.
+fn check_table(
+    table: &toml::Table,
+    properties: &[Property],
+    path: &str,
+    extra: bool,
+    unknown: &mut Vec<String>,
+) {
+    for (key, value) in table {
+        let path = format!("{}.{}", path, key);
+        let Some(property) = properties.iter().find(|property| property.name == key) else {
+            if !(extra && value.is_str()) {
+                unknown.push(path);
+            }
+            continue;
+        };
+        if let (Kind::ArgRules, toml::Value::Array(rules)) = (property.kind, value) {
+            for (i, rule) in rules.iter().enumerate() {
+                if let toml::Value::Table(rule) = rule {
+                    check_keys(rule, &["when", "add"], &format!("{}.{}", path, i), unknown);
+                }
+            }
+        }
+        let toml::Value::Table(value) = value else {
+            continue;
+        };
+        match property.kind {
+            Kind::Limits => check_table(value, LIMITS, &path, false, unknown),
+            Kind::Hooks => check_table(value, HOOKS, &path, false, unknown),
+            Kind::Output => check_keys(value, &["path", "append"], &path, unknown),
+            Kind::ExitCodes => {
+                let names: Vec<_> = ExitCode::ALL.iter().map(|code| code.name()).collect();
+                check_keys(value, &names, &path, unknown);
+            }
+            Kind::Env => {
+                for (name, value) in value {
+                    if let toml::Value::Table(value) = value {
+                        check_keys(value, &["from"], &format!("{}.{}", path, name), unknown);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_keys(table: &toml::Table, known: &[&str], path: &str, unknown: &mut Vec<String>) {
+    for key in table.keys() {
+        if !known.contains(&key.as_str()) {
+            unknown.push(format!("{}.{}", path, key));
+        }
+    }
+}
+
+/// A JSON Schema describing `config.toml`, for editors (e.g. taplo) to
+/// validate against.
+pub fn json_schema() -> Value {
+    Value::object([
+        (
+            "$schema",
+            Value::from("https://json-schema.org/draft/2020-12/schema"),
+        ),
+        ("title", Value::from("shdw configuration")),
+        ("type", Value::from("object")),
+        (
+            "properties",
+            Value::object([
+                (
+                    "version",
+                    Value::object([
+                        ("type", Value::from("integer")),
+                        ("description", Value::from("Config format version")),
+                    ]),
+                ),
+                ("settings", object(SETTINGS, &[])),
+                (
+                    "templates",
+                    Value::object([
+                        ("type", Value::from("object")),
+                        (
+                            "description",
+                            Value::from("Commands shared by several aliases, by name"),
+                        ),
+                        (
+                            "additionalProperties",
+                            object(
+                                &[Property {
+                                    name: "command",
+                                    kind: Kind::String,
+                                    description:
+                                        "Command with {placeholders} filled in by each alias",
+                                    example: "\"eza {flags} --group-directories-first\"",
+                                }],
+                                &["command"],
+                            ),
+                        ),
+                    ]),
+                ),
+                (
+                    "aliases",
+                    Value::object([
+                        ("type", Value::from("object")),
+                        (
+                            "description",
+                            Value::from("Aliases, keyed by the command they shadow"),
+                        ),
+                        ("additionalProperties", object(ALIAS, &[])),
+                    ]),
+                ),
+                (
+                    "groups",
+                    Value::object([
+                        ("type", Value::from("object")),
+                        (
+                            "description",
+                            Value::from(
+                                "Aliases run one after another by `shdw group run`, by group name",
+                            ),
+                        ),
+                        ("additionalProperties", kind_schema(Kind::StringList)),
+                    ]),
+                ),
+                (
+                    "subscriptions",
+                    Value::object([
+                        ("type", Value::from("object")),
+                        (
+                            "description",
+                            Value::from(
+                                "Alias files others maintain, by name: an https URL or a path, fetched by `shdw subscription update`",
+                            ),
+                        ),
+                        ("additionalProperties", string(None)),
+                    ]),
+                ),
+            ]),
+        ),
+    ])
+}
+
+/// An example `config.toml` using every option, each preceded by a comment
+/// describing it.
+pub fn example() -> String {
+    let mut out = String::from("version = 1\n\n[settings]\n");
+    push_properties(&mut out, SETTINGS);
+    out.push_str("\n[templates.eza]\n");
+    out.push_str("# Command with {placeholders} filled in by each alias using the template\n");
+    out.push_str("command = \"eza {flags} --group-directories-first\"\n");
+    // Options that only make sense on their own go on separate aliases.
+    let only = |names: &'static [&str]| ALIAS.iter().filter(move |p| names.contains(&p.name));
+    out.push_str("\n[aliases.ls]\n");
+    push_properties(
+        &mut out,
+        ALIAS
+            .iter()
+            .filter(|p| !["template", "extends", "args"].contains(&p.name)),
+    );
+    out.push_str("\n[aliases.la]\n");
+    push_properties(&mut out, only(&["extends", "args"]));
+    out.push_str("\n[aliases.ll]\n");
+    push_properties(&mut out, only(&["template"]));
+    out.push_str("flags = \"--long --all\"\n");
+    out.push_str("\n[groups]\n");
+    out.push_str("# Aliases run one after another by `shdw group run listings`\n");
+    out.push_str("listings = [\"ls\", \"la\"]\n");
+    out.push_str("\n[subscriptions]\n");
+    out.push_str(
+        "# Aliases your team keeps, beneath your own, fetched by `shdw subscription update`\n",
+    );
+    out.push_str("team = \"https://example.com/aliases.toml\"\n");
+    out
+}
+
+fn push_properties<'a>(out: &mut String, properties: impl IntoIterator<Item = &'a Property>) {
+    for property in properties {
+        out.push_str(&format!(
+            "# {}\n{} = {}\n",
+            property.description, property.name, property.example
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_unknown_keys() {
+        let config: toml::Table = toml::from_str(
+            r#"
+            version = 1
+            colour = true
+            [settings]
+            bin_path = "~/bin"
+            exit_codes = { command_failed = 1, oops = 2 }
+            [templates.eza]
+            command = "eza {flags}"
+            comand = "typo"
+            [aliases.ls]
+            command = "eza"
+            limits = { max_memory = "1G", max_mem = "1G" }
+            env = { TOKEN = { from = "file:/t", form = "x" } }
+            colour = "always"
+            [aliases.ll]
+            template = "eza"
+            flags = "--long"
+            "#,
+        )
+        .unwrap();
+        let mut unknown = unknown_keys(&config);
+        unknown.sort();
+        assert_eq!(
+            unknown,
+            [
+                "aliases.ls.colour",
+                "aliases.ls.env.TOKEN.form",
+                "aliases.ls.limits.max_mem",
+                "colour",
+                "settings.exit_codes.oops",
+                "templates.eza.comand",
+            ]
+        );
+    }
+
+    // Every documented key must survive a round trip through the real
+    // types, which catches keys the schema knows but serde silently ignores.
+    #[test]
+    fn test_example_matches_config_types() {
+        let config: Config = toml::from_str(&example()).unwrap();
+        let serialized = toml::to_string(&config).unwrap();
+        for property in SETTINGS.iter().chain(ALIAS).chain(LIMITS) {
+            assert!(
+                serialized.contains(&format!("\n{} = ", property.name))
+                    || serialized.contains(&format!(".{}]", property.name))
+                    || serialized.contains(&format!("{} = {{", property.name)),
+                "{} was dropped:\n{}",
+                property.name,
+                serialized
+            );
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// The value of an environment variable set for an alias: either written
+/// in the config, or fetched from a secret backend each time the alias runs
+/// so it never has to sit in the dotfiles.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnvValue {
+    Plain(String),
+    Secret(Source),
+}
+
+/// Where a secret comes from, written `backend:argument`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    /// The system keyring entry for a service: macOS Keychain via
+    /// `security`, or the Secret Service via `secret-tool` elsewhere.
+    Keyring(String),
+    /// Standard output of a shell command, e.g. `pass show github/token`.
+    Command(String),
+    /// Contents of a file, e.g. one decrypted by sops or agenix.
+    File(String),
+}
+
+impl EnvValue {
+    pub fn resolve(&self) -> Result<String, String> {
+        match self {
+            EnvValue::Plain(value) => Ok(value.clone()),
+            EnvValue::Secret(source) => source.resolve(),
+        }
+    }
+}
+
+impl Source {
+    /// Fetch the secret. Trailing newlines, which every backend adds, are
+    /// stripped.
+    pub fn resolve(&self) -> Result<String, String> {
+        let value = match self {
+            Source::Keyring(service) => keyring(service)?,
+            Source::Command(command) => run(shell(command), self)?,
+            Source::File(path) => {
+                let path = crate::paths::expand_home(path.as_ref());
+                fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+        };
+        Ok(value.trim_end_matches(['\n', '\r']).to_string())
+    }
+}
+
+fn keyring(service: &str) -> Result<String, String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["find-generic-password", "-s", service, "-w"]);
+        command
+    } else if cfg!(windows) {
+        return Err("keyring secrets are not supported on Windows; use command: instead".into());
+    } else {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", service]);
+        command
+    };
+    command.stdin(Stdio::null());
+    run(command, &Source::Keyring(service.to_string()))
+}
+
+/// `script` run by the platform shell, `sh -c` or `cmd /C`.
+pub fn shell(script: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", script]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    };
+    command.stdin(Stdio::null());
+    command
+}
+
+fn run(mut command: Command, source: &Source) -> Result<String, String> {
+    let output = command
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("{}: {}", source, e))?;
+    if !output.status.success() {
+        return Err(format!("{} exited with {}", source, output.status));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} is not valid UTF-8", source))
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("keyring", service)) if !service.is_empty() => {
+                Ok(Source::Keyring(service.to_string()))
+            }
+            Some(("command", command)) if !command.trim().is_empty() => {
+                Ok(Source::Command(command.trim().to_string()))
+            }
+            Some(("file", path)) if !path.is_empty() => Ok(Source::File(path.to_string())),
+            _ => Err(format!(
+                "Invalid secret source '{}', expected keyring:<service>, command:<command> or file:<path>",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Keyring(service) => write!(f, "keyring:{}", service),
+            Source::Command(command) => write!(f, "command:{}", command),
+            Source::File(path) => write!(f, "file:{}", path),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EnvValueDef {
+    Plain(String),
+    Secret { from: String },
+}
+
+impl Serialize for EnvValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            EnvValue::Plain(value) => EnvValueDef::Plain(value.clone()),
+            EnvValue::Secret(source) => EnvValueDef::Secret {
+                from: source.to_string(),
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EnvValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match EnvValueDef::deserialize(deserializer)? {
+            EnvValueDef::Plain(value) => Ok(EnvValue::Plain(value)),
+            EnvValueDef::Secret { from } => from
+                .parse()
+                .map(EnvValue::Secret)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_parse() {
+        assert_eq!(
+            "keyring:github".parse(),
+            Ok(Source::Keyring("github".to_string()))
+        );
+        assert_eq!(
+            "command: pass show x".parse(),
+            Ok(Source::Command("pass show x".to_string()))
+        );
+        assert!("vault:x".parse::<Source>().is_err());
+        assert!("keyring:".parse::<Source>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_source_strips_newline() {
+        let source = Source::Command("printf 'hunter2\\n'".to_string());
+        assert_eq!(source.resolve(), Ok("hunter2".to_string()));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::quote;
+use clap::ValueEnum;
+use std::env;
+use std::path::Path;
+
+/// The user's interactive shell, as far as shadow needs to know it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "nu")]
+    Nushell,
+    #[value(alias = "powershell")]
+    Pwsh,
+    #[value(alias = "sh")]
+    Posix,
+}
+
+impl Shell {
+    /// Guess the shell from `$SHELL`, falling back to PowerShell on Windows
+    /// and plain POSIX sh elsewhere.
+    pub fn detect() -> Self {
+        let name = env::var("SHELL")
+            .ok()
+            .and_then(|shell| {
+                Path::new(&shell)
+                    .file_stem()
+                    .and_then(|name| name.to_str())
+                    .map(str::to_string)
+            })
+            .unwrap_or_default();
+
+        match name.as_str() {
+            "bash" => Shell::Bash,
+            "zsh" => Shell::Zsh,
+            "fish" => Shell::Fish,
+            "nu" => Shell::Nushell,
+            "pwsh" | "powershell" => Shell::Pwsh,
+            _ if cfg!(windows) => Shell::Pwsh,
+            _ => Shell::Posix,
+        }
+    }
+
+    /// The name the shell goes by on the command line, e.g. `bash`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Nushell => "nu",
+            Shell::Pwsh => "pwsh",
+            Shell::Posix => "sh",
+        }
+    }
+
+    /// The startup file where PATH changes usually go.
+    pub fn rc_file(&self) -> &'static str {
+        match self {
+            Shell::Bash => "~/.bashrc",
+            Shell::Zsh => "~/.zshrc",
+            Shell::Fish => "~/.config/fish/config.fish",
+            Shell::Nushell => "~/.config/nushell/env.nu",
+            Shell::Pwsh => "$PROFILE",
+            Shell::Posix => "~/.profile",
+        }
+    }
+
+    /// A line that puts `dir` at the front of PATH.
+    pub fn path_line(&self, dir: &Path) -> String {
+        let dir = dir.display().to_string();
+        match self {
+            Shell::Bash | Shell::Zsh | Shell::Posix => {
+                format!("export PATH={}:\"$PATH\"", quote::posix(&dir))
+            }
+            Shell::Fish => format!("fish_add_path --move {}", quote::fish(&dir)),
+            Shell::Nushell => format!("$env.PATH = ($env.PATH | prepend {})", quote::nushell(&dir)),
+            Shell::Pwsh => format!(
+                "$env:PATH = {} + [IO.Path]::PathSeparator + $env:PATH",
+                quote::pwsh(&dir)
+            ),
+        }
+    }
+
+    /// The lines the README has users add to the startup file, for
+    /// `shdw uninstall` to list.
+    pub fn startup_lines(&self) -> &'static [&'static str] {
+        match self {
+            Shell::Bash => &[
+                "eval \"$(shdw init bash)\"",
+                "source <(shdw completions bash)",
+            ],
+            Shell::Zsh => &[
+                "eval \"$(shdw init zsh)\"",
+                "source <(shdw completions zsh)",
+            ],
+            Shell::Fish => &["shdw init fish | source", "shdw completions fish | source"],
+            Shell::Nushell => &["source ~/.config/nushell/shdw.nu"],
+            Shell::Pwsh => &["shdw init pwsh | Invoke-Expression"],
+            Shell::Posix => &["eval \"$(shdw init sh)\""],
+        }
+    }
+}
+
+/// Builtins of the POSIX shell language, which every shell but nushell and
+/// PowerShell also has.
+const POSIX_BUILTINS: &[&str] = &[
+    ".", ":", "[", "alias", "bg", "break", "cd", "command", "continue", "echo", "eval", "exec",
+    "exit", "export", "false", "fc", "fg", "getopts", "hash", "jobs", "kill", "printf", "pwd",
+    "read", "readonly", "return", "set", "shift", "test", "times", "trap", "true", "type",
+    "ulimit", "umask", "unalias", "unset", "wait",
+];
+
+const BASH_BUILTINS: &[&str] = &[
+    "bind",
+    "builtin",
+    "caller",
+    "compgen",
+    "complete",
+    "compopt",
+    "declare",
+    "dirs",
+    "disown",
+    "enable",
+    "help",
+    "history",
+    "let",
+    "local",
+    "logout",
+    "mapfile",
+    "popd",
+    "pushd",
+    "readarray",
+    "shopt",
+    "source",
+    "suspend",
+    "typeset",
+];
+
+const ZSH_BUILTINS: &[&str] = &[
+    "autoload",
+    "bindkey",
+    "builtin",
+    "chdir",
+    "declare",
+    "dirs",
+    "disown",
+    "emulate",
+    "enable",
+    "functions",
+    "history",
+    "integer",
+    "let",
+    "limit",
+    "local",
+    "logout",
+    "noglob",
+    "popd",
+    "print",
+    "pushd",
+    "pushln",
+    "rehash",
+    "sched",
+    "setopt",
+    "source",
+    "suspend",
+    "typeset",
+    "unfunction",
+    "unhash",
+    "unlimit",
+    "unsetopt",
+    "vared",
+    "whence",
+    "where",
+    "which",
+    "zle",
+    "zmodload",
+    "zstyle",
+];
+
+const FISH_BUILTINS: &[&str] = &[
+    "abbr",
+    "and",
+    "argparse",
+    "begin",
+    "bg",
+    "bind",
+    "block",
+    "break",
+    "builtin",
+    "case",
+    "cd",
+    "command",
+    "commandline",
+    "complete",
+    "contains",
+    "continue",
+    "count",
+    "disown",
+    "echo",
+    "else",
+    "emit",
+    "end",
+    "eval",
+    "exec",
+    "exit",
+    "false",
+    "fg",
+    "for",
+    "function",
+    "functions",
+    "history",
+    "if",
+    "jobs",
+    "math",
+    "not",
+    "or",
+    "printf",
+    "pwd",
+    "random",
+    "read",
+    "realpath",
+    "return",
+    "set",
+    "set_color",
+    "source",
+    "status",
+    "string",
+    "test",
+    "time",
+    "true",
+    "type",
+    "ulimit",
+    "wait",
+    "while",
+];
+
+impl Shell {
+    /// Whether `name` is one of this shell's builtins, which it runs in
+    /// place of any executable on PATH. Nushell's own commands (`ls`, `cd`,
+    /// ...) win too, but there shadows are run as `^ls`, and PowerShell's
+    /// aliases are removed by `shdw init pwsh`, so neither counts here.
+    pub fn has_builtin(&self, name: &str) -> bool {
+        let extra = match self {
+            Shell::Bash => BASH_BUILTINS,
+            Shell::Zsh => ZSH_BUILTINS,
+            Shell::Fish => return FISH_BUILTINS.contains(&name),
+            Shell::Posix => &[],
+            Shell::Nushell | Shell::Pwsh => return false,
+        };
+        POSIX_BUILTINS.contains(&name) || extra.contains(&name)
+    }
+
+    /// A function definition making `name` run `command` in this shell,
+    /// which unlike a symlink takes precedence over a builtin.
+    pub fn function(&self, name: &str, command: &str) -> String {
+        match self {
+            Shell::Fish => format!("function {}; {} $argv; end", name, command),
+            Shell::Nushell => format!(
+                "def --wrapped {} [...args] {{ ^{} ...$args }}",
+                name, command
+            ),
+            Shell::Pwsh => format!("function {} {{ {} @args }}", name, command),
+            Shell::Bash | Shell::Zsh | Shell::Posix => {
+                format!("{}() {{ {} \"$@\"; }}", name, command)
+            }
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Process supervision for the replacement command.
+//!
+//! The shim and the replacement share a process group, so the terminal
+//! delivers job-control signals (Ctrl-C, Ctrl-Z, window changes) to both and
+//! the replacement stays in the foreground. The shim only has to behave like a
+//! well-mannered parent: ignore keyboard interrupts while the child decides
+//! what to do with them, forward signals aimed at the shim alone, mirror stops
+//! so the shell sees the job as suspended, and die the same way the child did.
+//!
+//! On Windows the console broadcasts Ctrl-C to every attached process, so the
+//! shim simply ignores it; the child is placed in a job object that is killed
+//! if the shim goes away abnormally (console closed, task killed), so nothing
+//! is left orphaned in the background.
+
+use std::io;
+use std::process::{Child, ExitStatus};
+
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+
+#[cfg(unix)]
+static CHILD: AtomicI32 = AtomicI32::new(0);
+
+#[cfg(unix)]
+extern "C" fn forward(signal: libc::c_int) {
+    let pid = CHILD.load(Ordering::SeqCst);
+    if pid > 0 {
+        // SAFETY: kill is async-signal-safe.
+        unsafe {
+            libc::kill(pid, signal);
+        }
+    }
+}
+
+/// Wait for `child` to exit while keeping job control intact.
+#[cfg(unix)]
+pub fn wait(child: &mut Child) -> io::Result<ExitStatus> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let pid = child.id() as libc::pid_t;
+    CHILD.store(pid, Ordering::SeqCst);
+    let handler = forward as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: installing dispositions has no memory-safety preconditions.
+    let previous = unsafe {
+        [
+            (libc::SIGINT, libc::signal(libc::SIGINT, libc::SIG_IGN)),
+            (libc::SIGQUIT, libc::signal(libc::SIGQUIT, libc::SIG_IGN)),
+            (libc::SIGTERM, libc::signal(libc::SIGTERM, handler)),
+            (libc::SIGHUP, libc::signal(libc::SIGHUP, handler)),
+        ]
+    };
+
+    let result = loop {
+        let mut raw = 0;
+        // SAFETY: `raw` outlives the call.
+        if unsafe { libc::waitpid(pid, &mut raw, libc::WUNTRACED) } == -1 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            break Err(err);
+        }
+
+        if libc::WIFSTOPPED(raw) {
+            if !still_stopped(pid) {
+                // Stopped together with us (Ctrl-Z) and already resumed by `fg`.
+                continue;
+            }
+            // Only the child was stopped; suspend ourselves so the shell sees
+            // the job as stopped, then carry the continue on to the child.
+            // SAFETY: raise and kill have no memory-safety preconditions.
+            unsafe {
+                libc::raise(libc::SIGTSTP);
+                libc::kill(pid, libc::SIGCONT);
+            }
+            continue;
+        }
+
+        break Ok(ExitStatus::from_raw(raw));
+    };
+
+    CHILD.store(0, Ordering::SeqCst);
+    for (signal, disposition) in previous {
+        // SAFETY: restores the dispositions captured above.
+        unsafe {
+            libc::signal(signal, disposition);
+        }
+    }
+
+    result
+}
+
+/// Whether a child reported as stopped has not been continued since.
+#[cfg(unix)]
+fn still_stopped(pid: libc::pid_t) -> bool {
+    let mut raw = 0;
+    // SAFETY: `raw` outlives the call.
+    let reaped = unsafe { libc::waitpid(pid, &mut raw, libc::WNOHANG | libc::WCONTINUED) };
+    !(reaped == pid && libc::WIFCONTINUED(raw))
+}
+
+/// Wait for `child` to exit, tying its lifetime to ours until it does.
+#[cfg(windows)]
+pub fn wait(child: &mut Child) -> io::Result<ExitStatus> {
+    use windows::*;
+
+    let job = Job::new().and_then(|job| job.assign(child).map(|_| job));
+    // SAFETY: `ignore_interrupts` is a valid handler for the whole program.
+    unsafe { SetConsoleCtrlHandler(Some(ignore_interrupts), TRUE) };
+
+    let status = child.wait();
+
+    // SAFETY: removes the handler registered above.
+    unsafe { SetConsoleCtrlHandler(Some(ignore_interrupts), FALSE) };
+    if let Ok(job) = job {
+        // The child exited on its own; let anything it launched (e.g. a GUI
+        // editor behind a launcher) outlive the shim.
+        job.release();
+    }
+
+    status
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn wait(child: &mut Child) -> io::Result<ExitStatus> {
+    child.wait()
+}
+
+/// If the child was killed by an interrupt-style signal, die from the same
+/// signal so the calling shell or script reacts exactly as it would without
+/// the shim in between (e.g. a `for` loop stops on Ctrl-C).
+#[cfg(unix)]
+pub fn propagate(status: &ExitStatus) {
+    use std::os::unix::process::ExitStatusExt;
+
+    let Some(signal) = status.signal() else {
+        return;
+    };
+    if [
+        libc::SIGINT,
+        libc::SIGTERM,
+        libc::SIGHUP,
+        libc::SIGPIPE,
+        libc::SIGKILL,
+    ]
+    .contains(&signal)
+    {
+        // SAFETY: resetting a disposition and raising have no preconditions.
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn propagate(_status: &ExitStatus) {}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+
+    type Handle = *mut c_void;
+    type Bool = i32;
+
+    pub const TRUE: Bool = 1;
+    pub const FALSE: Bool = 0;
+    const CTRL_C_EVENT: u32 = 0;
+    const CTRL_BREAK_EVENT: u32 = 1;
+    const JOB_OBJECT_EXTENDED_LIMIT_INFORMATION: i32 = 9;
+    const JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE: u32 = 0x2000;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct BasicLimitInformation {
+        per_process_user_time_limit: i64,
+        per_job_user_time_limit: i64,
+        limit_flags: u32,
+        minimum_working_set_size: usize,
+        maximum_working_set_size: usize,
+        active_process_limit: u32,
+        affinity: usize,
+        priority_class: u32,
+        scheduling_class: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct IoCounters {
+        read_operation_count: u64,
+        write_operation_count: u64,
+        other_operation_count: u64,
+        read_transfer_count: u64,
+        write_transfer_count: u64,
+        other_transfer_count: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct ExtendedLimitInformation {
+        basic_limit_information: BasicLimitInformation,
+        io_info: IoCounters,
+        process_memory_limit: usize,
+        job_memory_limit: usize,
+        peak_process_memory_used: usize,
+        peak_job_memory_used: usize,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateJobObjectW(attributes: *mut c_void, name: *const u16) -> Handle;
+        fn SetInformationJobObject(job: Handle, class: i32, info: *mut c_void, len: u32) -> Bool;
+        fn AssignProcessToJobObject(job: Handle, process: Handle) -> Bool;
+        fn CloseHandle(handle: Handle) -> Bool;
+        pub fn SetConsoleCtrlHandler(
+            handler: Option<unsafe extern "system" fn(u32) -> Bool>,
+            add: Bool,
+        ) -> Bool;
+    }
+
+    /// Let the child, which shares our console, decide what Ctrl-C means.
+    /// Close and logoff events fall through to the default handler, which
+    /// terminates the shim and with it the job.
+    pub unsafe extern "system" fn ignore_interrupts(event: u32) -> Bool {
+        match event {
+            CTRL_C_EVENT | CTRL_BREAK_EVENT => TRUE,
+            _ => FALSE,
+        }
+    }
+
+    /// A job object whose processes are killed when its last handle closes.
+    pub struct Job(Handle);
+
+    impl Job {
+        pub fn new() -> io::Result<Self> {
+            // SAFETY: null attributes and name are allowed.
+            let handle = unsafe { CreateJobObjectW(std::ptr::null_mut(), std::ptr::null()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Job(handle);
+            job.set_limit_flags(JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE)?;
+            Ok(job)
+        }
+
+        pub fn assign(&self, child: &Child) -> io::Result<()> {
+            // SAFETY: both handles are valid for the duration of the call.
+            match unsafe { AssignProcessToJobObject(self.0, child.as_raw_handle() as Handle) } {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+
+        /// Drop the kill-on-close limit so closing the job leaves its
+        /// remaining processes running.
+        pub fn release(self) {
+            let _ = self.set_limit_flags(0);
+        }
+
+        fn set_limit_flags(&self, flags: u32) -> io::Result<()> {
+            let mut info = ExtendedLimitInformation::default();
+            info.basic_limit_information.limit_flags = flags;
+            // SAFETY: `info` is a correctly sized JOBOBJECT_EXTENDED_LIMIT_INFORMATION.
+            let ok = unsafe {
+                SetInformationJobObject(
+                    self.0,
+                    JOB_OBJECT_EXTENDED_LIMIT_INFORMATION,
+                    &mut info as *mut _ as *mut c_void,
+                    std::mem::size_of::<ExtendedLimitInformation>() as u32,
+                )
+            };
+            match ok {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: the handle is owned by this Job.
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
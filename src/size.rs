@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A byte count written with an optional binary unit suffix: `512K`, `64M`,
+/// `2G`, `1T`. `KB`/`KiB` style suffixes are accepted too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    const UNITS: [(&'static str, u64); 4] = [
+        ("T", 1 << 40),
+        ("G", 1 << 30),
+        ("M", 1 << 20),
+        ("K", 1 << 10),
+    ];
+
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let value: u64 = number.parse().map_err(|_| format!("Invalid size: {}", s))?;
+
+        let unit = unit.trim().to_ascii_uppercase();
+        let unit = unit
+            .strip_suffix("IB")
+            .or_else(|| unit.strip_suffix('B'))
+            .unwrap_or(&unit);
+        let multiplier = match unit {
+            "" => 1,
+            _ => Self::UNITS
+                .iter()
+                .find(|(suffix, _)| *suffix == unit)
+                .map(|(_, multiplier)| *multiplier)
+                .ok_or_else(|| format!("Invalid size unit in: {}", s))?,
+        };
+
+        value
+            .checked_mul(multiplier)
+            .map(Self)
+            .ok_or_else(|| format!("Size too large: {}", s))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (suffix, multiplier) in Self::UNITS {
+            if self.0 != 0 && self.0.is_multiple_of(multiplier) {
+                return write!(f, "{}{}", self.0 / multiplier, suffix);
+            }
+        }
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_units() {
+        let parse = |s: &str| s.parse::<ByteSize>().unwrap().bytes();
+        assert_eq!(parse("100"), 100);
+        assert_eq!(parse("4K"), 4096);
+        assert_eq!(parse("4kb"), 4096);
+        assert_eq!(parse("64MiB"), 64 << 20);
+        assert_eq!(parse("2G"), 2 << 30);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!("".parse::<ByteSize>().is_err());
+        assert!("G".parse::<ByteSize>().is_err());
+        assert!("5X".parse::<ByteSize>().is_err());
+        assert!("99999999999T".parse::<ByteSize>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for s in ["100", "4K", "64M", "2G", "1T"] {
+            assert_eq!(s.parse::<ByteSize>().unwrap().to_string(), s);
+        }
+    }
+}
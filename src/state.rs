@@ -0,0 +1,437 @@
+use crate::aliases::Alias;
+use crate::config::Config;
+use crate::error::{Result, ShadowError};
+use crate::paths;
+use crate::which;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What shdw has done to the machine, as opposed to what the config asks
+/// for. Lives in `state.toml` under the state directory, so `config.toml`
+/// stays purely declarative and safe to keep in git.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Symlinks shdw created, by alias name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    links: BTreeMap<String, LinkRecord>,
+    /// Where each alias came from and when it last changed, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, AliasRecord>,
+    /// Aliases that couldn't be linked because their bin directory was
+    /// read-only or full, for `shdw doctor` to point out until they are.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pending: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkRecord {
+    pub path: PathBuf,
+    /// What the link pointed at when created: the shdw binary of the time.
+    /// Missing from records written before it was kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<PathBuf>,
+    /// For a hardlink, the file it shares with the shdw binary of the time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+}
+
+impl LinkRecord {
+    /// Whether the link is still the one shdw made: it points at this
+    /// binary, or still at the binary it was made for, even if that has
+    /// since moved or been deleted; a hardlink is still that binary's file.
+    pub fn is_ours(&self) -> bool {
+        if which::is_shadow(&self.path) {
+            return true;
+        }
+        match (&self.target, fs::read_link(&self.path)) {
+            (Some(target), Ok(current)) => current == *target,
+            (_, Ok(_)) => false,
+            (_, Err(_)) => self
+                .file_id
+                .as_ref()
+                .is_some_and(|id| which::file_id(&self.path).as_ref() == Some(id)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AliasRecord {
+    pub source: Source,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub updated: u64,
+    /// Of the alias' config table, to notice it being edited by hand.
+    fingerprint: String,
+}
+
+/// How an alias got into the config.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// `shdw add`
+    Cli,
+    /// `shdw import`
+    Import,
+    /// `shdw adopt`
+    Adopt,
+    /// `shdw suggest`
+    Suggest,
+    /// Written into config.toml by hand (or by another machine).
+    Config,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Cli => write!(f, "shdw add"),
+            Source::Import => write!(f, "shdw import"),
+            Source::Adopt => write!(f, "shdw adopt"),
+            Source::Suggest => write!(f, "shdw suggest"),
+            Source::Config => write!(f, "config.toml"),
+        }
+    }
+}
+
+impl State {
+    /// Load the state file. A missing or unreadable one is treated as empty:
+    /// state is a record of side effects, never required to run an alias.
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(dir())?;
+        let contents =
+            toml::to_string_pretty(self).map_err(|e| ShadowError::ConfigError(e.to_string()))?;
+        fs::write(Self::path(), contents)?;
+        Ok(())
+    }
+
+    pub fn links(&self) -> &BTreeMap<String, LinkRecord> {
+        &self.links
+    }
+
+    pub fn record_link(&mut self, name: &str, path: PathBuf) {
+        let created = now();
+        let target = fs::read_link(&path).ok();
+        let file_id = match target {
+            Some(_) => None,
+            None => which::file_id(&path),
+        };
+        let record = LinkRecord {
+            path,
+            target,
+            file_id,
+            created,
+        };
+        self.links.insert(name.to_string(), record);
+        self.pending.remove(name);
+    }
+
+    /// Note that alias `name` is waiting for a bin directory shdw can write.
+    pub fn record_pending(&mut self, name: &str) {
+        self.pending.insert(name.to_string());
+    }
+
+    pub fn is_pending(&self, name: &str) -> bool {
+        self.pending.contains(name)
+    }
+
+    /// Whether what's at `path` is the link shdw made there for alias `name`,
+    /// and so can be replaced without moving it aside.
+    pub fn owns(&self, name: &str, path: &Path) -> bool {
+        self.links
+            .get(name)
+            .is_some_and(|record| record.path == path && record.is_ours())
+    }
+
+    pub fn forget_link(&mut self, name: &str) {
+        self.links.remove(name);
+        self.pending.remove(name);
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&AliasRecord> {
+        self.aliases.get(name)
+    }
+
+    /// Note that shdw itself just created alias `name`.
+    pub fn record_alias(&mut self, alias: &Alias, source: Source) {
+        let now = now();
+        let record = AliasRecord {
+            source,
+            created: now,
+            updated: now,
+            fingerprint: fingerprint(alias),
+        };
+        self.aliases.insert(alias.name().to_string(), record);
+    }
+
+    /// Bring alias records up to date with `config`: aliases seen for the
+    /// first time were written by hand, changed ones were edited, and
+    /// records of aliases no longer configured are dropped. Returns whether
+    /// anything changed.
+    pub fn observe(&mut self, config: &Config) -> bool {
+        let before = self.aliases.len();
+        self.aliases
+            .retain(|name, _| config.aliases().contains(name));
+        let mut changed = self.aliases.len() != before;
+        let before = self.pending.len();
+        self.pending.retain(|name| config.aliases().contains(name));
+        changed |= self.pending.len() != before;
+
+        let now = now();
+        for (name, alias) in config.aliases().iter() {
+            let fingerprint = fingerprint(alias);
+            match self.aliases.get_mut(name) {
+                Some(record) if record.fingerprint == fingerprint => continue,
+                Some(record) => {
+                    record.updated = now;
+                    record.fingerprint = fingerprint;
+                }
+                None => {
+                    let record = AliasRecord {
+                        source: Source::Config,
+                        created: now,
+                        updated: now,
+                        fingerprint,
+                    };
+                    self.aliases.insert(name.clone(), record);
+                }
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    fn path() -> PathBuf {
+        file("state.toml")
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// FNV-1a of the alias as config.toml holds it; stable across runs and Rust
+/// versions, unlike `DefaultHasher`.
+fn fingerprint(alias: &Alias) -> String {
+    let table = match alias.written() {
+        Some(table) => Some(table.clone()),
+        None => toml::Table::try_from(alias).ok(),
+    };
+    let text = table.map(|table| table.to_string()).unwrap_or_default();
+    format!("{:016x}", fnv1a(text.as_bytes()))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The state directory (see [`paths::state_dir`]).
+pub fn dir() -> PathBuf {
+    paths::state_dir()
+}
+
+/// A file of its own in the state directory, for state that doesn't belong
+/// in `state.toml` (logs, caches, journals).
+pub fn file(name: &str) -> PathBuf {
+    dir().join(name)
+}
+
+/// Note that alias `name` just ran. This is on every shim's path, so rather
+/// than rewriting `state.toml` it only bumps the modification time of a
+/// file of its own.
+pub fn record_use(name: &str) {
+    let path = file("usage").join(name);
+    let open = || fs::OpenOptions::new().create(true).append(true).open(&path);
+    let file = match open() {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let _ = fs::create_dir_all(file("usage"));
+            open()
+        }
+        file => file,
+    };
+    if let Ok(file) = file {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// When alias `name` last ran, in seconds since the Unix epoch, if it has
+/// since usage started being recorded.
+pub fn last_used(name: &str) -> Option<u64> {
+    let modified = fs::metadata(file("usage").join(name))
+        .ok()?
+        .modified()
+        .ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+/// Where [`divert`] keeps what it moved away from `path`: a directory named
+/// after a hash of the path, holding the moved file as `file` and the path
+/// it came from as `path`. Kept out of `state.toml` so that commands holding
+/// a loaded `State` can't save over a diversion made meanwhile.
+fn diversion(path: &Path) -> PathBuf {
+    let hash = fnv1a(path.as_os_str().as_encoded_bytes());
+    file("diverted").join(format!("{:016x}", hash))
+}
+
+/// Move the real file at `path` out of the way of a symlink, rather than
+/// deleting it: a user's own script or binary is never shdw's to destroy.
+/// Returns where it went.
+pub fn divert(path: &Path) -> Result<PathBuf> {
+    let dir = diversion(path);
+    fs::create_dir_all(&dir)?;
+    let backup = dir.join("file");
+    move_file(path, &backup)?;
+    fs::write(dir.join("path"), path.as_os_str().as_encoded_bytes())?;
+    Ok(backup)
+}
+
+/// Put back the file [`divert`] moved away from `path`, once nothing else
+/// is there. Returns where it had been kept, if anything was put back.
+pub fn restore(path: &Path) -> Result<Option<PathBuf>> {
+    let dir = diversion(path);
+    let backup = dir.join("file");
+    if backup.symlink_metadata().is_err() || path.symlink_metadata().is_ok() {
+        return Ok(None);
+    }
+    move_file(&backup, path)?;
+    let _ = fs::remove_dir_all(&dir);
+    Ok(Some(backup))
+}
+
+/// Rename, or copy and delete when `to` is on another filesystem.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Directory holding output of detached aliases.
+pub fn log_dir() -> PathBuf {
+    file("logs")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_tracks_hand_edits() {
+        let config = |command: &str| -> Config {
+            toml::from_str(&format!("[aliases.ls]\ncommand = \"{}\"\n", command)).unwrap()
+        };
+        let mut state = State::default();
+        assert!(state.observe(&config("eza")));
+        let fingerprint = state.alias("ls").unwrap().fingerprint.clone();
+        assert_eq!(state.alias("ls").unwrap().source, Source::Config);
+        assert!(!state.observe(&config("eza")));
+
+        assert!(state.observe(&config("lsd")));
+        assert_ne!(state.alias("ls").unwrap().fingerprint, fingerprint);
+
+        assert!(state.observe(&toml::from_str("").unwrap()));
+        assert!(state.alias("ls").is_none());
+    }
+
+    #[test]
+    fn test_pending_until_linked_or_removed() {
+        let mut state = State::default();
+        state.record_pending("ls");
+        state.record_pending("cat");
+        state.record_link("ls", PathBuf::from("/nonexistent/ls"));
+        assert!(!state.is_pending("ls"));
+        assert!(state.is_pending("cat"));
+        state.observe(&toml::from_str("").unwrap());
+        assert!(!state.is_pending("cat"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_record_survives_moved_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let link = dir.path().join("ls");
+        std::os::unix::fs::symlink("/old/prefix/bin/shdw", &link).unwrap();
+
+        let mut state = State::default();
+        state.record_link("ls", link.clone());
+        let record = state.links()["ls"].clone();
+        assert_eq!(
+            record.target.as_deref(),
+            Some(Path::new("/old/prefix/bin/shdw"))
+        );
+        assert!(record.is_ours());
+
+        fs::remove_file(&link).unwrap();
+        std::os::unix::fs::symlink("/usr/bin/ls", &link).unwrap();
+        assert!(!record.is_ours());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hardlink_record_survives_upgrade() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("shdw");
+        let link = dir.path().join("ls");
+        fs::write(&binary, "old shdw").unwrap();
+        fs::hard_link(&binary, &link).unwrap();
+
+        let mut state = State::default();
+        state.record_link("ls", link.clone());
+        let record = state.links()["ls"].clone();
+        assert!(record.target.is_none());
+        assert!(record.file_id.is_some());
+
+        // Upgrading replaces the binary; the hardlink keeps the old one.
+        fs::remove_file(&binary).unwrap();
+        fs::write(&binary, "new shdw").unwrap();
+        assert!(record.is_ours());
+        assert!(state.owns("ls", &link));
+
+        let script = dir.path().join("script");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::rename(&script, &link).unwrap();
+        assert!(!record.is_ours());
+    }
+
+    #[test]
+    fn test_divert_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = dir.path().join("state");
+        let script = dir.path().join("ls");
+        temp_env::with_var("SHDW_STATE_DIR", Some(&state_dir), || {
+            fs::write(&script, "#!/bin/sh\nexec eza \"$@\"\n").unwrap();
+            let backup = divert(&script).unwrap();
+            assert!(!script.exists());
+            assert!(backup.starts_with(&state_dir));
+
+            // Something else has since taken the path.
+            fs::write(&script, "").unwrap();
+            assert_eq!(restore(&script).unwrap(), None);
+            fs::remove_file(&script).unwrap();
+
+            assert_eq!(restore(&script).unwrap(), Some(backup));
+            assert!(fs::read_to_string(&script).unwrap().contains("eza"));
+            assert_eq!(restore(&script).unwrap(), None);
+        });
+    }
+}
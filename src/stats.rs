@@ -0,0 +1,140 @@
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often an alias ran, how often that failed, and for how long in all,
+/// kept with `settings.stats = true`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Counters {
+    pub invocations: u64,
+    pub failures: u64,
+    pub duration_ms: u64,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Count runs from here on, or stop, as the alias' effective `stats` says.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Count a run of alias `name`, if stats are on. Best effort, like the rest
+/// of the state: a shim works the same if the counters can't be written.
+pub fn record(name: &str, failed: bool, duration: Duration) {
+    if ENABLED.load(Ordering::Relaxed) {
+        let _ = update(name, failed, duration);
+    }
+}
+
+/// Shims of the same alias can finish at once, so the counters are read
+/// and written back under a lock.
+fn update(name: &str, failed: bool, duration: Duration) -> std::io::Result<()> {
+    let dir = state::file("stats");
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(dir.join(format!("{}.toml", name)))?;
+    file.lock()?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let mut counters: Counters = toml::from_str(&contents).unwrap_or_default();
+    counters.invocations += 1;
+    counters.failures += failed as u64;
+    counters.duration_ms += duration.as_millis() as u64;
+    let contents = toml::to_string(&counters).unwrap_or_default();
+    file.set_len(0)?;
+    file.rewind()?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Every alias' counters, by name.
+pub fn all() -> BTreeMap<String, Counters> {
+    let entries = fs::read_dir(state::file("stats")).into_iter().flatten();
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_stem()?.to_str()?.to_string();
+            let counters = toml::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some((name, counters))
+        })
+        .collect()
+}
+
+/// `stats` in the Prometheus text format, for node_exporter's textfile
+/// collector.
+pub fn to_prometheus(stats: &BTreeMap<String, Counters>) -> String {
+    let mut out = String::new();
+    let mut metric = |metric: &str, help: &str, value: fn(&Counters) -> String| {
+        let _ = writeln!(out, "# HELP {} {}", metric, help);
+        let _ = writeln!(out, "# TYPE {} counter", metric);
+        for (name, counters) in stats {
+            let _ = writeln!(
+                out,
+                "{}{{alias=\"{}\"}} {}",
+                metric,
+                label(name),
+                value(counters)
+            );
+        }
+    };
+    metric("shdw_invocations_total", "Runs of the alias", |c| {
+        c.invocations.to_string()
+    });
+    metric(
+        "shdw_failures_total",
+        "Runs of the alias that failed",
+        |c| c.failures.to_string(),
+    );
+    metric(
+        "shdw_duration_seconds_total",
+        "Time spent running the alias",
+        |c| format!("{:.3}", c.duration_ms as f64 / 1000.0),
+    );
+    out
+}
+
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_accumulate() {
+        let dir = tempfile::tempdir().unwrap();
+        temp_env::with_var("SHDW_STATE_DIR", Some(dir.path()), || {
+            update("ls", false, Duration::from_millis(1500)).unwrap();
+            update("ls", true, Duration::from_millis(250)).unwrap();
+            let stats = all();
+            assert_eq!(
+                stats["ls"],
+                Counters {
+                    invocations: 2,
+                    failures: 1,
+                    duration_ms: 1750
+                }
+            );
+            let text = to_prometheus(&stats);
+            assert!(
+                text.contains("shdw_failures_total{alias=\"ls\"} 1\n"),
+                "{}",
+                text
+            );
+            assert!(text.contains("shdw_duration_seconds_total{alias=\"ls\"} 1.750\n"));
+        });
+    }
+}
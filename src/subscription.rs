@@ -0,0 +1,78 @@
+use crate::config::Config;
+use crate::error::{Result, ShadowError};
+use crate::paths;
+use crate::state;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where subscription `name`'s aliases were last fetched to. Only
+/// `shdw subscription update` writes it, so loading the config never waits
+/// on the network.
+pub fn cached(name: &str) -> PathBuf {
+    state::file("subscriptions").join(format!("{}.toml", name))
+}
+
+/// Fetch subscription `name` from `url` and keep it for the config to layer
+/// in, if it parses; returns how many aliases it has. A file that doesn't
+/// parse leaves the copy from the last update as it was.
+pub fn update(name: &str, url: &str) -> Result<usize> {
+    let contents = fetch(url)?;
+    let path = cached(name);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let temporary = dir.join(format!(".{}.{}", name, std::process::id()));
+    fs::write(&temporary, contents)?;
+    let fetched = Config::from_file(&temporary).and_then(|config| {
+        fs::rename(&temporary, &path)?;
+        Ok(config.aliases().len())
+    });
+    let _ = fs::remove_file(&temporary);
+    fetched
+}
+
+/// The contents at `url`: http(s) through curl, anything else as a file
+/// path (with or without `file://`).
+fn fetch(url: &str) -> Result<String> {
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        let path = paths::expand_home(Path::new(url.strip_prefix("file://").unwrap_or(url)));
+        return fs::read_to_string(&path)
+            .map_err(|e| ShadowError::ConfigError(format!("{}: {}", path.display(), e)));
+    }
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--max-time", "30", url])
+        .output()
+        .map_err(|e| ShadowError::ConfigError(format!("Failed to run curl: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(ShadowError::ConfigError(format!(
+            "Failed to fetch {}: {}",
+            url,
+            stderr.trim()
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| ShadowError::ConfigError(format!("{} is not UTF-8", url)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_keeps_last_good_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("team.toml");
+        temp_env::with_var("SHDW_STATE_DIR", Some(dir.path().join("state")), || {
+            fs::write(&source, "[aliases.k]\ncommand = \"kubectl\"\n").unwrap();
+            let url = format!("file://{}", source.display());
+            assert_eq!(update("team", &url).unwrap(), 1);
+
+            fs::write(&source, "[aliases.k\n").unwrap();
+            assert!(update("team", &url).is_err());
+            let kept = Config::from_file(&cached("team")).unwrap();
+            assert!(kept.aliases().contains("k"));
+        });
+    }
+}
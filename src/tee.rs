@@ -0,0 +1,135 @@
+use crate::paths;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Copies a child's piped stdout and stderr both to our own and to a log
+/// file shared by the two streams.
+pub struct Tee {
+    file: Arc<Mutex<File>>,
+}
+
+impl Tee {
+    /// Open the log for `template`, expanding `{name}`, `{date}` (UTC,
+    /// `YYYY-MM-DD`), `{time}` (UTC, `HHMMSS`) and a leading `~`.
+    pub fn open(template: &str, name: &str) -> io::Result<Self> {
+        let path = log_path(template, name, SystemTime::now());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Start copying whichever of the child's streams were piped. Join the
+    /// returned handles after the child exits to flush the tail of the output.
+    pub fn attach(&self, child: &mut Child) -> Vec<JoinHandle<()>> {
+        let mut copiers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            copiers.push(self.copy(stdout, io::stdout));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            copiers.push(self.copy(stderr, io::stderr));
+        }
+        copiers
+    }
+
+    fn copy<R, W>(&self, mut from: R, to: fn() -> W) -> JoinHandle<()>
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        let file = Arc::clone(&self.file);
+        thread::spawn(move || {
+            let mut buf = [0; 8192];
+            loop {
+                let n = match from.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(_) => break,
+                };
+                // A closed terminal shouldn't stop the log, or vice versa.
+                let mut out = to();
+                let _ = out.write_all(&buf[..n]).and_then(|_| out.flush());
+                if let Ok(mut file) = file.lock() {
+                    let _ = file.write_all(&buf[..n]);
+                }
+            }
+        })
+    }
+}
+
+fn log_path(template: &str, name: &str, now: SystemTime) -> PathBuf {
+    let secs = now
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let seconds = secs % 86400;
+    let path = template
+        .replace("{name}", name)
+        .replace("{date}", &utc_date(secs))
+        .replace(
+            "{time}",
+            &format!(
+                "{:02}{:02}{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ),
+        );
+    paths::expand_home(path.as_ref())
+}
+
+/// `YYYY-MM-DD` in UTC for a time in seconds since the Unix epoch.
+pub fn utc_date(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Days since 1970-01-01 to a (year, month, day) in the proleptic Gregorian
+/// calendar (Howard Hinnant's `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20740), (2026, 10, 14));
+    }
+
+    #[test]
+    fn test_log_path_placeholders() {
+        let now = UNIX_EPOCH + Duration::from_secs(20740 * 86400 + 3600 + 2 * 60 + 3);
+        assert_eq!(
+            log_path("/logs/{name}-{date}-{time}.log", "make", now),
+            PathBuf::from("/logs/make-2026-10-14-010203.log")
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A command shared by several aliases, with `{param}` placeholders each
+/// alias fills in:
+///
+/// ```toml
+/// [templates.eza]
+/// command = "eza {flags} --group-directories-first"
+///
+/// [aliases]
+/// ll = { template = "eza", flags = "--long --all" }
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Template {
+    command: String,
+}
+
+impl Template {
+    /// The command with every placeholder replaced by its parameter.
+    /// Placeholders without a parameter, and parameters the template
+    /// doesn't use, are both errors so typos don't go unnoticed.
+    pub fn expand(&self, params: &BTreeMap<String, String>) -> Result<String, String> {
+        let mut out = String::new();
+        let mut used = Vec::new();
+        let mut rest = self.command.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                return Err(format!("unclosed '{{' in \"{}\"", self.command));
+            };
+            let key = &rest[start + 1..start + len];
+            let value = params
+                .get(key)
+                .ok_or_else(|| format!("no value for {{{}}}", key))?;
+            out.push_str(&rest[..start]);
+            out.push_str(value);
+            used.push(key);
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+
+        if let Some(unused) = params.keys().find(|key| !used.contains(&key.as_str())) {
+            return Err(format!("the template has no {{{}}}", unused));
+        }
+        Ok(out.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand() {
+        let template = Template {
+            command: "eza {flags} --group-directories-first".to_string(),
+        };
+        assert_eq!(
+            template.expand(&params(&[("flags", "--long --all")])),
+            Ok("eza --long --all --group-directories-first".to_string())
+        );
+        assert_eq!(
+            template.expand(&params(&[("flags", "")])),
+            Ok("eza --group-directories-first".to_string())
+        );
+        assert!(template.expand(&params(&[])).is_err());
+        assert!(template
+            .expand(&params(&[("flags", "-l"), ("colour", "always")]))
+            .is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A file mode creation mask in octal, as `umask` takes it: `027` keeps new
+/// files from being group-writable or readable by others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Umask(u32);
+
+impl Umask {
+    /// Only applied on Unix.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+impl FromStr for Umask {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s.len() > 4 {
+            return Err(format!("Invalid umask: {}", s));
+        }
+        u32::from_str_radix(s, 8)
+            .ok()
+            .filter(|bits| *bits <= 0o777)
+            .map(Self)
+            .ok_or_else(|| format!("Invalid umask (expected octal such as 027): {}", s))
+    }
+}
+
+impl fmt::Display for Umask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03o}", self.0)
+    }
+}
+
+impl Serialize for Umask {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Umask {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_umask() {
+        assert_eq!("027".parse::<Umask>().unwrap().bits(), 0o027);
+        assert_eq!("0077".parse::<Umask>().unwrap().bits(), 0o077);
+        assert_eq!("22".parse::<Umask>().unwrap().to_string(), "022");
+        assert!("089".parse::<Umask>().is_err());
+        assert!("1777".parse::<Umask>().is_err());
+        assert!("".parse::<Umask>().is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::json::Value;
+use crate::tee;
+
+/// Where this binary came from, as recorded by `build.rs`, for `--version`
+/// and bug reports.
+pub struct Provenance {
+    pub version: &'static str,
+    /// Short hash of the commit built, with `-dirty` for uncommitted changes;
+    /// unknown when built outside a git checkout.
+    pub commit: Option<&'static str>,
+    /// UTC date of the build, `YYYY-MM-DD`.
+    pub built: String,
+    pub target: &'static str,
+    pub features: Vec<&'static str>,
+}
+
+impl Provenance {
+    pub fn current() -> Self {
+        let built = env!("SHDW_BUILD_EPOCH").parse().unwrap_or_default();
+        Provenance {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: option_env!("SHDW_GIT_COMMIT"),
+            built: tee::utc_date(built),
+            target: env!("SHDW_TARGET"),
+            features: env!("SHDW_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+        }
+    }
+
+    /// `shdw 0.1.0 (abc123 2026-01-01)`, the one-line form.
+    pub fn summary(&self) -> String {
+        format!(
+            "shdw {} ({} {})",
+            self.version,
+            self.commit.unwrap_or("unknown commit"),
+            self.built
+        )
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object([
+            ("version", Value::from(self.version)),
+            ("commit", Value::from(self.commit)),
+            ("built", Value::from(self.built.as_str())),
+            ("target", Value::from(self.target)),
+            ("features", Value::from(self.features.clone())),
+        ])
+    }
+
+    pub fn print(&self) {
+        println!("shdw {}", self.version);
+        println!("commit:   {}", self.commit.unwrap_or("unknown"));
+        println!("built:    {}", self.built);
+        println!("target:   {}", self.target);
+        match self.features.is_empty() {
+            true => println!("features: none"),
+            false => println!("features: {}", self.features.join(", ")),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use crate::log;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Something a command noticed that didn't stop it. `shdw` holds these
+/// until the command is done and prints them together, after its output,
+/// so they don't get lost in it; `--deny-warnings` makes any of them fail
+/// the command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// A bin directory that isn't on PATH, so nothing linked there runs.
+    NotOnPath(PathBuf),
+    /// A replacement program that isn't installed, with the aliases that
+    /// run it.
+    MissingReplacement {
+        program: String,
+        aliases: Vec<String>,
+    },
+    Other(String),
+}
+
+impl Warning {
+    pub fn missing_replacement(program: &str, aliases: &[&str]) -> Self {
+        Warning::MissingReplacement {
+            program: program.to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::NotOnPath(dir) => write!(
+                f,
+                "{} is not on PATH, so the aliases linked there don't shadow anything",
+                dir.display()
+            ),
+            Warning::MissingReplacement { program, aliases } => write!(
+                f,
+                "{} is not installed, so {} can't run",
+                program,
+                aliases.join(", ")
+            ),
+            Warning::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for Warning {
+    fn from(message: String) -> Self {
+        Warning::Other(message)
+    }
+}
+
+impl From<&str> for Warning {
+    fn from(message: &str) -> Self {
+        Warning::Other(message.to_string())
+    }
+}
+
+static COLLECTING: AtomicBool = AtomicBool::new(false);
+static HELD: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Hold warnings from here on, until `finish`. Shims never do: they print
+/// each warning straight away, before the replacement's output.
+pub fn collect() {
+    COLLECTING.store(true, Ordering::Relaxed);
+}
+
+pub fn warn(warning: impl Into<Warning>) {
+    let warning = warning.into();
+    if !COLLECTING.load(Ordering::Relaxed) {
+        COUNT.fetch_add(1, Ordering::Relaxed);
+        log::warning(&warning);
+        return;
+    }
+    if hold(&mut HELD.lock().unwrap_or_else(|e| e.into_inner()), warning) {
+        COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Several aliases often run into the same problem; it's said once.
+fn hold(held: &mut Vec<Warning>, warning: Warning) -> bool {
+    let new = !held.contains(&warning);
+    if new {
+        held.push(warning);
+    }
+    new
+}
+
+/// Print the warnings held so far and stop holding them; returns how many
+/// warnings there have been in all, held or not.
+pub fn finish() -> usize {
+    COLLECTING.store(false, Ordering::Relaxed);
+    let held = std::mem::take(&mut *HELD.lock().unwrap_or_else(|e| e.into_inner()));
+    for warning in &held {
+        log::warning(warning);
+    }
+    COUNT.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warnings_are_held_once() {
+        let mut held = Vec::new();
+        assert!(hold(
+            &mut held,
+            Warning::NotOnPath(PathBuf::from("/opt/bin"))
+        ));
+        assert!(!hold(
+            &mut held,
+            Warning::NotOnPath(PathBuf::from("/opt/bin"))
+        ));
+        assert!(hold(&mut held, "could not save state".into()));
+        assert_eq!(held.len(), 2);
+
+        let missing = Warning::missing_replacement("eza", &["ls", "ll"]);
+        assert_eq!(
+            missing.to_string(),
+            "eza is not installed, so ls, ll can't run"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories on `PATH`, in lookup order.
+pub fn path_dirs() -> Vec<PathBuf> {
+    env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+/// Position of `dir` on `PATH`, comparing canonicalized paths where possible.
+pub fn path_position(dir: &Path) -> Option<usize> {
+    let dir = canonical(dir);
+    path_dirs().iter().position(|entry| canonical(entry) == dir)
+}
+
+/// Every executable called `name` on `PATH`, in the order the shell would
+/// try them.
+pub fn find_all(name: &str) -> Vec<PathBuf> {
+    path_dirs()
+        .iter()
+        .flat_map(|dir| candidates(dir, name))
+        .filter(|candidate| is_executable(candidate))
+        .collect()
+}
+
+/// The executable the shell would run for `name`, if any. Paths containing a
+/// separator are checked directly instead of searched for.
+pub fn find(name: &str) -> Option<PathBuf> {
+    if name.contains(std::path::MAIN_SEPARATOR) || name.contains('/') {
+        let path = PathBuf::from(name);
+        return is_executable(&path).then_some(path);
+    }
+    find_all(name).into_iter().next()
+}
+
+/// Like [`find`], but skips anything inside `exclude`, which is how the
+/// original command is found behind a shadow symlink.
+pub fn find_excluding(name: &str, exclude: &Path) -> Option<PathBuf> {
+    let exclude = canonical(exclude);
+    find_all(name)
+        .into_iter()
+        .find(|candidate| candidate.parent().map(canonical).as_ref() != Some(&exclude))
+}
+
+/// Whether `path` resolves to the running shadow binary, by a symlink or
+/// as a hardlink to it.
+pub fn is_shadow(path: &Path) -> bool {
+    let Ok(exe) = env::current_exe() else {
+        return false;
+    };
+    canonical(&exe) == canonical(path) || file_id(&exe).is_some_and(|id| file_id(path) == Some(id))
+}
+
+/// Which file `path` is, following symlinks: hardlinks to one file share it.
+/// Unix only.
+#[cfg(unix)]
+pub fn file_id(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path).ok()?;
+    Some(format!("{}:{}", metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_path: &Path) -> Option<String> {
+    None
+}
+
+/// The version/shim manager that owns `path`, if it looks like one of theirs.
+pub fn shim_manager(path: &Path) -> Option<&'static str> {
+    const MANAGERS: &[(&str, &str)] = &[
+        (".asdf/shims", "asdf"),
+        ("mise/shims", "mise"),
+        ("rtx/shims", "rtx"),
+        (".pyenv/shims", "pyenv"),
+        (".rbenv/shims", "rbenv"),
+        (".nodenv/shims", "nodenv"),
+        (".goenv/shims", "goenv"),
+        (".volta/bin", "volta"),
+        (".proto/shims", "proto"),
+        ("scoop/shims", "scoop"),
+    ];
+
+    let dir = path.parent()?.to_string_lossy().replace('\\', "/");
+    MANAGERS
+        .iter()
+        .find(|(suffix, _)| dir.ends_with(suffix))
+        .map(|(_, manager)| *manager)
+}
+
+fn candidates(dir: &Path, name: &str) -> Vec<PathBuf> {
+    if cfg!(windows) && Path::new(name).extension().is_none() {
+        let exts = env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT;.COM".to_string());
+        exts.split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| dir.join(format!("{}{}", name, ext.to_ascii_lowercase())))
+            .collect()
+    } else {
+        vec![dir.join(name)]
+    }
+}
+
+pub fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    if !metadata.is_file() {
+        return false;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    true
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
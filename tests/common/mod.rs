@@ -0,0 +1,106 @@
+//! An isolated machine for end-to-end tests: a temporary HOME with its own
+//! config and state directories, and a bin directory put first on PATH.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+pub struct Sandbox {
+    home: TempDir,
+}
+
+impl Sandbox {
+    /// A sandbox whose `config.toml` contains `config`.
+    pub fn new(config: &str) -> Self {
+        let home = tempfile::tempdir().unwrap();
+        let sandbox = Sandbox { home };
+        fs::create_dir_all(sandbox.config_dir()).unwrap();
+        fs::create_dir_all(sandbox.bin()).unwrap();
+        fs::write(sandbox.config_dir().join("config.toml"), config).unwrap();
+        sandbox
+    }
+
+    pub fn home(&self) -> &Path {
+        self.home.path()
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.home().join("config")
+    }
+
+    pub fn bin(&self) -> PathBuf {
+        self.home().join("bin")
+    }
+
+    /// Shadow `name` by linking the binary into the bin directory, the way
+    /// `shdw add` does: a symlink on Unix, `name.exe` on Windows.
+    pub fn shim(&self, name: &str) -> PathBuf {
+        let exe = Path::new(env!("CARGO_BIN_EXE_shdw"));
+        #[cfg(unix)]
+        {
+            let link = self.bin().join(name);
+            std::os::unix::fs::symlink(exe, &link).unwrap();
+            link
+        }
+        // Symlinks need extra privileges on Windows; a copy dispatches the
+        // same way, on its file name.
+        #[cfg(windows)]
+        {
+            let link = self.bin().join(format!("{}.exe", name));
+            fs::copy(exe, &link).unwrap();
+            link
+        }
+    }
+
+    /// Put an executable script called `name` in `dir`, for commands that
+    /// shims run or hide.
+    #[cfg(unix)]
+    pub fn script(&self, dir: &Path, name: &str, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    /// `program` run inside the sandbox, with `extra` directories after the
+    /// bin directory on PATH and the rest of the real PATH behind them.
+    pub fn command(&self, program: impl AsRef<Path>, extra: &[&Path]) -> Command {
+        let mut dirs = vec![self.bin()];
+        dirs.extend(extra.iter().map(|dir| dir.to_path_buf()));
+        dirs.extend(env::split_paths(&env::var_os("PATH").unwrap_or_default()));
+        let path: OsString = env::join_paths(dirs).unwrap();
+
+        let mut command = Command::new(program.as_ref());
+        command
+            .env("HOME", self.home())
+            .env("USERPROFILE", self.home())
+            .env("XDG_CONFIG_HOME", self.home().join(".config"))
+            .env("SHDW_CONFIG_DIR", self.config_dir())
+            .env("SHDW_STATE_DIR", self.home().join("state"))
+            .env("PATH", path);
+        command
+    }
+
+    /// `shdw` itself, run inside the sandbox.
+    pub fn shdw(&self) -> Command {
+        self.command(env!("CARGO_BIN_EXE_shdw"), &[])
+    }
+}
+
+/// Standard output as a string, after checking `output` came from a
+/// successful run.
+pub fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "failed with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
@@ -0,0 +1,1079 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+// Default exit codes, see `shdw exit-codes`.
+const COMMAND_NOT_FOUND: i32 = 127;
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+
+    const COMMAND_FAILED: i32 = 128;
+
+    #[test]
+    fn test_shim_runs_replacement_with_arguments() {
+        let sandbox = Sandbox::new("[aliases.greet]\ncommand = \"echo hello\"\n");
+        let shim = sandbox.shim("greet");
+        let output = sandbox.command(&shim, &[]).arg("world").output().unwrap();
+        assert_eq!(stdout(&output), "hello world\n");
+    }
+
+    #[test]
+    fn test_raw_runs_the_original_behind_the_shim() {
+        let sandbox = Sandbox::new("[aliases.tool]\ncommand = \"echo replacement\"\n");
+        let elsewhere = sandbox.home().join("elsewhere");
+        sandbox.script(&elsewhere, "tool", "echo original \"$@\"");
+        let shim = sandbox.shim("tool");
+
+        let output = sandbox
+            .command(&shim, &[&elsewhere])
+            .args(["--raw", "arg"])
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "original arg\n");
+
+        // The same, going through PATH the way a shell would.
+        let output = sandbox
+            .command("tool", &[&elsewhere])
+            .arg("-R")
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "original\n");
+    }
+
+    #[test]
+    fn test_arguments_after_separator_pass_through() {
+        let sandbox = Sandbox::new("[aliases.mytool]\ncommand = \"echo\"\n");
+        let shim = sandbox.shim("mytool");
+        let output = sandbox
+            .command(&shim, &[])
+            .args(["--", "--raw", "file"])
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "-- --raw file\n");
+    }
+
+    #[test]
+    fn test_unwritable_config_dir() {
+        let sandbox = Sandbox::new("");
+        // Not even root can create a directory inside a file.
+        let blocked = sandbox.home().join("file");
+        std::fs::write(&blocked, "").unwrap();
+        let baked = sandbox.home().join("baked.toml");
+        std::fs::write(&baked, "[aliases.tool]\ncommand = \"echo baked\"\n").unwrap();
+        let shim = sandbox.shim("tool");
+
+        let output = sandbox
+            .command(&shim, &[])
+            .env("SHDW_CONFIG_DIR", blocked.join("shdw"))
+            .env("SHDW_STATE_DIR", blocked.join("state"))
+            .env("SHDW_CONFIG", &baked)
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "baked\n");
+
+        let shdw = || {
+            let mut command = sandbox.shdw();
+            command.env("SHDW_CONFIG_DIR", blocked.join("shdw"));
+            command
+        };
+        assert!(shdw().arg("list").status().unwrap().success());
+        let output = shdw().args(["add", "hi", "echo hi"]).output().unwrap();
+        assert_eq!(output.status.code(), Some(2));
+    }
+
+    #[test]
+    fn test_baked_binary_brings_its_config() {
+        let sandbox = Sandbox::new("[aliases.tool]\ncommand = \"echo mine\"\n");
+        let image = sandbox.home().join("image.toml");
+        std::fs::write(&image, "[aliases.tool]\ncommand = \"echo baked\"\n").unwrap();
+        let baked = sandbox.home().join("shdw-baked");
+        let output = sandbox
+            .shdw()
+            .arg("bake")
+            .arg("--config")
+            .arg(&image)
+            .arg("--out")
+            .arg(&baked)
+            .output()
+            .unwrap();
+        stdout(&output);
+
+        // Cached from the user's config first, which mustn't leak in.
+        let shim = sandbox.shim("tool");
+        assert_eq!(
+            stdout(&sandbox.command(&shim, &[]).output().unwrap()),
+            "mine\n"
+        );
+        let link = sandbox.home().join("tool");
+        std::os::unix::fs::symlink(&baked, &link).unwrap();
+        let output = sandbox.command(&link, &[]).output().unwrap();
+        assert_eq!(stdout(&output), "baked\n");
+        assert_eq!(
+            stdout(&sandbox.command(&shim, &[]).output().unwrap()),
+            "mine\n"
+        );
+    }
+
+    #[test]
+    fn test_unusual_argv0() {
+        use std::os::unix::process::CommandExt;
+
+        let sandbox = Sandbox::new("[aliases.tool]\ncommand = \"echo tool\"\n");
+        let shim = sandbox.shim("tool");
+        for arg0 in ["-tool", "./tool"] {
+            let output = sandbox.command(&shim, &[]).arg0(arg0).output().unwrap();
+            assert_eq!(stdout(&output), "tool\n", "as {}", arg0);
+        }
+    }
+
+    #[test]
+    fn test_shdw_as_routes_to_an_alias() {
+        let sandbox = Sandbox::new(
+            "[aliases.tool]\ncommand = \"echo tool\"\n\n[aliases.leak]\ncommand = \"printenv SHDW_AS\"\n",
+        );
+        let output = sandbox
+            .shdw()
+            .env("SHDW_AS", "tool")
+            .arg("x")
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "tool x\n");
+
+        // The alias' command doesn't see it.
+        let status = sandbox.shdw().env("SHDW_AS", "leak").status().unwrap();
+        assert_eq!(status.code(), Some(COMMAND_FAILED));
+    }
+
+    #[test]
+    fn test_help_banner() {
+        let config = "[settings]\nhelp_banner = true\n\n[aliases.tool]\ncommand = \"echo\"\n";
+        let sandbox = Sandbox::new(config);
+        let shim = sandbox.shim("tool");
+        let output = sandbox.command(&shim, &[]).arg("--help").output().unwrap();
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("tool is shadowed by echo"), "{}", stderr);
+
+        let output = sandbox.command(&shim, &[]).arg("-x").output().unwrap();
+        assert!(output.stderr.is_empty());
+    }
+
+    #[test]
+    fn test_failure_exit_codes() {
+        let sandbox = Sandbox::new("[aliases.fail]\ncommand = \"false\"\n");
+        let shim = sandbox.shim("fail");
+        let status = sandbox.command(&shim, &[]).status().unwrap();
+        assert_eq!(status.code(), Some(COMMAND_FAILED));
+
+        write_config(
+            &sandbox,
+            "[settings.exit_codes]\ncommand_failed = 1\n\n[aliases.fail]\ncommand = \"false\"\n",
+        );
+        let status = sandbox.command(&shim, &[]).status().unwrap();
+        assert_eq!(status.code(), Some(1));
+    }
+
+    #[test]
+    fn test_shim_without_alias() {
+        let sandbox = Sandbox::new("");
+        let shim = sandbox.shim("ghost");
+        let output = sandbox.command(&shim, &[]).output().unwrap();
+        assert_eq!(output.status.code(), Some(COMMAND_NOT_FOUND));
+    }
+
+    #[test]
+    fn test_raw_layers() {
+        let sandbox = Sandbox::new(
+            "[settings]\nalways_use_raw = true\n\n[aliases.tool]\ncommand = \"echo replacement\"\n",
+        );
+        let elsewhere = sandbox.home().join("elsewhere");
+        sandbox.script(&elsewhere, "tool", "echo original");
+        let shim = sandbox.shim("tool");
+
+        let output = sandbox.command(&shim, &[&elsewhere]).output().unwrap();
+        assert_eq!(stdout(&output), "original\n");
+
+        // The environment comes before [settings].
+        let output = sandbox
+            .command(&shim, &[&elsewhere])
+            .env("SHDW_RAW", "0")
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "replacement\n");
+    }
+
+    #[test]
+    fn test_failure_footer() {
+        let sandbox = Sandbox::new(
+            "[settings]\nfailure_footer = true\n\n[aliases.tool]\ncommand = \"fails\"\n",
+        );
+        let elsewhere = sandbox.home().join("elsewhere");
+        sandbox.script(&elsewhere, "fails", "exit 2");
+        let shim = sandbox.shim("tool");
+        let output = sandbox.command(&shim, &[&elsewhere]).output().unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("shadowed tool"), "{}", stderr);
+        assert!(
+            stderr.contains("fails exited 2 (use --shdw-raw"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_shim_without_alias_passes_through() {
+        let sandbox = Sandbox::new("[settings]\non_missing_config = \"passthrough\"\n");
+        let elsewhere = sandbox.home().join("elsewhere");
+        sandbox.script(&elsewhere, "tool", "echo original \"$@\"\nexit 3");
+        let shim = sandbox.shim("tool");
+
+        let output = sandbox
+            .command(&shim, &[&elsewhere])
+            .args(["--raw", "arg"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "original --raw arg\n"
+        );
+    }
+
+    #[test]
+    fn test_add_creates_a_working_shim() {
+        let sandbox = Sandbox::new("");
+        let output = sandbox
+            .shdw()
+            .arg("add")
+            .arg("--bin-path")
+            .arg(sandbox.bin())
+            .args(["hi", "echo hi"])
+            .output()
+            .unwrap();
+        stdout(&output);
+        let output = sandbox.command("hi", &[]).arg("there").output().unwrap();
+        assert_eq!(stdout(&output), "hi there\n");
+    }
+
+    #[test]
+    fn test_failed_add_changes_nothing() {
+        let sandbox = Sandbox::new("");
+        let output = sandbox
+            .shdw()
+            .arg("add")
+            .arg("--bin-path")
+            .arg(sandbox.bin())
+            .args(["ls", "echo one"])
+            .output()
+            .unwrap();
+        stdout(&output);
+        let state_file = sandbox.home().join("state/state.toml");
+        let state = std::fs::read_to_string(&state_file).unwrap();
+
+        let other = sandbox.home().join("other");
+        let output = sandbox
+            .shdw()
+            .arg("add")
+            .arg("--bin-path")
+            .arg(&other)
+            .args(["ls", "echo two"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(129));
+        assert!(other.join("ls").symlink_metadata().is_err());
+        assert_eq!(std::fs::read_to_string(&state_file).unwrap(), state);
+        let output = sandbox.command("ls", &[]).output().unwrap();
+        assert_eq!(stdout(&output), "one\n");
+    }
+
+    #[test]
+    fn test_protected_commands_stay_unshadowed() {
+        let sandbox = Sandbox::new("");
+        let config = format!(
+            "[settings]\nbin_path = {:?}\nprotected = [\"ssh\", \"env\"]\n",
+            sandbox.bin()
+        );
+        std::fs::write(sandbox.config_dir().join("config.toml"), config).unwrap();
+        let aliases = sandbox.home().join("aliases.sh");
+        std::fs::write(&aliases, "alias ssh='mosh'\nalias ll='ls -l'\n").unwrap();
+        let output = sandbox
+            .shdw()
+            .arg("import")
+            .arg(&aliases)
+            .arg("--yes")
+            .output()
+            .unwrap();
+        let printed = stdout(&output);
+        assert!(printed.contains("Skipping") && printed.contains("ssh is protected"));
+
+        let theirs = sandbox.home().join("theirs.toml");
+        std::fs::write(&theirs, "[aliases.env]\ncommand = \"printenv\"\n").unwrap();
+        let output = sandbox.shdw().arg("merge").arg(&theirs).output().unwrap();
+        assert!(String::from_utf8_lossy(&output.stderr).contains("env is protected"));
+
+        let list = stdout(&sandbox.shdw().arg("list").output().unwrap());
+        assert!(list.contains("ll"), "{}", list);
+        assert!(!list.contains("ssh") && !list.contains("env"), "{}", list);
+        assert!(sandbox.bin().join("ll").symlink_metadata().is_ok());
+        assert!(sandbox.bin().join("ssh").symlink_metadata().is_err());
+        assert!(sandbox.bin().join("env").symlink_metadata().is_err());
+    }
+
+    #[test]
+    fn test_aliases_named_after_builtins() {
+        let sandbox = Sandbox::new("");
+        let add = |settings: &str, args: &[&str]| {
+            let config = format!("[settings]\nbin_path = {:?}\n{}", sandbox.bin(), settings);
+            write_config(&sandbox, &config);
+            sandbox
+                .shdw()
+                .env("SHELL", "/bin/bash")
+                .arg("add")
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        let output = add("", &["shopt", "echo shopt"]);
+        stdout(&output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("shopt is a bash builtin"), "{}", stderr);
+        assert!(sandbox.bin().join("shopt").symlink_metadata().is_ok());
+
+        let output = add("builtins = \"refuse\"\n", &["cd", "echo cd"]);
+        assert_eq!(output.status.code(), Some(64));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("cd() { echo cd \"$@\"; }"), "{}", stderr);
+        assert!(sandbox.bin().join("cd").symlink_metadata().is_err());
+
+        // Kept for `shdw run` only, it shadows nothing.
+        stdout(&add(
+            "builtins = \"refuse\"\n",
+            &["cd", "echo cd", "--no-link"],
+        ));
+        let list = stdout(&sandbox.shdw().arg("list").output().unwrap());
+        assert!(list.contains("cd → echo cd [not linked]"), "{}", list);
+
+        let output = add("builtins = \"allow\"\n", &["pwd", "echo pwd"]);
+        stdout(&output);
+        assert!(output.stderr.is_empty());
+    }
+
+    #[test]
+    fn test_archive_import_checks_like_import() {
+        let there = Sandbox::new(
+            "[aliases.ll]\ncommand = \"ls -l\"\n\n\
+             [aliases.ssh]\ncommand = \"ls\"\n\n\
+             [aliases.\"a/b\"]\ncommand = \"ls\"\n\n\
+             [aliases.up]\ncommand = \"cd ..\"\n",
+        );
+        let archive = there.home().join("aliases.tar.gz");
+        let output = there
+            .shdw()
+            .arg("export")
+            .arg("--archive")
+            .arg(&archive)
+            .output()
+            .unwrap();
+        stdout(&output);
+
+        let sandbox = Sandbox::new("");
+        let config = format!(
+            "[settings]\nbin_path = {:?}\nprotected = [\"ssh\"]\n",
+            sandbox.bin()
+        );
+        std::fs::write(sandbox.config_dir().join("config.toml"), config).unwrap();
+        let output = sandbox
+            .shdw()
+            .arg("import")
+            .arg("--archive")
+            .arg(&archive)
+            .arg("--yes")
+            .output()
+            .unwrap();
+        let printed = stdout(&output);
+        assert!(printed.contains("ssh is protected"), "{}", printed);
+        assert!(printed.contains("Invalid alias name: 'a/b'"), "{}", printed);
+        assert!(
+            printed.contains("up runs the shell builtin cd"),
+            "{}",
+            printed
+        );
+        assert!(printed.contains("Added alias: ll"), "{}", printed);
+        assert!(sandbox.bin().join("ssh").symlink_metadata().is_err());
+        let list = stdout(&sandbox.shdw().arg("list").output().unwrap());
+        assert!(!list.contains("ssh") && !list.contains("up"), "{}", list);
+    }
+
+    #[test]
+    fn test_daemon_answers_only_for_its_config() {
+        let sandbox = Sandbox::new(
+            "[subscriptions]\nteam = \"https://example.invalid/team.toml\"\n\n\
+             [aliases.greet]\ncommand = \"echo mine\"\n",
+        );
+        let subscribed = sandbox.home().join("state/subscriptions/team.toml");
+        std::fs::create_dir_all(subscribed.parent().unwrap()).unwrap();
+        std::fs::write(&subscribed, "[aliases.hey]\ncommand = \"echo theirs\"\n").unwrap();
+        let greet = sandbox.shim("greet");
+        let hey = sandbox.shim("hey");
+        let mut daemon = sandbox.shdw().arg("daemon").spawn().unwrap();
+        let socket = sandbox.home().join("state/daemon.sock");
+        for _ in 0..100 {
+            if socket.exists() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        let run = |shim: &std::path::Path, config: Option<&std::path::Path>| {
+            let mut command = sandbox.command(shim, &[]);
+            command.env("SHDW_DEBUG", "1");
+            if let Some(config) = config {
+                command.env("SHDW_CONFIG", config);
+            }
+            let output = command.output().unwrap();
+            let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+            (stdout(&output), stderr)
+        };
+
+        let elsewhere = sandbox.home().join("elsewhere.toml");
+        std::fs::write(
+            &elsewhere,
+            "[aliases.greet]\ncommand = \"echo elsewhere\"\n",
+        )
+        .unwrap();
+        let ours = run(&greet, None);
+        let theirs = run(&greet, Some(&elsewhere));
+        // Edits reach shims before the daemon gets round to reloading.
+        let before = run(&hey, None);
+        std::fs::write(&subscribed, "[aliases.hey]\ncommand = \"echo updated\"\n").unwrap();
+        let after = run(&hey, None);
+        daemon.kill().unwrap();
+        daemon.wait().unwrap();
+
+        assert_eq!(ours.0, "mine\n");
+        assert!(ours.1.contains("dispatched from the daemon"), "{}", ours.1);
+        assert_eq!(theirs.0, "elsewhere\n");
+        assert!(
+            !theirs.1.contains("dispatched from the daemon"),
+            "{}",
+            theirs.1
+        );
+        assert_eq!(before.0, "theirs\n");
+        assert_eq!(after.0, "updated\n");
+    }
+
+    #[test]
+    fn test_project_aliases_layer_over_your_own() {
+        let sandbox = Sandbox::new(
+            "[aliases.ll]\ncommand = \"echo global ll\"\n\n\
+             [aliases.ls]\ncommand = \"echo global ls\"\n",
+        );
+        let project = sandbox.home().join("proj/.shdw/config.toml");
+        std::fs::create_dir_all(project.parent().unwrap()).unwrap();
+        std::fs::write(
+            &project,
+            "[aliases.ls]\ncommand = \"echo project ls\"\n\n\
+             [aliases.mk]\ncommand = \"echo project mk\"\n",
+        )
+        .unwrap();
+        let ll = sandbox.shim("ll");
+        let ls = sandbox.shim("ls");
+        let in_project = |program: &std::path::Path| {
+            let mut command = sandbox.command(program, &[]);
+            command.env("SHDW_PROJECT_CONFIG", &project);
+            command
+        };
+
+        let output = in_project(std::path::Path::new(env!("CARGO_BIN_EXE_shdw")))
+            .args(["sync", "--project"])
+            .output()
+            .unwrap();
+        assert!(stdout(&output).contains("Linked 2 aliases"));
+        let mk = sandbox.home().join("proj/.shdw/bin/mk");
+        assert_eq!(stdout(&in_project(&mk).output().unwrap()), "project mk\n");
+        assert_eq!(stdout(&in_project(&ll).output().unwrap()), "global ll\n");
+        assert_eq!(stdout(&in_project(&ls).output().unwrap()), "project ls\n");
+
+        // Saving your config from inside the project keeps your own ls.
+        let output = in_project(std::path::Path::new(env!("CARGO_BIN_EXE_shdw")))
+            .args(["add", "--no-link", "hi", "echo hi"])
+            .output()
+            .unwrap();
+        stdout(&output);
+        let saved = std::fs::read_to_string(sandbox.config_dir().join("config.toml")).unwrap();
+        assert!(saved.contains("echo global ls"), "{}", saved);
+        assert!(!saved.contains("project"), "{}", saved);
+        assert_eq!(
+            stdout(&sandbox.command(&ls, &[]).output().unwrap()),
+            "global ls\n"
+        );
+
+        // A project alias is the project's to remove, and stays linked.
+        let remove = |name: &str| {
+            in_project(std::path::Path::new(env!("CARGO_BIN_EXE_shdw")))
+                .args(["remove", name])
+                .output()
+                .unwrap()
+        };
+        let output = remove("mk");
+        assert_eq!(output.status.code(), Some(64));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("remove it there"));
+        assert!(mk.symlink_metadata().is_ok());
+        // Removing ls there removes your own, beneath the project's.
+        stdout(&remove("ls"));
+        assert!(ls.symlink_metadata().is_ok());
+        assert_eq!(stdout(&in_project(&ls).output().unwrap()), "project ls\n");
+        let saved = std::fs::read_to_string(sandbox.config_dir().join("config.toml")).unwrap();
+        assert!(
+            !saved.contains("global ls") && saved.contains("global ll"),
+            "{}",
+            saved
+        );
+    }
+
+    #[test]
+    fn test_add_from_stdin() {
+        use std::io::Write;
+        use std::process::Stdio;
+
+        let sandbox = Sandbox::new("");
+        let mut child = sandbox
+            .shdw()
+            .arg("add")
+            .arg("--bin-path")
+            .arg(sandbox.bin())
+            .args(["say", "--from-stdin"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"echo \"$HOME\" it's\n")
+            .unwrap();
+        assert!(child.wait().unwrap().success());
+        let output = sandbox.command("say", &[]).arg("a").output().unwrap();
+        assert_eq!(stdout(&output), "\"$HOME\" it's a\n");
+    }
+
+    #[test]
+    fn test_deny_warnings() {
+        let sandbox = Sandbox::new("");
+        let elsewhere = sandbox.home().join("elsewhere");
+        let add = |name: &str, deny: bool| {
+            let mut command = sandbox.shdw();
+            command.arg("add").arg("--bin-path").arg(&elsewhere);
+            if deny {
+                command.arg("--deny-warnings");
+            }
+            command.args([name, "echo hi"]).output().unwrap()
+        };
+
+        let output = add("hi", false);
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("is not on PATH"), "{}", stderr);
+
+        let output = add("hey", true);
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("--deny-warnings"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_expand_first_word() {
+        let sandbox = Sandbox::new("[aliases.gs]\ncommand = \"git status\"\n");
+        let output = sandbox
+            .shdw()
+            .args(["expand", "--cursor", "2", "--", "gs -s"])
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "git status -s\n10\n");
+
+        let output = sandbox
+            .shdw()
+            .args(["expand", "--", "ls -l"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ls -l\n");
+    }
+
+    #[test]
+    fn test_list_tree() {
+        let sandbox = Sandbox::new(
+            "[aliases]\nl = { command = \"eza\" }\nll = { extends = \"l\", args = \"-l\" }\n",
+        );
+        let output = sandbox
+            .shdw()
+            .args(["list", "--tree"])
+            .env("SHDW_ASCII", "1")
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "l -> eza\n`-- ll -> eza -l\n");
+    }
+
+    #[test]
+    fn test_doctor_over_ssh() {
+        let sandbox = Sandbox::new("[aliases.tool]\ncommand = \"no-such-replacement\"\n");
+        sandbox.shim("shdw");
+        // Runs the remote command here: ssh -o BatchMode=yes -- <host> <command>
+        let fake = sandbox.home().join("fake");
+        sandbox.script(&fake, "ssh", "shift 4\nexec sh -c \"$1\"");
+
+        let output = sandbox
+            .command(env!("CARGO_BIN_EXE_shdw"), &[&fake])
+            .args(["doctor", "--host", "devbox"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let report = String::from_utf8_lossy(&output.stdout);
+        assert!(report.starts_with("devbox\n"), "{}", report);
+        assert!(
+            report.contains("replacement 'no-such-replacement' is not installed"),
+            "{}",
+            report
+        );
+        assert!(report.contains(" no-such-replacement\n"), "{}", report);
+
+        // The same JSON whatever either end's terminal can show.
+        let json = |ascii: &str| {
+            let output = sandbox
+                .command(env!("CARGO_BIN_EXE_shdw"), &[&fake])
+                .args(["doctor", "--host", "devbox", "--format", "json"])
+                .env("SHDW_ASCII", ascii)
+                .env("LANG", "C.UTF-8")
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        let report = json("1");
+        assert!(report.contains("\"name\": \"tool\""), "{}", report);
+        assert!(
+            report.contains("\"command\": \"no-such-replacement\""),
+            "{}",
+            report
+        );
+        assert!(!report.contains("->"), "{}", report);
+        assert_eq!(json("0"), report);
+    }
+
+    #[test]
+    fn test_run_capture_as_json() {
+        let sandbox = Sandbox::new("[aliases.hi]\ncommand = \"echo hi\"\nlink = false\n");
+        let output = sandbox
+            .shdw()
+            .args(["run", "hi", "--capture", "--format", "json", "--", "there"])
+            .output()
+            .unwrap();
+        let report = stdout(&output);
+        assert!(report.contains("\"stdout\": \"hi there\\n\""), "{}", report);
+        assert!(report.contains("\"exit_code\": 0"), "{}", report);
+        assert!(report.contains("\"duration_ms\": "), "{}", report);
+    }
+
+    #[test]
+    fn test_aliases_running_each_other_stop() {
+        let sandbox = Sandbox::new(
+            "[aliases.ping]\ncommand = \"pong\"\n[aliases.pong]\ncommand = \"ping\"\n",
+        );
+        sandbox.shim("pong");
+        let shim = sandbox.shim("ping");
+        let output = sandbox.command(&shim, &[]).output().unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("ping -> pong -> ping -> pong -> ping -> pong -> ping -> pong -> ping"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_inherit_stdin_false() {
+        let sandbox = Sandbox::new("[aliases.slurp]\ncommand = \"cat\"\ninherit_stdin = false\n");
+        let input = sandbox.home().join("input");
+        std::fs::write(&input, "piped in\n").unwrap();
+        let shim = sandbox.shim("slurp");
+        let output = sandbox
+            .command(&shim, &[])
+            .stdin(std::fs::File::open(&input).unwrap())
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "");
+    }
+
+    #[test]
+    fn test_detach_returns_before_the_command_finishes() {
+        let sandbox = Sandbox::new("[aliases.bg]\ncommand = \"slow\"\ndetach = true\n");
+        let elsewhere = sandbox.home().join("elsewhere");
+        let finished = sandbox.home().join("finished");
+        sandbox.script(
+            &elsewhere,
+            "slow",
+            &format!(
+                "echo \"out $*\"; echo err >&2; sleep 1; touch '{}'",
+                finished.display()
+            ),
+        );
+        let shim = sandbox.shim("bg");
+
+        let output = sandbox
+            .command(&shim, &[&elsewhere])
+            .arg("x")
+            .output()
+            .unwrap();
+        assert_eq!(stdout(&output), "");
+        assert!(!finished.exists());
+        for _ in 0..100 {
+            if finished.exists() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        assert!(finished.exists());
+        let log = std::fs::read_to_string(sandbox.home().join("state/logs/bg.log")).unwrap();
+        assert_eq!(log, "out x\nerr\n");
+    }
+
+    #[test]
+    fn test_output_redirection() {
+        let sandbox = Sandbox::new(
+            "[aliases.appends]\ncommand = \"both\"\nstdout = \"~/out.log\"\nstderr = \"null\"\n\n\
+             [aliases.truncates]\ncommand = \"both\"\n\
+             stderr = { path = \"~/logs/err.log\", append = false }\n",
+        );
+        let elsewhere = sandbox.home().join("elsewhere");
+        sandbox.script(&elsewhere, "both", "echo \"out $1\"; echo \"err $1\" >&2");
+        let appends = sandbox.shim("appends");
+        let truncates = sandbox.shim("truncates");
+        let run = |shim: &std::path::Path, arg: &str| {
+            sandbox
+                .command(shim, &[&elsewhere])
+                .arg(arg)
+                .output()
+                .unwrap()
+        };
+
+        for arg in ["1", "2"] {
+            let output = run(&appends, arg);
+            assert_eq!(stdout(&output), "");
+            assert!(output.stderr.is_empty());
+        }
+        let out = std::fs::read_to_string(sandbox.home().join("out.log")).unwrap();
+        assert_eq!(out, "out 1\nout 2\n");
+
+        for arg in ["1", "2"] {
+            let output = run(&truncates, arg);
+            assert_eq!(stdout(&output), format!("out {}\n", arg));
+            assert!(output.stderr.is_empty());
+        }
+        let err = std::fs::read_to_string(sandbox.home().join("logs/err.log")).unwrap();
+        assert_eq!(err, "err 2\n");
+    }
+
+    #[test]
+    fn test_grep() {
+        let sandbox = Sandbox::new(
+            "[aliases.gh]\ncommand = \"gh\"\ndescription = \"GitHub CLI\"\n\
+             env = { GH_TOKEN = { from = \"command:pass show github/token\" }, GH_PAGER = \"less\" }\n\n\
+             [aliases.grep]\ncommand = \"rg --smart-case\"\n",
+        );
+        let grep = |args: &[&str]| sandbox.shdw().arg("grep").args(args).output().unwrap();
+
+        assert_eq!(
+            stdout(&grep(&["g[hr]"])),
+            "gh: name: gh\ngh: command: gh\ngrep: name: grep\n"
+        );
+        // Secrets are searched by where they come from, never fetched.
+        assert_eq!(
+            stdout(&grep(&["-i", "GITHUB"])),
+            "gh: description: GitHub CLI\n\
+             gh: env GH_TOKEN: GH_TOKEN=from command:pass show github/token\n"
+        );
+        assert_eq!(grep(&["GITHUB"]).status.code(), Some(1));
+        assert_eq!(grep(&["("]).status.code(), Some(64));
+    }
+
+    #[test]
+    fn test_merge() {
+        let sandbox = Sandbox::new("");
+        write_config(
+            &sandbox,
+            &format!(
+                "[settings]\nbin_path = {:?}\n\n\
+                 [aliases.ll]\ncommand = \"ls -l\"\n\n\
+                 [aliases.gs]\ncommand = \"git status\"\n",
+                sandbox.bin()
+            ),
+        );
+        let theirs = sandbox.home().join("theirs.toml");
+        std::fs::write(
+            &theirs,
+            "[aliases.ll]\ncommand = \"ls -la\"\n\n[aliases.up]\ncommand = \"cd ..\"\n",
+        )
+        .unwrap();
+        let merge = |args: &[&str]| {
+            let output = sandbox
+                .shdw()
+                .arg("merge")
+                .arg(&theirs)
+                .args(args)
+                .output()
+                .unwrap();
+            stdout(&output)
+        };
+        let list = || stdout(&sandbox.shdw().arg("list").output().unwrap());
+
+        let before = list();
+        let printed = merge(&["--dry-run"]);
+        assert!(printed.contains("+ up → cd .."), "{}", printed);
+        assert!(printed.contains("! ll differs:"), "{}", printed);
+        assert_eq!(list(), before);
+
+        // Without a terminal to ask on, conflicts keep yours.
+        let printed = merge(&[]);
+        assert!(printed.contains("Kept 1 conflicting alias"), "{}", printed);
+        assert!(printed.contains("Added alias: up"), "{}", printed);
+        let merged = list();
+        assert!(merged.contains("ll → ls -l\n"), "{}", merged);
+        assert!(merged.contains("gs → git status"), "{}", merged);
+        assert!(merged.contains("up → cd .."), "{}", merged);
+
+        let printed = merge(&["--on-conflict", "theirs", "--link"]);
+        assert!(printed.contains("Replaced alias: ll"), "{}", printed);
+        assert!(list().contains("ll → ls -la"));
+        assert!(sandbox.bin().join("ll").symlink_metadata().is_ok());
+        assert_eq!(merge(&[]), "Nothing to merge\n");
+    }
+
+    #[test]
+    fn test_uninstall() {
+        let sandbox = Sandbox::new("");
+        write_config(
+            &sandbox,
+            &format!("[settings]\nbin_path = {:?}\n", sandbox.bin()),
+        );
+        for (name, command) in [("ll", "ls -l"), ("gs", "git status")] {
+            stdout(
+                &sandbox
+                    .shdw()
+                    .args(["add", name, command])
+                    .output()
+                    .unwrap(),
+            );
+        }
+        // Someone else's file where gs' link was, and one shdw never knew.
+        let gs = sandbox.bin().join("gs");
+        std::fs::remove_file(&gs).unwrap();
+        std::fs::write(&gs, "mine").unwrap();
+        let other = sandbox.bin().join("other");
+        std::fs::write(&other, "mine").unwrap();
+        let ll = sandbox.bin().join("ll");
+        let config = sandbox.config_dir().join("config.toml");
+        let state = sandbox.home().join("state");
+        let uninstall =
+            |args: &[&str]| sandbox.shdw().arg("uninstall").args(args).output().unwrap();
+
+        let output = uninstall(&["--dry-run", "--purge"]);
+        let printed = stdout(&output);
+        assert!(
+            printed.contains(&format!("Would remove {}", ll.display())),
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains(&format!("Would remove {}", config.display())),
+            "{}",
+            printed
+        );
+        assert!(ll.symlink_metadata().is_ok() && config.exists());
+
+        // Purging asks first, and there's no one to say yes.
+        let output = uninstall(&["--purge"]);
+        let printed = stdout(&output);
+        assert!(
+            printed.contains(&format!("Removed {}", ll.display())),
+            "{}",
+            printed
+        );
+        assert!(printed.contains("Keeping config and state"), "{}", printed);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("leaving {}", gs.display())),
+            "{}",
+            stderr
+        );
+        assert!(ll.symlink_metadata().is_err());
+        assert_eq!(std::fs::read_to_string(&gs).unwrap(), "mine");
+        assert_eq!(std::fs::read_to_string(&other).unwrap(), "mine");
+        assert!(config.exists() && state.exists());
+
+        stdout(&uninstall(&["--purge", "--yes"]));
+        assert!(!config.exists() && !state.exists());
+        assert!(gs.exists() && other.exists());
+    }
+
+    #[test]
+    fn test_on_missing_hook() {
+        let sandbox = Sandbox::new("");
+        let log = sandbox.home().join("hook.log");
+        write_config(
+            &sandbox,
+            &format!(
+                "[settings.hooks]\non_missing = 'echo \"$SHDW_EVENT $SHDW_ALIAS\" >> {}'\n\n\
+                 [aliases.gone]\ncommand = \"shdw-test-not-installed\"\n",
+                log.display()
+            ),
+        );
+        let shim = sandbox.shim("gone");
+        // Loading the config, then from the cache.
+        for _ in 0..2 {
+            let status = sandbox.command(&shim, &[]).status().unwrap();
+            assert_eq!(status.code(), Some(COMMAND_FAILED));
+        }
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "missing gone\nmissing gone\n"
+        );
+    }
+
+    #[test]
+    fn test_url_hooks_are_posted() {
+        let sandbox = Sandbox::new("");
+        write_config(
+            &sandbox,
+            "[settings.hooks]\non_add = \"https://hooks.example.invalid/shdw\"\n\
+             on_remove = \"https://hooks.example.invalid/fails\"\n",
+        );
+        // A curl that notes what it was asked to send, and fails for one URL.
+        let fake = sandbox.home().join("fake");
+        let posted = sandbox.home().join("posted");
+        sandbox.script(
+            &fake,
+            "curl",
+            &format!(
+                "for arg; do echo \"$arg\"; done >> '{}'\ncase \"$*\" in *fails) exit 22;; esac",
+                posted.display()
+            ),
+        );
+        let shdw = |args: &[&str]| {
+            sandbox
+                .command(env!("CARGO_BIN_EXE_shdw"), &[&fake])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        stdout(&shdw(&["add", "ll", "ls -l", "--no-link"]));
+        let sent = std::fs::read_to_string(&posted).unwrap();
+        let sent: Vec<&str> = sent.lines().collect();
+        let body = sent.iter().position(|arg| *arg == "--data-binary").unwrap();
+        assert_eq!(
+            sent[body + 1..],
+            [
+                r#"{"event":"add","aliases":["ll"]}"#,
+                "https://hooks.example.invalid/shdw"
+            ]
+        );
+        assert!(sent.contains(&"Content-Type: application/json"));
+
+        let output = shdw(&["remove", "ll"]);
+        stdout(&output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("the on_remove hook exited with"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_refused_remove_keeps_the_link() {
+        let sandbox = Sandbox::new("");
+        write_config(
+            &sandbox,
+            &format!(
+                "[settings]\nbin_path = {:?}\n\n\
+                 [subscriptions]\nteam = \"https://example.invalid/team.toml\"\n",
+                sandbox.bin()
+            ),
+        );
+        let subscribed = sandbox.home().join("state/subscriptions/team.toml");
+        std::fs::create_dir_all(subscribed.parent().unwrap()).unwrap();
+        std::fs::write(&subscribed, "[aliases.gs]\ncommand = \"echo status\"\n").unwrap();
+        stdout(&sandbox.shdw().arg("bootstrap").output().unwrap());
+        let gs = sandbox.bin().join("gs");
+        assert!(gs.symlink_metadata().is_ok());
+
+        let output = sandbox.shdw().args(["remove", "gs"]).output().unwrap();
+        assert_eq!(output.status.code(), Some(64));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("comes from subscription team"),
+            "{}",
+            stderr
+        );
+        assert!(gs.symlink_metadata().is_ok());
+        assert_eq!(
+            stdout(&sandbox.command(&gs, &[]).output().unwrap()),
+            "status\n"
+        );
+    }
+
+    #[test]
+    fn test_json_log_format() {
+        let sandbox = Sandbox::new("[settings.exit_codes]\ncommand_failed = 300\n");
+        let output = sandbox
+            .shdw()
+            .args(["--log-format", "json", "bootstrap"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(stderr.lines().count(), 1, "{}", stderr);
+        assert!(stderr.starts_with("{\"ts\":"), "{}", stderr);
+        assert!(
+            stderr.contains("Config is invalid:\\n  Exit code"),
+            "{}",
+            stderr
+        );
+    }
+
+    fn write_config(sandbox: &Sandbox, config: &str) {
+        std::fs::write(sandbox.config_dir().join("config.toml"), config).unwrap();
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+
+    #[test]
+    fn test_exe_shim_dispatches_on_its_stem() {
+        let sandbox = Sandbox::new("[aliases.greet]\ncommand = \"cmd /C echo hello\"\n");
+        let shim = sandbox.shim("greet");
+        let output = sandbox.command(&shim, &[]).output().unwrap();
+        assert!(stdout(&output).starts_with("hello"));
+    }
+
+    #[test]
+    fn test_exe_shim_without_alias() {
+        let sandbox = Sandbox::new("");
+        let shim = sandbox.shim("ghost");
+        let status = sandbox.command(&shim, &[]).status().unwrap();
+        assert_eq!(status.code(), Some(COMMAND_NOT_FOUND));
+    }
+}
+
+#[test]
+fn test_shdw_itself_is_not_dispatched() {
+    let sandbox = Sandbox::new("");
+    let output = sandbox.shdw().arg("--version").output().unwrap();
+    assert!(stdout(&output).contains(env!("CARGO_PKG_VERSION")));
+}
@@ -0,0 +1,166 @@
+#![cfg(unix)]
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const SHIM: &str = "jobtest";
+
+/// A throwaway HOME with a config defining `jobtest = "sh -c"` and a symlink
+/// shim pointing at the freshly built binary.
+fn setup() -> (TempDir, PathBuf) {
+    let home = tempfile::tempdir().unwrap();
+    let config_dir = home.path().join(".config/shdw");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("config.toml"),
+        format!("[aliases.{}]\ncommand = \"sh -c\"\n", SHIM),
+    )
+    .unwrap();
+
+    let bin = home.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let shim = bin.join(SHIM);
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_shdw"), &shim).unwrap();
+    (home, shim)
+}
+
+fn shim_command(home: &Path, shim: &Path, script: &str) -> Command {
+    let mut command = Command::new(shim);
+    command
+        .arg(script)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"));
+    command
+}
+
+fn wait_timeout(child: &mut std::process::Child, timeout: Duration) -> ExitStatus {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            return status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("shim did not exit within {:?}", timeout);
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn openpty() -> (RawFd, RawFd) {
+    let (mut master, mut slave) = (0, 0);
+    // SAFETY: both out-pointers are valid; the optional arguments are null.
+    let rc = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    assert_eq!(rc, 0, "openpty failed: {}", std::io::Error::last_os_error());
+    (master, slave)
+}
+
+#[test]
+fn test_ctrl_c_is_left_to_the_child() {
+    let (home, shim) = setup();
+    let (master, slave) = openpty();
+
+    let mut command = shim_command(
+        home.path(),
+        &shim,
+        "trap 'echo caught; exit 5' INT; echo ready; for _ in $(seq 200); do sleep 0.05; done",
+    );
+    // SAFETY: each dup returns a fresh descriptor owned by the Stdio.
+    unsafe {
+        command
+            .stdin(Stdio::from_raw_fd(libc::dup(slave)))
+            .stdout(Stdio::from_raw_fd(libc::dup(slave)))
+            .stderr(Stdio::from_raw_fd(libc::dup(slave)))
+            .pre_exec(|| {
+                // Become a session leader with the pty as controlling
+                // terminal, like a login shell running the shim in the
+                // foreground.
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+    }
+    let mut child = command.spawn().unwrap();
+    // SAFETY: `slave` is no longer needed in the parent.
+    unsafe { libc::close(slave) };
+
+    // SAFETY: `master` is owned by this File from here on.
+    let mut terminal = unsafe { File::from_raw_fd(master) };
+    let mut reader = terminal.try_clone().unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0u8; 256];
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut output = String::new();
+    let read_until = |output: &mut String, needle: &str| {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !output.contains(needle) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(left) {
+                Ok(chunk) => output.push_str(&String::from_utf8_lossy(&chunk)),
+                Err(_) => panic!("never saw {:?} in {:?}", needle, output),
+            }
+        }
+    };
+
+    read_until(&mut output, "ready");
+    terminal.write_all(b"\x03").unwrap();
+    read_until(&mut output, "caught");
+
+    let status = wait_timeout(&mut child, Duration::from_secs(10));
+    assert_eq!(status.signal(), None, "shim was killed by Ctrl-C");
+    assert_eq!(status.code(), Some(128));
+}
+
+#[test]
+fn test_child_signal_death_is_propagated() {
+    let (home, shim) = setup();
+    let mut child = shim_command(home.path(), &shim, "kill -TERM $$")
+        .spawn()
+        .unwrap();
+
+    let status = wait_timeout(&mut child, Duration::from_secs(10));
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+}
+
+#[test]
+fn test_sigterm_to_shim_is_forwarded() {
+    let (home, shim) = setup();
+    let marker = home.path().join("terminated");
+    let script = format!(
+        "trap 'touch {}; exit 3' TERM; for _ in $(seq 200); do sleep 0.05; done",
+        marker.display()
+    );
+    let mut child = shim_command(home.path(), &shim, &script).spawn().unwrap();
+
+    thread::sleep(Duration::from_millis(300));
+    // SAFETY: sending a signal to our own child.
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+
+    let status = wait_timeout(&mut child, Duration::from_secs(10));
+    assert!(marker.exists(), "SIGTERM never reached the replacement");
+    assert_eq!(status.code(), Some(128));
+}